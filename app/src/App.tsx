@@ -4,6 +4,7 @@ import MainPanel from './components/MainPanel.tsx'
 import TopBar from './components/TopBar.tsx'
 import { ModeSelectionWizard } from './components/ModeSelectionWizard.tsx'
 import { NotificationContainer } from './components/Notifications.tsx'
+import { SamplingApproval } from './components/SamplingApproval.tsx'
 import ModelsRoute from './routes/models'
 import SettingsRoute from './routes/settings'
 import MonitoringDashboard from './components/MonitoringDashboard.tsx'
@@ -50,6 +51,9 @@ function App() {
 
       {/* Notifications */}
       <NotificationContainer />
+
+      {/* MCP sampling requests waiting for the user */}
+      <SamplingApproval />
     </div>
   )
 }
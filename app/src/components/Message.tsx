@@ -1,4 +1,4 @@
-import { User, Copy, Check, Pencil, Wrench, Loader2 } from 'lucide-react'
+import { User, Copy, Check, Pencil, Wrench, Loader2, Play, RotateCcw } from 'lucide-react'
 import { useState, memo } from 'react'
 import { useChatStore, type ChatMessage } from '../store/chatStore'
 import Markdown from '../lib/markdown'
@@ -13,6 +13,8 @@ function Message({ message }: MessageProps) {
   const [isEditing, setIsEditing] = useState(false)
   const [editContent, setEditContent] = useState(message.content)
   const editUserMessage = useChatStore(state => state.editUserMessage)
+  const resumeMessage = useChatStore(state => state.resumeMessage)
+  const isLast = useChatStore(state => state.messages[state.messages.length - 1]?.id === message.id)
 
   const copyToClipboard = async () => {
     try {
@@ -51,6 +53,9 @@ function Message({ message }: MessageProps) {
   }
 
   const isUser = message.role === 'user'
+  // A reply cut short by a quit, a cancel or an error can be picked up again
+  const isUnfinished = !isUser && isLast && !message.isStreaming
+    && ['interrupted', 'cancelled', 'failed'].includes(message.status ?? '')
 
   return (
     <div className={`w-full flex items-start gap-4 py-5 px-1 ${isUser ? 'bg-gray-50/30' : 'bg-white'} group border-b border-gray-100/40 last:border-b-0`}>
@@ -188,6 +193,26 @@ function Message({ message }: MessageProps) {
               </button>
             )}
 
+            {/* Resume / regenerate (unfinished replies only) */}
+            {isUnfinished && message.content && (
+              <button
+                onClick={() => resumeMessage(message.id)}
+                className="p-1.5 text-gray-400 hover:text-blue-600 hover:bg-blue-50 rounded-lg transition-all"
+                title="Continue reply"
+              >
+                <Play size={14} />
+              </button>
+            )}
+            {isUnfinished && (
+              <button
+                onClick={() => resumeMessage(message.id, true)}
+                className="p-1.5 text-gray-400 hover:text-blue-600 hover:bg-blue-50 rounded-lg transition-all"
+                title="Regenerate reply"
+              >
+                <RotateCcw size={14} />
+              </button>
+            )}
+
             {/* Copy button */}
             {!isUser && !isEditing && message.content && (
               <button
@@ -209,6 +234,7 @@ export default memo(Message, (prev, next) => {
   if (prev.message.id !== next.message.id) return false
   if (prev.message.content !== next.message.content) return false
   if (prev.message.isStreaming !== next.message.isStreaming) return false
+  if (prev.message.status !== next.message.status) return false
   if (prev.message.toolCalls !== next.message.toolCalls) return false
   return true
 })
\ No newline at end of file
@@ -0,0 +1,73 @@
+import { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import Dialog from './Dialog'
+
+interface SamplingRequest {
+  id: string
+  server: string
+  provider: string
+  model: string
+  system_prompt?: string | null
+  messages: { role: string; content: { type: string; text?: string } }[]
+  max_tokens: number
+}
+
+const PREVIEW_LENGTH = 300
+
+function preview(request: SamplingRequest): string {
+  const last = [...request.messages].reverse().find(m => m.content.type === 'text')
+  const text = last?.content.text?.trim() ?? ''
+  return text.length > PREVIEW_LENGTH ? `${text.slice(0, PREVIEW_LENGTH)}…` : text
+}
+
+// Asks the user to approve MCP sampling requests (a server asking one of our models for a completion)
+export function SamplingApproval() {
+  const [queue, setQueue] = useState<SamplingRequest[]>([])
+
+  useEffect(() => {
+    const unlistenRequest = listen<SamplingRequest>('mcp:sampling-request', (e) => {
+      setQueue(q => [...q, e.payload])
+    })
+    // Timed out, or its server went away
+    const unlistenClosed = listen<{ id: string }>('mcp:sampling-request-closed', (e) => {
+      setQueue(q => q.filter(r => r.id !== e.payload.id))
+    })
+    return () => {
+      unlistenRequest.then(fn => fn())
+      unlistenClosed.then(fn => fn())
+    }
+  }, [])
+
+  const current = queue[0]
+  // Still shown while the dialog fades out
+  const last = useRef<SamplingRequest | null>(null)
+  if (current) last.current = current
+  const shown = current ?? last.current
+
+  const respond = async (approved: boolean) => {
+    if (!current) return
+    setQueue(q => q.slice(1))
+    try {
+      // False when the request was closed on the backend meanwhile
+      await invoke<boolean>('mcp_sampling_respond', { id: current.id, approved })
+    } catch (e) {
+      console.error('Failed to answer sampling request:', e)
+    }
+  }
+
+  const text = shown ? preview(shown) : ''
+  return (
+    <Dialog
+      isOpen={!!current}
+      title={shown ? `${shown.server} wants to use ${shown.model}` : ''}
+      description={shown
+        ? `The MCP server asks ${shown.provider} for a reply of up to ${shown.max_tokens} tokens${text ? `: "${text}"` : '.'}`
+        : ''}
+      confirmLabel="Allow"
+      cancelLabel="Deny"
+      onConfirm={() => respond(true)}
+      onCancel={() => respond(false)}
+    />
+  )
+}
@@ -19,6 +19,19 @@ export interface ChatMessage {
   toolCalls?: ToolCallState[]
   timestamp: number
   isStreaming?: boolean
+  /** Stored status of an assistant reply, e.g. `interrupted` when the app quit while it streamed */
+  status?: string
+}
+
+/** A stored message row as returned by the backend */
+interface MessageRow {
+  id: string
+  created_at: number
+}
+
+interface ChatStreamResponse {
+  stream_id?: string
+  user_message?: MessageRow | null
 }
 
 export interface ChatOptions {
@@ -36,6 +49,8 @@ interface ChatState {
   streamingMessageId: string | null
   currentStreamId: string | null  // Track current stream ID
   currentSystemPrompt: string | null
+  /** Background runs across all chats */
+  runs: ChatRunInfo[]
 
   // Actions
   setCurrentModel: (model: string) => void
@@ -44,17 +59,254 @@ interface ChatState {
   loadChat: (chatId: string, systemPrompt?: string | null) => Promise<boolean>
   addMessage: (message: Omit<ChatMessage, 'id' | 'timestamp'>) => string
   updateMessage: (id: string, content: string) => void
+  adoptStoredRow: (id: string, row: MessageRow) => void
   updateStreamingMessage: (id: string, content: string) => void
   updateMessageToolCalls: (id: string, toolCall: ToolCallState) => void
   markToolCallsDone: (id: string) => void
   setStreaming: (isStreaming: boolean, messageId?: string, streamId?: string) => void
   sendMessage: (content: string, options?: ChatOptions, images?: string[]) => Promise<void>
   editUserMessage: (messageId: string, newContent: string) => Promise<void>
+  /** Continue the unfinished (interrupted, cancelled or failed) last reply, or with `regenerate` replace it */
+  resumeMessage: (messageId: string, regenerate?: boolean) => Promise<void>
+  /** Follow a run still in progress for `chatId`, e.g. after switching back to the chat */
+  reattachRun: (chatId: string) => Promise<boolean>
+  /** Reload `runs` from the backend */
+  refreshRuns: () => Promise<void>
   stopStreaming: () => void
   clearMessages: () => void
   generateAutoTitle: (chatId: string, userContent: string) => Promise<void>
 }
 
+/** A background run as reported by `chat_list_runs` */
+export interface ChatRunInfo {
+  stream_id: string
+  chat_id: string | null
+  model: string
+  started_at: number
+  elapsed_ms: number
+  tokens: number
+}
+
+/** A run in progress; chunks with `seq` <= `seq` are already contained in `content` */
+interface ChatRunSnapshot {
+  run: ChatRunInfo
+  content: string
+  seq: number
+}
+
+/** Keeps one assistant message in sync with a backend run through its `chat:*` events */
+interface RunFollower {
+  /** Start applying events of `streamId`; `snapshot` is content the message already has */
+  start: (streamId: string, snapshot?: { content: string; seq: number }) => void
+  /** The run could not be started */
+  fail: (error: unknown) => void
+  /** Stop following without cancelling the run, e.g. when switching chats */
+  detach: () => void
+}
+
+// The follower of the run shown in the current chat, if any
+let activeFollower: RunFollower | null = null
+
+const RUN_EVENTS = ['chat:chunk', 'chat:tool-start', 'chat:cancelled', 'chat:error', 'chat:complete'] as const
+
+// Pick the provider for a model: local Ollama models (and local mode) use Ollama,
+// everything else the active provider
+const resolveProviderId = (model: string) => {
+  const { activeProviderId, providers, appMode } = useSettingsStore.getState()
+  const { models } = useModelsStore.getState()
+  const isLocalModel = models.some(m => m.name === model)
+  const ollamaProvider = providers.find(p => p.provider_type === 'ollama')
+  if ((isLocalModel || appMode === 'local') && ollamaProvider) {
+    return ollamaProvider.id
+  }
+  return activeProviderId
+}
+
+// Messages for `chat_stream`: the transcript shown, with the chat's system prompt first.
+// Ids let the backend recognize turns it has already stored.
+const apiMessages = (state: ChatState) => {
+  const messages = state.messages
+    .filter(msg => msg.role !== 'assistant' || msg.content.trim() !== '')
+    .map(msg => ({ id: msg.id as string | undefined, role: msg.role, content: msg.content, images: msg.images }))
+  if (state.currentSystemPrompt) {
+    messages.unshift({ id: undefined, role: 'system', content: state.currentSystemPrompt, images: undefined })
+  }
+  return messages
+}
+
+// Listeners are registered before the run is started, so events that arrive before
+// `start` knows the stream id are buffered and replayed.
+const followRun = async (
+  get: () => ChatState,
+  assistantMessageId: string,
+  onFinished?: () => void,
+): Promise<RunFollower> => {
+  let streamId: string | null = null
+  let lastSeq = 0
+  let early: Array<[string, any]> = []
+  const unlisteners: Array<() => void> = []
+
+  // ── ChatGPT-style character drip queue ──
+  // Incoming tokens are queued. A 30ms interval drips them to the UI
+  // at a controlled rate, decoupling API speed from display speed.
+  let pendingText = ''          // Text waiting to be dripped to UI
+  let displayedContent = ''     // Text currently shown to user
+  let dripIntervalId: ReturnType<typeof setInterval> | null = null
+  let streamDone = false        // True when backend signals done
+  const DRIP_MS = 30            // Drip every 30ms (~33fps)
+
+  const cleanup = () => {
+    if (dripIntervalId) { clearInterval(dripIntervalId); dripIntervalId = null }
+    // Flush any remaining pending text immediately
+    if (pendingText.length > 0) {
+      displayedContent += pendingText
+      pendingText = ''
+      get().updateStreamingMessage(assistantMessageId, displayedContent)
+    }
+    unlisteners.forEach(unlisten => unlisten())
+    unlisteners.length = 0
+    if (activeFollower === follower) activeFollower = null
+  }
+
+  const dripTick = () => {
+    if (pendingText.length === 0) {
+      if (streamDone) {
+        // All text displayed and stream is done — finalize
+        const finalState = get()
+        finalState.setStreaming(false)
+        finalState.markToolCallsDone(assistantMessageId)
+        if (finalState.messages.some(m => m.id === assistantMessageId)) {
+          finalState.updateMessage(assistantMessageId, displayedContent)
+          // The backend has stored the reply; let the sidebar reorder chats
+          window.dispatchEvent(new CustomEvent('chats-refresh'))
+        }
+        cleanup()
+        finalState.refreshRuns()
+        onFinished?.()
+      }
+      return
+    }
+
+    // Adaptive batch size:
+    //  - Queue short (< 5): drip all (for slow providers like Ollama)
+    //  - Queue medium (5-100): drip 2-4 chars (smooth typing feel)
+    //  - Queue long (100+): drip more to gradually catch up
+    let batchSize: number
+    if (pendingText.length <= 4) {
+      batchSize = pendingText.length
+    } else if (pendingText.length < 100) {
+      batchSize = 3
+    } else {
+      batchSize = Math.min(Math.ceil(pendingText.length / 15), 25)
+    }
+
+    const batch = pendingText.slice(0, batchSize)
+    pendingText = pendingText.slice(batchSize)
+    displayedContent += batch
+    get().updateStreamingMessage(assistantMessageId, displayedContent)
+  }
+
+  // Both the final chunk and `chat:complete` signal the end; the drip tick finalizes
+  // once the pending queue is empty
+  const finish = () => {
+    if (streamDone) return
+    streamDone = true
+    if (pendingText.length === 0) dripTick()
+  }
+
+  const showError = (error: unknown) => {
+    const st = get()
+    st.setStreaming(false)
+    // Don't replace content if we already have some response
+    const currentMessage = st.messages.find(m => m.id === assistantMessageId)
+    if (currentMessage && (currentMessage.content.trim() || displayedContent || pendingText)) {
+      st.updateMessage(assistantMessageId, displayedContent + pendingText)
+    } else {
+      st.updateMessage(assistantMessageId, `Error: ${error || 'Failed to get response from model'}`)
+    }
+    pendingText = ''
+    cleanup()
+  }
+
+  const handle = (event: string, payload: any) => {
+    switch (event) {
+      case 'chat:chunk': {
+        // Already part of the snapshot we started from
+        if (typeof payload.seq === 'number' && payload.seq <= lastSeq) return
+        const part: string = payload?.message?.content ?? ''
+        if (part.length > 0) {
+          // Content after a tool call means the tool has finished
+          const currentMsg = get().messages.find(m => m.id === assistantMessageId)
+          if (currentMsg?.toolCalls?.some(t => t.status === 'calling')) {
+            get().markToolCallsDone(assistantMessageId)
+          }
+          pendingText += part
+          if (!dripIntervalId) {
+            dripIntervalId = setInterval(dripTick, DRIP_MS)
+          }
+        }
+        if (payload?.done) finish()
+        break
+      }
+      case 'chat:tool-start':
+        get().updateMessageToolCalls(assistantMessageId, {
+          id: `tool_${Date.now()}_${Math.random()}`,
+          name: payload.tool,
+          args: payload.args,
+          status: 'calling'
+        })
+        break
+      case 'chat:cancelled':
+      case 'chat:complete':
+        finish()
+        break
+      case 'chat:error':
+        showError(payload?.error)
+        break
+    }
+  }
+
+  const follower: RunFollower = {
+    start: (id, snapshot) => {
+      streamId = id
+      if (snapshot) {
+        displayedContent = snapshot.content
+        lastSeq = snapshot.seq
+        get().updateStreamingMessage(assistantMessageId, displayedContent)
+      }
+      get().setStreaming(true, assistantMessageId, id)
+      get().refreshRuns()
+      const buffered = early
+      early = []
+      for (const [event, payload] of buffered) {
+        if (payload?.stream_id === id) handle(event, payload)
+      }
+    },
+    fail: (error) => showError(error),
+    detach: () => {
+      // Keep what has arrived; the stored draft has the rest
+      get().updateMessage(assistantMessageId, displayedContent + pendingText)
+      pendingText = ''
+      cleanup()
+      get().setStreaming(false)
+    },
+  }
+
+  for (const event of RUN_EVENTS) {
+    unlisteners.push(await listen(event, (e: any) => {
+      if (!streamId) {
+        early.push([event, e.payload])
+      } else if (e.payload?.stream_id === streamId) {
+        handle(event, e.payload)
+      }
+    }))
+  }
+
+  activeFollower?.detach()
+  activeFollower = follower
+  return follower
+}
+
 export const useChatStore = create<ChatState>((set, get) => ({
   messages: [],
   currentChatId: null,
@@ -63,6 +315,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
   streamingMessageId: null,
   currentStreamId: null,
   currentSystemPrompt: null,
+  runs: [],
 
   setCurrentModel: (model) => set({ currentModel: model }),
   setCurrentChatId: (chatId) => set({ currentChatId: chatId }),
@@ -86,13 +339,15 @@ export const useChatStore = create<ChatState>((set, get) => ({
 
   loadChat: async (chatId: string, systemPrompt?: string | null) => {
     try {
-      const state = get()
-      if (state.isStreaming) {
-        await state.stopStreaming()
-      }
+      // Runs keep going in the background; stop following the one of the chat we leave
+      activeFollower?.detach()
       set({ currentChatId: chatId, messages: [], currentSystemPrompt: systemPrompt || null })
       const rows = await invoke<any>('db_list_messages', { chatId, limit: 1000 })
-      const msgs: ChatMessage[] = (rows as any[]).map((r) => {
+      // Tool results and tool-call-only assistant turns are kept for the model, not shown
+      const visible = (rows as any[]).filter((r) =>
+        r.role !== 'tool' && !(r.role === 'assistant' && !r.content)
+      )
+      const msgs: ChatMessage[] = visible.map((r) => {
         let images: string[] | undefined
         try {
           if (r.meta_json) {
@@ -109,9 +364,11 @@ export const useChatStore = create<ChatState>((set, get) => ({
           content: r.content,
           images,
           timestamp: Number(r.created_at) || Date.now(),
+          status: r.status ?? undefined,
         }
       })
       set({ messages: msgs })
+      await get().reattachRun(chatId)
       return true
     } catch (e) {
       console.error('db_list_messages failed', e)
@@ -145,6 +402,15 @@ export const useChatStore = create<ChatState>((set, get) => ({
     })
   },
 
+  // Give a message the id and timestamp of the row the backend stored it as
+  adoptStoredRow: (id, row) => {
+    set((state) => ({
+      messages: state.messages.map(msg =>
+        msg.id === id ? { ...msg, id: row.id, timestamp: Number(row.created_at) || msg.timestamp } : msg
+      ),
+    }))
+  },
+
   updateStreamingMessage: (id, content) => {
     set((state) => {
       const messageIndex = state.messages.findIndex(msg => msg.id === id)
@@ -216,43 +482,29 @@ export const useChatStore = create<ChatState>((set, get) => ({
       console.warn('Already streaming, ignoring new message request')
       return
     }
-
-    // prevent race conditions by setting streaming immediately
-    set({ isStreaming: true })
-    const cleanupOnError = () => set({ isStreaming: false })
-
     if (!state.currentModel) {
       console.error('No model selected')
       return
     }
 
+    // prevent race conditions by setting streaming immediately
+    set({ isStreaming: true })
+
     // Ensure we have a chat in DB
     if (!state.currentChatId) {
       await get().createNewChat({ model: state.currentModel })
     }
 
-    // Add user message (UI)
-    state.addMessage({
+    // Add user message (UI); the backend stores it with the rest of the turn
+    const userMessageId = state.addMessage({
       role: 'user',
       content: content.trim(),
       images,
     })
-    // Persist user message
     const chatId = get().currentChatId
-    if (chatId) {
-      try {
-        // Ensure chat has model set in DB
-        if (state.currentModel) {
-          invoke('db_set_chat_model', { chatId, model: state.currentModel }).catch(() => { })
-        }
-
-        const metaJson = images && images.length > 0 ? JSON.stringify({ images }) : null
-        await invoke('db_append_message', { chatId, role: 'user', content: content.trim(), metaJson })
-        // Inform listeners (Sidebar) to refresh chats ordering
-        window.dispatchEvent(new CustomEvent('chats-refresh'))
-      } catch (e) {
-        console.warn('db_append_message (user) failed', e)
-      }
+    if (chatId && state.currentModel) {
+      // Ensure chat has model set in DB
+      invoke('db_set_chat_model', { chatId, model: state.currentModel }).catch(() => { })
     }
 
     // Add assistant message placeholder
@@ -262,249 +514,23 @@ export const useChatStore = create<ChatState>((set, get) => ({
       isStreaming: true,
     })
 
-    let currentStreamId: string | null = null
-    // Don't set streaming yet - wait for event listeners
-
-    let unlistenChunk: (() => void) | null = null
-    let unlistenError: (() => void) | null = null
-    let unlistenComplete: (() => void) | null = null
-    let unlistenStreamStart: (() => void) | null = null
-    let unlistenCancelled: (() => void) | null = null
-    let unlistenToolStart: (() => void) | null = null
-
-    // ── ChatGPT-style character drip queue ──
-    // Incoming tokens are queued. A 30ms interval drips them to the UI
-    // at a controlled rate, decoupling API speed from display speed.
-    let pendingText = ''          // Text waiting to be dripped to UI
-    let displayedContent = ''     // Text currently shown to user
-    let dripIntervalId: ReturnType<typeof setInterval> | null = null
-    let streamDone = false        // True when backend signals done
-    let persisted = false
-    const DRIP_MS = 30            // Drip every 30ms (~33fps)
-
-    const dripTick = () => {
-      if (pendingText.length === 0) {
-        if (streamDone) {
-          // All text displayed and stream is done — finalize
-          if (dripIntervalId) { clearInterval(dripIntervalId); dripIntervalId = null }
-
-          const finalState = get()
-          finalState.setStreaming(false)
-          finalState.markToolCallsDone(assistantMessageId)
-          const currentMessage = finalState.messages.find(m => m.id === assistantMessageId)
-          if (currentMessage) {
-            finalState.updateMessage(assistantMessageId, displayedContent)
-            if (!persisted && chatId) {
-              invoke('db_append_message', { chatId, role: 'assistant', content: displayedContent, metaJson: null })
-                .then(() => window.dispatchEvent(new CustomEvent('chats-refresh')))
-                .catch((e) => console.warn('db_append_message (assistant) failed', e))
-              persisted = true
-            }
-          }
-
-          // Auto-title trigger
-          const stateAfter = get()
-          if (stateAfter.messages.length <= 5 && chatId) {
-            const userMsg = stateAfter.messages.find(m => m.role === 'user')
-            if (userMsg && !stateAfter.currentSystemPrompt?.includes('Generate a short')) {
-              stateAfter.generateAutoTitle(chatId, userMsg.content).catch(console.error)
-            }
-          }
-          cleanup()
+    const follower = await followRun(get, assistantMessageId, () => {
+      // Auto-title trigger
+      const stateAfter = get()
+      if (stateAfter.messages.length <= 5 && chatId && stateAfter.currentChatId === chatId) {
+        const userMsg = stateAfter.messages.find(m => m.role === 'user')
+        if (userMsg && !stateAfter.currentSystemPrompt?.includes('Generate a short')) {
+          stateAfter.generateAutoTitle(chatId, userMsg.content).catch(console.error)
         }
-        return
-      }
-
-      // Adaptive batch size:
-      //  - Queue short (< 5): drip all (for slow providers like Ollama)
-      //  - Queue medium (5-100): drip 2-4 chars (smooth typing feel)
-      //  - Queue long (100+): drip more to gradually catch up
-      let batchSize: number
-      if (pendingText.length <= 4) {
-        batchSize = pendingText.length
-      } else if (pendingText.length < 100) {
-        batchSize = 3
-      } else {
-        batchSize = Math.min(Math.ceil(pendingText.length / 15), 25)
-      }
-
-      const batch = pendingText.slice(0, batchSize)
-      pendingText = pendingText.slice(batchSize)
-      displayedContent += batch
-
-      get().updateStreamingMessage(assistantMessageId, displayedContent)
-    }
-
-    const startDrip = () => {
-      if (!dripIntervalId) {
-        dripIntervalId = setInterval(dripTick, DRIP_MS)
-      }
-    }
-
-    const cleanup = () => {
-      if (dripIntervalId) { clearInterval(dripIntervalId); dripIntervalId = null }
-      // Flush any remaining pending text immediately
-      if (pendingText.length > 0) {
-        displayedContent += pendingText
-        pendingText = ''
-        get().updateStreamingMessage(assistantMessageId, displayedContent)
       }
-      if (unlistenChunk) unlistenChunk()
-      if (unlistenError) unlistenError()
-      if (unlistenComplete) unlistenComplete()
-      if (unlistenStreamStart) unlistenStreamStart()
-      if (unlistenCancelled) unlistenCancelled()
-      if (unlistenToolStart) unlistenToolStart()
-    }
+    })
+    get().setStreaming(true, assistantMessageId)
 
     try {
-      // Listen for stream start to get stream ID
-      unlistenStreamStart = await listen('chat:stream-start', (event: any) => {
-        const { stream_id } = event.payload as { stream_id: string }
-
-        currentStreamId = stream_id
-        const currentState = get()
-        currentState.setStreaming(true, assistantMessageId, stream_id)
-      })
-
-      // Listen for tool start
-      unlistenToolStart = await listen('chat:tool-start', (event: any) => {
-        const payload = event.payload as { stream_id: string, tool: string, args: any }
-
-        if (payload.stream_id === currentStreamId) {
-          get().updateMessageToolCalls(assistantMessageId, {
-            id: `tool_${Date.now()}_${Math.random()}`,
-            name: payload.tool,
-            args: payload.args,
-            status: 'calling'
-          })
-        }
-      })
-
-      // Listen for cancellation
-      unlistenCancelled = await listen('chat:cancelled', (event: any) => {
-        const { stream_id } = event.payload as { stream_id: string }
-
-        if (stream_id === currentStreamId) {
-          const currentState = get()
-          currentState.setStreaming(false)
-          cleanup()
-        }
-      })
-      // Set up event listeners for streaming
-      unlistenChunk = await listen('chat:chunk', (event: any) => {
-        const chunk = event.payload as { stream_id?: string; message?: { role?: string; content?: string }; done?: boolean }
-
-        // Filter by stream_id to prevent auto-title or other streams from leaking in
-        if (chunk.stream_id && currentStreamId && chunk.stream_id !== currentStreamId) return
-
-        // Only process chunks for the currently streaming assistant message
-        const currentState = get()
-        if (currentState.streamingMessageId !== assistantMessageId) {
-          return
-        }
-
-        // If we receive content, mark any previous tool calls as done and queue it
-        const part = chunk?.message?.content ?? ''
-        if (part.length > 0) {
-          const currentMsg = currentState.messages.find(m => m.id === assistantMessageId)
-          if (currentMsg && currentMsg.toolCalls && currentMsg.toolCalls.some(t => t.status === 'calling')) {
-            currentState.markToolCallsDone(assistantMessageId)
-          }
-
-          // Queue the text for dripping (don't update state directly)
-          pendingText += part
-          startDrip()
-        }
-
-        // If the provider sends done=true, signal the drip to finalize when empty
-        if (chunk?.done) {
-          streamDone = true
-          // If nothing is pending, finalize immediately
-          if (pendingText.length === 0) {
-            dripTick()
-          }
-        }
-      })
-
-      unlistenError = await listen('chat:error', (event: any) => {
-
-        const payload = event.payload as { stream_id?: string; error?: string }
-        if (payload?.stream_id && payload.stream_id !== currentStreamId) {
-          return
-        }
-        state.setStreaming(false)
-
-        // Don't replace content if we already have some response
-        const currentMessage = get().messages.find(m => m.id === assistantMessageId)
-        if (currentMessage && currentMessage.content.trim()) {
-          // Just stop streaming, keep existing content
-          state.updateMessage(assistantMessageId, currentMessage.content)
-        } else {
-          // No content yet, show error
-          state.updateMessage(assistantMessageId, `Error: ${payload?.error || 'Failed to get response from model'}`)
-        }
-        cleanup()
-      })
-
-      unlistenComplete = await listen('chat:complete', (event: any) => {
-        const payload = event.payload as { completed: boolean; stream_id?: string }
-
-        // Only process completion for the current stream
-        if (payload.stream_id && payload.stream_id !== currentStreamId) return
-
-        // Signal that the stream is done — drip tick will handle finalization
-        // when the pending queue is empty
-        streamDone = true
-        if (pendingText.length === 0) {
-          dripTick() // Finalize immediately if nothing pending
-        }
-      })
-
-      // NOW set streaming state after all listeners are ready (before invoke)
-      state.setStreaming(true, assistantMessageId)
-
-      // Prepare messages for API from a fresh snapshot to avoid stale state
-      const latest = get()
-      const apiMessages = latest.messages
-        .filter(msg => msg.role !== 'assistant' || msg.content.trim() !== '')
-        .map(msg => ({
-          role: msg.role,
-          content: msg.content,
-          images: msg.images,
-        }))
-
-      // Inject system prompt if it exists
-      const freshState = get()
-      if (freshState.currentSystemPrompt) {
-        apiMessages.unshift({ role: 'system', content: freshState.currentSystemPrompt, images: undefined })
-      }
-
-      // Get active provider ID from settings
-      const { activeProviderId, providers } = useSettingsStore.getState()
-
-      const { appMode } = useSettingsStore.getState()
-      const { models } = useModelsStore.getState()
-      let providerId = activeProviderId
-
-      // Intelligent Provider Selection
-      // 1. If the model is in our known local Ollama models list, force Ollama provider
-      const isLocalModel = models.some(m => m.name === state.currentModel)
-      const ollamaProvider = providers.find(p => p.provider_type === 'ollama')
-
-      if (isLocalModel && ollamaProvider) {
-        providerId = ollamaProvider.id
-      } else if (appMode === 'local' && ollamaProvider) {
-        // Fallback: If we are strictly in local mode, default to Ollama
-        providerId = ollamaProvider.id
-      }
-      // Otherwise use the active (Cloud) provider
-
-      // Send the chat request
-      await invoke('chat_stream', {
+      const response = await invoke<ChatStreamResponse>('chat_stream', {
         request: {
           model: state.currentModel,
-          messages: apiMessages,
+          messages: apiMessages(get()),
           stream: true,
           options: options ? {
             temperature: options.temperature,
@@ -513,23 +539,19 @@ export const useChatStore = create<ChatState>((set, get) => ({
             max_tokens: options.maxTokens,
           } : undefined,
         },
-        providerId: providerId
+        providerId: resolveProviderId(state.currentModel),
+        chatId,
       })
-
-      // Add a timeout safety net
-      setTimeout(() => {
-        const currentState = get()
-        if (currentState.isStreaming && currentState.streamingMessageId === assistantMessageId) {
-          currentState.setStreaming(false)
-          cleanup()
-        }
-      }, 60000) // 60 second timeout
-
+      if (response?.user_message) {
+        get().adoptStoredRow(userMessageId, response.user_message)
+        window.dispatchEvent(new CustomEvent('chats-refresh'))
+      }
+      // The run continues in the background; its events carry this id
+      if (!response?.stream_id) throw new Error('No stream id returned')
+      follower.start(response.stream_id)
     } catch (error) {
       console.error('Failed to send message:', error)
-      cleanupOnError()
-      state.updateMessage(assistantMessageId, `Error: ${error}`)
-      cleanup()
+      follower.fail(error)
     }
   },
 
@@ -575,195 +597,94 @@ export const useChatStore = create<ChatState>((set, get) => ({
     // Yield to main thread to allow UI to update and prevent freeze
     await new Promise(resolve => setTimeout(resolve, 10))
 
-    // 3. Trigger Generation (Re-use logic from sendMessage mostly)
-
-    // Add assistant message placeholder - use get() for fresh state!
-    const freshStateForAdd = get()
-    const assistantMessageId = freshStateForAdd.addMessage({
+    // 3. Trigger Generation
+    const assistantMessageId = get().addMessage({
       role: 'assistant',
       content: '',
       isStreaming: true,
     })
+    const follower = await followRun(get, assistantMessageId)
+    get().setStreaming(true, assistantMessageId)
 
-    let currentStreamId: string | null = null
-
-    // ── ChatGPT-style character drip queue (same as sendMessage) ──
-    let pendingText = ''
-    let displayedContent = ''
-    let dripIntervalId: ReturnType<typeof setInterval> | null = null
-    let streamDone = false
-    let persisted = false
-    const DRIP_MS = 30
-
-    const dripTick = () => {
-      if (pendingText.length === 0) {
-        if (streamDone) {
-          if (dripIntervalId) { clearInterval(dripIntervalId); dripIntervalId = null }
-          const finalState = get()
-          finalState.setStreaming(false)
-          finalState.markToolCallsDone(assistantMessageId)
-          const currentMessage = finalState.messages.find(m => m.id === assistantMessageId)
-          if (currentMessage) {
-            finalState.updateMessage(assistantMessageId, displayedContent)
-            if (!persisted && chatId) {
-              invoke('db_append_message', { chatId, role: 'assistant', content: displayedContent, metaJson: null })
-                .then(() => window.dispatchEvent(new CustomEvent('chats-refresh')))
-                .catch(() => { })
-              persisted = true
-            }
-          }
-          cleanup()
-        }
-        return
-      }
-
-      let batchSize: number
-      if (pendingText.length <= 4) {
-        batchSize = pendingText.length
-      } else if (pendingText.length < 100) {
-        batchSize = 3
-      } else {
-        batchSize = Math.min(Math.ceil(pendingText.length / 15), 25)
-      }
-
-      const batch = pendingText.slice(0, batchSize)
-      pendingText = pendingText.slice(batchSize)
-      displayedContent += batch
-      get().updateStreamingMessage(assistantMessageId, displayedContent)
-    }
-
-    const startDrip = () => {
-      if (!dripIntervalId) {
-        dripIntervalId = setInterval(dripTick, DRIP_MS)
-      }
+    try {
+      const currentModel = get().currentModel
+      // The edited turn keeps its row id, so the backend does not store it again
+      const response = await invoke<ChatStreamResponse>('chat_stream', {
+        request: { model: currentModel, messages: apiMessages(get()), stream: true },
+        providerId: resolveProviderId(currentModel),
+        chatId,
+      })
+      if (!response?.stream_id) throw new Error('No stream id returned')
+      follower.start(response.stream_id)
+    } catch (error) {
+      follower.fail(error)
     }
+  },
 
-    let unlistenChunk: (() => void) | null = null
-    let unlistenError: (() => void) | null = null
-    let unlistenComplete: (() => void) | null = null
-    let unlistenStreamStart: (() => void) | null = null
-    let unlistenCancelled: (() => void) | null = null
-    let unlistenToolStart: (() => void) | null = null
-
-    const cleanup = () => {
-      if (dripIntervalId) { clearInterval(dripIntervalId); dripIntervalId = null }
-      if (pendingText.length > 0) {
-        displayedContent += pendingText
-        pendingText = ''
-        get().updateStreamingMessage(assistantMessageId, displayedContent)
-      }
-      if (unlistenChunk) unlistenChunk()
-      if (unlistenError) unlistenError()
-      if (unlistenComplete) unlistenComplete()
-      if (unlistenStreamStart) unlistenStreamStart()
-      if (unlistenCancelled) unlistenCancelled()
-      if (unlistenToolStart) unlistenToolStart()
+  resumeMessage: async (messageId: string, regenerate = false) => {
+    const state = get()
+    const chatId = state.currentChatId
+    const message = state.messages.find(m => m.id === messageId)
+    if (!chatId || !message || state.isStreaming) return
+
+    let assistantMessageId = messageId
+    if (regenerate || !message.content) {
+      // The backend deletes the unfinished row and generates a new reply
+      set((s) => ({ messages: s.messages.filter(m => m.id !== messageId) }))
+      assistantMessageId = get().addMessage({ role: 'assistant', content: '', isStreaming: true })
+    } else {
+      set((s) => ({
+        messages: s.messages.map(m => m.id === messageId ? { ...m, status: undefined, isStreaming: true } : m),
+      }))
     }
+    const follower = await followRun(get, assistantMessageId)
+    get().setStreaming(true, assistantMessageId)
 
     try {
-      unlistenStreamStart = await listen('chat:stream-start', (event: any) => {
-        const { stream_id } = event.payload as { stream_id: string }
-        currentStreamId = stream_id
-        get().setStreaming(true, assistantMessageId, stream_id)
-      })
-
-      unlistenToolStart = await listen('chat:tool-start', (event: any) => {
-        const payload = event.payload as { stream_id: string, tool: string, args: any }
-        if (payload.stream_id === currentStreamId) {
-          get().updateMessageToolCalls(assistantMessageId, {
-            id: `tool_${Date.now()}_${Math.random()}`,
-            name: payload.tool,
-            args: payload.args,
-            status: 'calling'
-          })
-        }
-      })
-
-      unlistenCancelled = await listen('chat:cancelled', (event: any) => {
-        const { stream_id } = event.payload as { stream_id: string }
-        if (stream_id === currentStreamId) {
-          get().setStreaming(false)
-          cleanup()
-        }
-      })
-
-      unlistenChunk = await listen('chat:chunk', (event: any) => {
-        const chunk = event.payload as { stream_id?: string; message?: { role?: string; content?: string }; done?: boolean }
-
-        if (chunk.stream_id && currentStreamId && chunk.stream_id !== currentStreamId) return
-
-        const currentState = get()
-        if (currentState.streamingMessageId !== assistantMessageId) return
-
-        const part = chunk?.message?.content ?? ''
-        if (part.length > 0) {
-          const currentMsg = currentState.messages.find(m => m.id === assistantMessageId)
-          if (currentMsg && currentMsg.toolCalls && currentMsg.toolCalls.some(t => t.status === 'calling')) {
-            currentState.markToolCallsDone(assistantMessageId)
-          }
-
-          pendingText += part
-          startDrip()
-        }
-
-        if (chunk?.done) {
-          streamDone = true
-          if (pendingText.length === 0) dripTick()
-        }
-      })
-
-      unlistenError = await listen('chat:error', (event: any) => {
-        const payload = event.payload as { stream_id?: string; error?: string }
-        if (payload?.stream_id && payload.stream_id !== currentStreamId) return
-        const st = get()
-        st.setStreaming(false)
-        const currentMessage = st.messages.find(m => m.id === assistantMessageId)
-        if (currentMessage && !currentMessage.content) {
-          st.updateMessage(assistantMessageId, `Error: ${payload?.error || 'Failed'}`)
-        }
-        cleanup()
-      })
-
-      unlistenComplete = await listen('chat:complete', (event: any) => {
-        const payload = event.payload as { completed: boolean; stream_id?: string }
-        if (payload.stream_id && payload.stream_id !== currentStreamId) return
-        streamDone = true
-        if (pendingText.length === 0) dripTick()
-      })
-
-      get().setStreaming(true, assistantMessageId)
-
-      // Prepare messages + resolve provider
-      const latest = get()
-      const apiMessages = latest.messages
-        .filter(msg => msg.role !== 'assistant' || msg.content.trim() !== '')
-        .map(msg => ({ role: msg.role, content: msg.content, images: msg.images }))
-
-      if (latest.currentSystemPrompt) {
-        apiMessages.unshift({ role: 'system', content: latest.currentSystemPrompt, images: undefined })
-      }
-
-      const { activeProviderId, providers, appMode } = useSettingsStore.getState()
-      const { models } = useModelsStore.getState()
-      let providerId = activeProviderId
       const currentModel = get().currentModel
-      const isLocalModel = models.some(m => m.name === currentModel)
-      const ollamaProvider = providers.find(p => p.provider_type === 'ollama')
-      if (isLocalModel && ollamaProvider) {
-        providerId = ollamaProvider.id
-      } else if (appMode === 'local' && ollamaProvider) {
-        providerId = ollamaProvider.id
-      }
-
-      await invoke('chat_stream', {
-        request: { model: currentModel, messages: apiMessages, stream: true },
-        providerId
+      // Only the system prompt is taken from the request; the history is the stored one
+      const response = await invoke<ChatStreamResponse>('chat_resume', {
+        request: { model: currentModel, messages: apiMessages(get()).filter(m => m.role === 'system'), stream: true },
+        providerId: resolveProviderId(currentModel),
+        chatId,
+        messageId,
+        regenerate,
       })
-
+      if (!response?.stream_id) throw new Error('No stream id returned')
+      // A continued run replays the saved content as its first chunk
+      const resumed = assistantMessageId === messageId
+      follower.start(response.stream_id, resumed ? { content: message.content, seq: 1 } : undefined)
     } catch (error) {
-      get().setStreaming(false)
-      get().updateMessage(assistantMessageId, `Error: ${error}`)
-      cleanup()
+      follower.fail(error)
+    }
+  },
+
+  reattachRun: async (chatId: string) => {
+    let snapshot: ChatRunSnapshot
+    try {
+      snapshot = await invoke<ChatRunSnapshot>('chat_reattach', { chatId })
+    } catch {
+      // Nothing running for this chat
+      return false
+    }
+    if (get().currentChatId !== chatId) return false
+
+    // The run's own rows are still being written; show its buffered content instead
+    set((state) => ({
+      messages: state.messages.filter(m => m.role === 'user' || m.timestamp < snapshot.run.started_at),
+    }))
+    const assistantMessageId = get().addMessage({ role: 'assistant', content: '', isStreaming: true })
+    const follower = await followRun(get, assistantMessageId)
+    follower.start(snapshot.run.stream_id, { content: snapshot.content, seq: snapshot.seq })
+    return true
+  },
+
+  refreshRuns: async () => {
+    try {
+      const runs = await invoke<ChatRunInfo[]>('chat_list_runs')
+      set({ runs })
+    } catch (e) {
+      console.warn('chat_list_runs failed', e)
     }
   },
 
@@ -771,11 +692,16 @@ export const useChatStore = create<ChatState>((set, get) => ({
     const state = get()
     if (state.isStreaming) {
       try {
-        await invoke('chat_cancel')
-        state.setStreaming(false)
+        if (state.currentStreamId) {
+          await invoke('chat_cancel', { streamId: state.currentStreamId })
+        }
       } catch (error) {
         console.error('Failed to stop streaming:', error)
-        // Force stop anyway
+      }
+      // Stop following right away; the backend finishes the cancelled draft on its own
+      if (activeFollower) {
+        activeFollower.detach()
+      } else {
         state.setStreaming(false)
       }
     }
@@ -818,17 +744,17 @@ export const useChatStore = create<ChatState>((set, get) => ({
     let titleAccumulator = ''
     let titleStreamId: string | null = null
     let isDone = false
+    // Chunks that arrive before `chat_stream` has returned the id of our run
+    const early: Array<{ stream_id?: string; message?: { content?: string }; done?: boolean }> = []
 
     // Create a promise that resolves when generation is done
     await new Promise<void>(async (resolvePromise) => {
       let unlistenChunk: (() => void) | null = null
-      let unlistenStart: (() => void) | null = null
       let timeout: any = null
 
       const cleanup = () => {
         if (timeout) clearTimeout(timeout)
         if (unlistenChunk) unlistenChunk()
-        if (unlistenStart) unlistenStart()
       }
 
       // Wrap resolve to cleanup
@@ -837,23 +763,23 @@ export const useChatStore = create<ChatState>((set, get) => ({
         resolvePromise()
       }
 
-      unlistenChunk = await listen('chat:chunk', (event: any) => {
-        const chunk = event.payload as { stream_id?: string; message?: { content?: string }; done?: boolean }
-        if (chunk.stream_id && chunk.stream_id === titleStreamId) {
-          if (chunk.message?.content) {
-            titleAccumulator += chunk.message.content
-          }
-          if (chunk.done) {
-            isDone = true
-            resolve()
-          }
+      const onChunk = (chunk: { stream_id?: string; message?: { content?: string }; done?: boolean }) => {
+        if (chunk.stream_id !== titleStreamId) return
+        if (chunk.message?.content) {
+          titleAccumulator += chunk.message.content
         }
-      })
+        if (chunk.done && !isDone) {
+          isDone = true
+          resolve()
+        }
+      }
 
-      unlistenStart = await listen('chat:stream-start', (event: any) => {
-        const payload = event.payload as { stream_id: string }
-        if (!titleStreamId) {
-          titleStreamId = payload.stream_id
+      unlistenChunk = await listen('chat:chunk', (event: any) => {
+        const chunk = event.payload as { stream_id?: string; message?: { content?: string }; done?: boolean }
+        if (titleStreamId) {
+          onChunk(chunk)
+        } else {
+          early.push(chunk)
         }
       })
 
@@ -866,7 +792,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
       }, 60000)
 
       try {
-        await invoke('chat_stream', {
+        const response = await invoke<ChatStreamResponse>('chat_stream', {
           request: {
             model: titleModel,
             messages: [
@@ -878,6 +804,8 @@ export const useChatStore = create<ChatState>((set, get) => ({
             options: { temperature: 0.7, max_tokens: titleModel.includes('thinking') || titleModel.includes('r1') ? 2048 : 256 }
           }
         })
+        titleStreamId = response?.stream_id ?? null
+        early.splice(0).forEach(onChunk)
       } catch (e) {
         console.error('Auto-title invoke failed', e)
         resolve()
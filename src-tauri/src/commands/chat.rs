@@ -2,16 +2,13 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use std::sync::atomic::{AtomicBool};
+use tauri::Emitter;
 use uuid::Uuid;
 use crate::commands::settings::{settings_get, provider_get_active};
-use crate::providers::{ProviderType, ChatMessage as ProviderChatMessage, ChatOptions as ProviderChatOptions};
-use crate::providers::traits::LLMProvider;
-use crate::providers::orchestrator::ChatOrchestrator;
-use crate::providers::ollama::OllamaProvider;
-use crate::providers::openai::OpenAIProvider;
-use crate::providers::anthropic::AnthropicProvider;
-use crate::providers::google::GoogleProvider;
+use crate::commands::db::{db_delete_messages_after, db_list_messages, history_from_rows, insert_message, MessageMeta, MessageRow};
+use crate::db::get_pool;
+use crate::providers::{create_provider, ChatMessage as ProviderChatMessage, ProviderConfig, ChatOptions as ProviderChatOptions};
+use crate::providers::orchestrator::{ChatOrchestrator, ChatRun, LoopLimits};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -19,6 +16,11 @@ pub struct ChatMessage {
     pub content: String,
     pub images: Option<Vec<String>>,
     pub tool_calls: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+    /// Row id of a message that is already stored, e.g. a user turn edited in place
+    #[serde(default)]
+    pub id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,6 +29,12 @@ pub struct ChatRequest {
     pub messages: Vec<ChatMessage>,
     pub stream: Option<bool>,
     pub options: Option<ChatOptions>,
+    /// Scheduler priority when the provider is busy (higher first, default 0)
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Loop limits for this run, overriding the chat's own
+    #[serde(default)]
+    pub limits: Option<LoopLimits>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,11 +49,66 @@ pub struct ChatOptions {
 pub struct ChatResponse {
     pub success: bool,
     pub error: Option<String>,
+    /// Id of the background run; progress arrives as `chat:*` events tagged with it
+    pub stream_id: Option<String>,
+    /// The user turn as stored for a persisted chat, so the UI can use its row id
+    pub user_message: Option<MessageRow>,
+}
+
+/// A background chat run as reported to the UI
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatRunInfo {
+    pub stream_id: String,
+    pub chat_id: Option<String>,
+    pub model: String,
+    pub started_at: i64,
+    pub elapsed_ms: u64,
+    pub tokens: u64,
+}
+
+/// Everything a UI needs to pick up a run mid-stream.
+/// Chunk events with `seq` <= `seq` here are already contained in `content`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatRunSnapshot {
+    pub run: ChatRunInfo,
+    pub content: String,
+    pub seq: u64,
 }
 
 // Global state to track active streams
 lazy_static::lazy_static! {
-    static ref ACTIVE_STREAMS: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref ACTIVE_STREAMS: Arc<Mutex<HashMap<String, Arc<ChatRun>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Keeps a run listed in `ACTIVE_STREAMS`, where it can be listed, cancelled and
+/// reattached to, until the registration is dropped
+pub struct RunRegistration {
+    stream_id: String,
+}
+
+pub async fn register_run(stream_id: &str, run: Arc<ChatRun>) -> RunRegistration {
+    ACTIVE_STREAMS.lock().await.insert(stream_id.to_string(), run);
+    RunRegistration { stream_id: stream_id.to_string() }
+}
+
+impl Drop for RunRegistration {
+    fn drop(&mut self) {
+        let stream_id = std::mem::take(&mut self.stream_id);
+        tokio::spawn(async move {
+            ACTIVE_STREAMS.lock().await.remove(&stream_id);
+        });
+    }
+}
+
+fn run_info(stream_id: &str, run: &ChatRun) -> ChatRunInfo {
+    ChatRunInfo {
+        stream_id: stream_id.to_string(),
+        chat_id: run.chat_id.clone(),
+        model: run.model.clone(),
+        started_at: run.started_at,
+        elapsed_ms: run.elapsed_ms(),
+        tokens: run.tokens(),
+    }
 }
 
 #[tauri::command]
@@ -54,9 +117,63 @@ pub async fn chat_stream(
     request: ChatRequest,
     _server_url: Option<String>, // Deprecated/Unused? ProviderConfig handles URL.
     provider_id: Option<String>,
+    chat_id: Option<String>,
 ) -> Result<ChatResponse, String> {
-    
-    // 1. Resolve Provider Configuration
+    let provider_config = resolve_provider(provider_id).await?;
+    let mut messages = to_provider_messages(&request.messages);
+
+    // Persisted chats: save the new user turn and replay the stored transcript,
+    // which (unlike the UI copy) includes every tool call and tool result.
+    let mut user_message = None;
+    if let Some(cid) = &chat_id {
+        (messages, user_message) = prepare_persisted_history(cid, &request.messages, messages).await?;
+    }
+
+    let stream_id = start_run(app, provider_config, request, messages, chat_id, None).await;
+    Ok(ChatResponse { success: true, error: None, stream_id: Some(stream_id), user_message })
+}
+
+/// Continue (or, with `regenerate`, replace) an assistant reply left `interrupted`,
+/// `cancelled` or `failed`. The reply must be the last message of the chat; `request`
+/// supplies the model, options and system prompt as for `chat_stream`, but its other
+/// messages are ignored in favour of the stored transcript.
+#[tauri::command]
+pub async fn chat_resume(
+    app: tauri::AppHandle,
+    request: ChatRequest,
+    provider_id: Option<String>,
+    chat_id: String,
+    message_id: String,
+    regenerate: bool,
+) -> Result<ChatResponse, String> {
+    let mut rows = db_list_messages(chat_id.clone(), Some(i64::MAX)).await?;
+    let draft = match rows.pop() {
+        Some(row) if row.id == message_id => row,
+        _ => return Err("Only the last message of a chat can be resumed".to_string()),
+    };
+    let resumable = draft.role == "assistant"
+        && matches!(draft.status.as_deref(), Some("interrupted" | "cancelled" | "failed"));
+    if !resumable {
+        return Err("Message is not an unfinished assistant reply".to_string());
+    }
+
+    let provider_config = resolve_provider(provider_id).await?;
+    let mut messages: Vec<ProviderChatMessage> = to_provider_messages(&request.messages).into_iter()
+        .take_while(|m| m.role == "system")
+        .collect();
+    messages.extend(history_from_rows(&rows));
+
+    let resume = if regenerate || draft.content.is_empty() {
+        db_delete_messages_after(chat_id.clone(), draft.created_at - 1).await?;
+        None
+    } else {
+        Some((draft.id, draft.content))
+    };
+    let stream_id = start_run(app, provider_config, request, messages, Some(chat_id), resume).await;
+    Ok(ChatResponse { success: true, error: None, stream_id: Some(stream_id), user_message: None })
+}
+
+async fn resolve_provider(provider_id: Option<String>) -> Result<ProviderConfig, String> {
     let provider_config = if let Some(pid) = provider_id {
         let settings = settings_get().await?;
         settings.providers.into_iter()
@@ -65,43 +182,33 @@ pub async fn chat_stream(
     } else {
         provider_get_active().await?
     };
-
     println!("Using provider: {} ({:?})", provider_config.name, provider_config.provider_type);
+    Ok(provider_config)
+}
 
-    // 2. Instantiate correct Provider Adapter
-    let provider: Box<dyn LLMProvider + Send + Sync> = match provider_config.provider_type {
-        ProviderType::Ollama => Box::new(OllamaProvider),
-        ProviderType::OpenAI | ProviderType::Other => Box::new(OpenAIProvider),
-        ProviderType::Anthropic => Box::new(AnthropicProvider),
-        ProviderType::Google => Box::new(GoogleProvider),
-    };
-
-    // 3. Register Stream for Cancellation
-    let stream_id = Uuid::new_v4().to_string();
-    let should_cancel = Arc::new(AtomicBool::new(false));
-    {
-        let mut active_streams = ACTIVE_STREAMS.lock().await;
-        active_streams.insert(stream_id.clone(), should_cancel.clone());
-    }
-
-    // 4. Transform Data Types (Command -> Provider)
-    let messages: Vec<ProviderChatMessage> = request.messages.iter().map(|m| {
+fn to_provider_messages(messages: &[ChatMessage]) -> Vec<ProviderChatMessage> {
+    messages.iter().map(|m| {
         ProviderChatMessage {
             role: m.role.clone(),
             content: m.content.clone(),
             images: m.images.clone(),
             tool_calls: m.tool_calls.clone(),
-            tool_call_id: None, // Frontend messages don't usually send IDs back unless it's a tool result?
-                                // If it's a tool result, m.role would be tool.
-                                // But `tool_call_id` is usually needed for `tool` messages.
-                                // Our `ChatMessage` struct in `chat.rs` doesn't have `tool_call_id`.
-                                // We might need to look into content or handle it if we want to support history with tool results correctly.
-                                // For now, let's assume `tool_calls` handles the assistant side, and we might be missing `tool_call_id` for tool output messages if frontend doesn't send it.
-                                // However, `orchestrator` handles new messages.
-                                // If `history` is passed from frontend, we trust it.
+            tool_call_id: m.tool_call_id.clone(),
         }
-    }).collect();
+    }).collect()
+}
 
+/// Register a run and drive the conversation loop in the background; the UI follows it
+/// through `chat:*` events tagged with the returned stream id
+async fn start_run(
+    app: tauri::AppHandle,
+    provider_config: ProviderConfig,
+    request: ChatRequest,
+    messages: Vec<ProviderChatMessage>,
+    chat_id: Option<String>,
+    resume: Option<(String, String)>,
+) -> String {
+    let provider = create_provider(&provider_config.provider_type);
     let options = request.options.map(|o| ProviderChatOptions {
         temperature: o.temperature,
         top_k: o.top_k,
@@ -109,40 +216,106 @@ pub async fn chat_stream(
         max_tokens: o.max_tokens,
     });
 
-    // 5. Initialize Orchestrator
-    let orchestrator = ChatOrchestrator::new(app.clone(), provider);
-
-    // 6. Run Conversation Loop
-    let result = orchestrator.run_conversation(
-        &provider_config,
-        &request.model,
-        messages,
-        options,
-        &stream_id,
-        should_cancel
-    ).await;
-
-    // 7. Cleanup
-    {
-        let mut active_streams = ACTIVE_STREAMS.lock().await;
-        active_streams.remove(&stream_id);
+    let stream_id = Uuid::new_v4().to_string();
+    let run = Arc::new(ChatRun::new(chat_id.clone(), request.model.clone(), request.priority.unwrap_or(0)));
+    let registration = register_run(&stream_id, run.clone()).await;
+
+    let mut orchestrator = ChatOrchestrator::new(app.clone(), provider, chat_id).with_limits(request.limits);
+    if let Some((draft_id, partial)) = resume {
+        orchestrator = orchestrator.resume_draft(draft_id, partial);
     }
+    let task_stream_id = stream_id.clone();
+    tokio::spawn(async move {
+        let result = orchestrator.run_conversation(
+            &provider_config,
+            &request.model,
+            messages,
+            options,
+            &task_stream_id,
+            run
+        ).await;
+        drop(registration);
+
+        if let Err(e) = result {
+            eprintln!("Chat error: {}", e);
+            let _ = app.emit("chat:error", serde_json::json!({"stream_id": task_stream_id, "error": e.to_string()}));
+        }
+    });
+    stream_id
+}
+
+/// Save the trailing user turn of `messages` unless it names a row that is already stored
+/// (an edited turn), and return the request's system messages followed by the stored
+/// transcript, together with the newly stored user row.
+async fn prepare_persisted_history(
+    chat_id: &str,
+    request: &[ChatMessage],
+    messages: Vec<ProviderChatMessage>,
+) -> Result<(Vec<ProviderChatMessage>, Option<MessageRow>), String> {
+    let mut rows = db_list_messages(chat_id.to_string(), Some(i64::MAX)).await?;
+    let mut stored = None;
 
-    match result {
-        Ok(_) => Ok(ChatResponse { success: true, error: None }),
-        Err(e) => {
-             eprintln!("Chat error: {}", e);
-             Ok(ChatResponse { success: false, error: Some(e.to_string()) })
+    if let (Some(last), Some(sent)) = (messages.last().filter(|m| m.role == "user"), request.last()) {
+        let already_stored = sent.id.as_ref().is_some_and(|id| rows.iter().any(|r| &r.id == id));
+        if !already_stored {
+            let pool = get_pool().await?;
+            stored = Some(insert_message(&pool, chat_id, &last.role, &last.content, MessageMeta::from_message(last).to_json()).await?);
         }
     }
+
+    if let Some(row) = &stored {
+        rows.push(row.clone());
+    }
+
+    let mut result: Vec<ProviderChatMessage> = messages.into_iter()
+        .take_while(|m| m.role == "system")
+        .collect();
+    result.extend(history_from_rows(&rows));
+    Ok((result, stored))
 }
 
 #[tauri::command]
 pub async fn chat_cancel(stream_id: String) -> Result<(), String> {
     let active_streams = ACTIVE_STREAMS.lock().await;
-    if let Some(should_cancel) = active_streams.get(&stream_id) {
-        should_cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+    if let Some(run) = active_streams.get(&stream_id) {
+        run.cancel();
         println!("Cancelling stream {}", stream_id);
     }
     Ok(())
+}
+
+#[tauri::command]
+pub async fn chat_list_runs() -> Result<Vec<ChatRunInfo>, String> {
+    let active_streams = ACTIVE_STREAMS.lock().await;
+    let mut runs: Vec<ChatRunInfo> = active_streams.iter()
+        .map(|(id, run)| run_info(id, run))
+        .collect();
+    runs.sort_by_key(|r| r.started_at);
+    Ok(runs)
+}
+
+#[tauri::command]
+pub async fn chat_run_buffer(stream_id: String) -> Result<String, String> {
+    let active_streams = ACTIVE_STREAMS.lock().await;
+    let run = active_streams.get(&stream_id)
+        .ok_or_else(|| format!("Stream '{}' is not running", stream_id))?;
+    Ok(run.snapshot().0)
+}
+
+/// Reattach a UI to a run in progress, e.g. after a window reload or when switching back to
+/// its chat. Returns the run's buffered content; later chunks arrive as `chat:chunk` events.
+/// Given only a chat id, the chat's newest run is picked.
+#[tauri::command]
+pub async fn chat_reattach(stream_id: Option<String>, chat_id: Option<String>) -> Result<ChatRunSnapshot, String> {
+    let active_streams = ACTIVE_STREAMS.lock().await;
+    let (id, run) = active_streams.iter()
+        .filter(|(id, run)| {
+            stream_id.as_ref().map(|s| s == *id).unwrap_or(true)
+                && chat_id.as_ref().map(|c| run.chat_id.as_ref() == Some(c)).unwrap_or(true)
+        })
+        // Ties (same millisecond) fall back to the stream id so the choice is stable
+        .max_by(|(a_id, a), (b_id, b)| a.started_at.cmp(&b.started_at).then_with(|| a_id.cmp(b_id)))
+        .ok_or_else(|| "No matching chat run in progress".to_string())?;
+    let (content, seq) = run.snapshot();
+    Ok(ChatRunSnapshot { run: run_info(id, run), content, seq })
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::db::{get_pool, touch_chat_updated};
-use sqlx::FromRow;
+use crate::providers::ChatMessage as ProviderChatMessage;
+use crate::providers::orchestrator::LoopLimits;
+use sqlx::{FromRow, SqlitePool};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct ChatMeta {
@@ -14,7 +16,7 @@ pub struct ChatMeta {
 	pub title: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct MessageRow {
 	pub id: String,
 	pub chat_id: String,
@@ -22,6 +24,58 @@ pub struct MessageRow {
 	pub content: String,
 	pub created_at: i64,
 	pub meta_json: Option<String>,
+	/// Draft state of assistant replies: streaming, complete, cancelled, failed or interrupted
+	pub status: Option<String>,
+}
+
+/// Per-chat settings stored in `chats.params_json`.
+/// Keys this struct does not know about are preserved in `extra`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChatParams {
+	/// Built-in tools enabled for this chat (opt-in); `None` enables none of them
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub native_tools: Option<Vec<String>>,
+	/// Token budget for a single tool result before it is truncated and stored for paging
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub max_tool_result_tokens: Option<usize>,
+	/// Ask the model to continue replies cut off by the output token limit and join the parts
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub auto_continue: Option<bool>,
+	/// Agent loop budgets; `None` uses the defaults
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub limits: Option<LoopLimits>,
+	#[serde(flatten)]
+	pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+pub async fn get_chat_params(chat_id: &str) -> Result<ChatParams, String> {
+	let pool = get_pool().await?;
+	let params_json: Option<Option<String>> = sqlx::query_scalar("SELECT params_json FROM chats WHERE id = ?")
+		.bind(chat_id)
+		.fetch_optional(&pool)
+		.await
+		.map_err(|e| format!("get chat params failed: {}", e))?;
+	Ok(params_json.flatten()
+		.and_then(|p| serde_json::from_str(&p).ok())
+		.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn db_get_chat_params(chat_id: String) -> Result<ChatParams, String> {
+	get_chat_params(&chat_id).await
+}
+
+#[tauri::command]
+pub async fn db_set_chat_params(chat_id: String, params: ChatParams) -> Result<bool, String> {
+	let pool = get_pool().await?;
+	let params_json = serde_json::to_string(&params).map_err(|e| format!("serialize chat params failed: {}", e))?;
+	let res = sqlx::query("UPDATE chats SET params_json = ? WHERE id = ?")
+		.bind(params_json)
+		.bind(chat_id)
+		.execute(&pool)
+		.await
+		.map_err(|e| format!("set chat params failed: {}", e))?;
+	Ok(res.rows_affected() > 0)
 }
 
 #[tauri::command]
@@ -44,23 +98,169 @@ pub async fn db_create_chat(model: Option<String>, system_prompt: Option<String>
 	Ok(ChatMeta { id, created_at: now, updated_at: now, model, system_prompt, params_json, title })
 }
 
+/// Create the chat that stores a delegated sub-conversation, linked to the parent chat and
+/// to the assistant message whose tool call started it. Hidden from the chat list.
+pub async fn create_child_chat(
+	pool: &SqlitePool,
+	parent_chat_id: &str,
+	parent_message_id: Option<&str>,
+	model: &str,
+	system_prompt: Option<&str>,
+	title: &str,
+) -> Result<String, String> {
+	let id = Uuid::new_v4().to_string();
+	let now = chrono::Utc::now().timestamp_millis();
+	sqlx::query("INSERT INTO chats (id, created_at, updated_at, model, system_prompt, title, parent_chat_id, parent_message_id) VALUES (?,?,?,?,?,?,?,?)")
+		.bind(&id)
+		.bind(now)
+		.bind(now)
+		.bind(model)
+		.bind(system_prompt)
+		.bind(title)
+		.bind(parent_chat_id)
+		.bind(parent_message_id)
+		.execute(pool)
+		.await
+		.map_err(|e| format!("create child chat failed: {}", e))?;
+	Ok(id)
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ChildChat {
+	pub id: String,
+	pub created_at: i64,
+	pub model: Option<String>,
+	pub title: Option<String>,
+	/// Assistant message whose delegate call started this chat
+	pub parent_message_id: Option<String>,
+}
+
+/// Delegated sub-conversations started from a chat, oldest first
 #[tauri::command]
-pub async fn db_append_message(chat_id: String, role: String, content: String, meta_json: Option<String>) -> Result<MessageRow, String> {
+pub async fn db_list_child_chats(chat_id: String) -> Result<Vec<ChildChat>, String> {
 	let pool = get_pool().await?;
+	sqlx::query_as::<_, ChildChat>(
+		"SELECT id, created_at, model, title, parent_message_id FROM chats WHERE parent_chat_id = ? ORDER BY created_at ASC"
+	)
+	.bind(chat_id)
+	.fetch_all(&pool)
+	.await
+	.map_err(|e| format!("list child chats failed: {}", e))
+}
+
+/// Extra per-message data stored in `messages.meta_json`.
+/// Tool calls and tool call ids are kept here so a chat can be replayed to a provider verbatim.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MessageMeta {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub images: Option<Vec<String>>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub tool_calls: Option<Vec<serde_json::Value>>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub tool_call_id: Option<String>,
+}
+
+impl MessageMeta {
+	pub fn from_message(msg: &ProviderChatMessage) -> Self {
+		Self {
+			images: msg.images.clone().filter(|i| !i.is_empty()),
+			tool_calls: msg.tool_calls.clone().filter(|c| !c.is_empty()),
+			tool_call_id: msg.tool_call_id.clone(),
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		self.images.is_none() && self.tool_calls.is_none() && self.tool_call_id.is_none()
+	}
+
+	pub fn to_json(&self) -> Option<String> {
+		if self.is_empty() { None } else { serde_json::to_string(self).ok() }
+	}
+}
+
+impl MessageRow {
+	/// Convert a stored row back into the provider message format
+	pub fn to_provider_message(&self) -> ProviderChatMessage {
+		let meta: MessageMeta = self.meta_json.as_deref()
+			.and_then(|m| serde_json::from_str(m).ok())
+			.unwrap_or_default();
+		ProviderChatMessage {
+			role: self.role.clone(),
+			content: self.content.clone(),
+			images: meta.images,
+			tool_calls: meta.tool_calls,
+			tool_call_id: meta.tool_call_id,
+		}
+	}
+}
+
+/// Insert a message row. `created_at` is kept strictly increasing within a chat so that
+/// rows written in the same millisecond (tool calls and their results) replay in order.
+pub async fn insert_message(pool: &SqlitePool, chat_id: &str, role: &str, content: &str, meta_json: Option<String>) -> Result<MessageRow, String> {
 	let id = Uuid::new_v4().to_string();
-	let now = chrono::Utc::now().timestamp_millis();
+	let last: Option<i64> = sqlx::query_scalar("SELECT MAX(created_at) FROM messages WHERE chat_id = ?")
+		.bind(chat_id)
+		.fetch_one(pool)
+		.await
+		.map_err(|e| format!("append message failed: {}", e))?;
+	let now = chrono::Utc::now().timestamp_millis().max(last.map(|l| l + 1).unwrap_or(0));
 	sqlx::query("INSERT INTO messages (id, chat_id, role, content, created_at, meta_json) VALUES (?,?,?,?,?,?)")
 		.bind(&id)
-		.bind(&chat_id)
-		.bind(&role)
-		.bind(&content)
+		.bind(chat_id)
+		.bind(role)
+		.bind(content)
 		.bind(now)
 		.bind(&meta_json)
-		.execute(&pool)
+		.execute(pool)
 		.await
 		.map_err(|e| format!("append message failed: {}", e))?;
-	touch_chat_updated(&pool, &chat_id).await?;
-	Ok(MessageRow { id, chat_id, role, content, created_at: now, meta_json })
+	touch_chat_updated(pool, chat_id).await?;
+	Ok(MessageRow { id, chat_id: chat_id.to_string(), role: role.to_string(), content: content.to_string(), created_at: now, meta_json, status: None })
+}
+
+/// Insert an empty assistant row in the `streaming` state; its content is checkpointed
+/// with `update_draft` while the reply streams in.
+pub async fn insert_draft(pool: &SqlitePool, chat_id: &str) -> Result<String, String> {
+	let row = insert_message(pool, chat_id, "assistant", "", None).await?;
+	set_message_status(pool, &row.id, "streaming").await?;
+	Ok(row.id)
+}
+
+pub async fn update_draft(pool: &SqlitePool, id: &str, content: &str, meta_json: Option<String>, status: &str) -> Result<(), String> {
+	sqlx::query("UPDATE messages SET content = ?, meta_json = ?, status = ? WHERE id = ?")
+		.bind(content)
+		.bind(&meta_json)
+		.bind(status)
+		.bind(id)
+		.execute(pool)
+		.await
+		.map_err(|e| format!("update draft failed: {}", e))?;
+	Ok(())
+}
+
+async fn set_message_status(pool: &SqlitePool, id: &str, status: &str) -> Result<(), String> {
+	sqlx::query("UPDATE messages SET status = ? WHERE id = ?")
+		.bind(status)
+		.bind(id)
+		.execute(pool)
+		.await
+		.map_err(|e| format!("set message status failed: {}", e))?;
+	Ok(())
+}
+
+/// A stored transcript in provider format, including tool calls and results
+pub fn history_from_rows(rows: &[MessageRow]) -> Vec<ProviderChatMessage> {
+	rows.iter()
+		.map(MessageRow::to_provider_message)
+		// Drafts that never received any output would be rejected by most providers
+		.filter(|m| !(m.role == "assistant" && m.content.is_empty() && m.tool_calls.is_none()))
+		.collect()
+}
+
+#[tauri::command]
+pub async fn db_append_message(chat_id: String, role: String, content: String, meta_json: Option<String>) -> Result<MessageRow, String> {
+	let pool = get_pool().await?;
+	insert_message(&pool, &chat_id, &role, &content, meta_json).await
 }
 
 #[tauri::command]
@@ -92,7 +292,7 @@ pub async fn db_list_chats(limit: Option<i64>) -> Result<Vec<ChatMeta>, String>
 	let pool = get_pool().await?;
 	let l = limit.unwrap_or(100);
 	let rows = sqlx::query_as::<_, ChatMeta>(
-		"SELECT id, created_at, updated_at, model, system_prompt, params_json, title FROM chats ORDER BY updated_at DESC LIMIT ?"
+		"SELECT id, created_at, updated_at, model, system_prompt, params_json, title FROM chats WHERE parent_chat_id IS NULL ORDER BY updated_at DESC LIMIT ?"
 	)
 	.bind(l)
 	.fetch_all(&pool)
@@ -120,7 +320,7 @@ pub async fn db_list_chats_with_flags(limit: Option<i64>) -> Result<Vec<ChatWith
 	let rows = sqlx::query_as::<_, ChatWithFlags>(
 		r#"SELECT c.id, c.created_at, c.updated_at, c.model, c.system_prompt, c.params_json, c.title,
 		   EXISTS(SELECT 1 FROM messages m WHERE m.chat_id = c.id LIMIT 1) AS has_messages
-		   FROM chats c ORDER BY c.updated_at DESC LIMIT ?"#
+		   FROM chats c WHERE c.parent_chat_id IS NULL ORDER BY c.updated_at DESC LIMIT ?"#
 	)
 	.bind(l)
 	.fetch_all(&pool)
@@ -134,7 +334,7 @@ pub async fn db_list_messages(chat_id: String, limit: Option<i64>) -> Result<Vec
 	let pool = get_pool().await?;
 	let l = limit.unwrap_or(500);
 	let rows = sqlx::query_as::<_, MessageRow>(
-		"SELECT id, chat_id, role, content, created_at, meta_json FROM messages WHERE chat_id = ? ORDER BY created_at ASC LIMIT ?"
+		"SELECT id, chat_id, role, content, created_at, meta_json, status FROM messages WHERE chat_id = ? ORDER BY created_at ASC LIMIT ?"
 	)
 	.bind(chat_id)
 	.bind(l)
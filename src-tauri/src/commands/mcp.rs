@@ -1,8 +1,20 @@
-use crate::mcp::McpClient;
+use crate::commands::settings::{settings_get, settings_set};
+use crate::mcp::config::McpServerConfig;
+use crate::mcp::config_files::{self, ConfigFormat, ExportResult, ImportPreview};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use crate::mcp::logs::{self, LogEntry};
+use crate::commands::db::MessageRow;
+use crate::mcp::prompts;
+use crate::mcp::protocol::{Completion, CompletionRef, GetPromptResult, Prompt, Resource, ResourceContents, ResourceTemplate};
+use crate::mcp::resources::{self, AttachedResource};
+use crate::mcp::sampling;
+use crate::mcp::supervisor::{self, ServerStatus};
+use crate::mcp::{naming, McpClient};
 
 #[tauri::command]
-pub async fn connect_mcp_server(name: String, command: String, args: Vec<String>) -> Result<(), String> {
-    match McpClient::connect(&name, &command, &args).await {
+pub async fn connect_mcp_server(app: tauri::AppHandle, name: String, command: String, args: Vec<String>) -> Result<(), String> {
+    match supervisor::start(&app, McpServerConfig::stdio(&name, &command, &args)).await {
         Ok(_) => {
             println!("Connected to MCP server: {}", name);
             Ok(())
@@ -12,8 +24,8 @@ pub async fn connect_mcp_server(name: String, command: String, args: Vec<String>
 }
 
 #[tauri::command]
-pub async fn connect_mcp_http(name: String, url: String, auth_token: Option<String>) -> Result<String, String> {
-    match McpClient::connect_http(&name, &url, auth_token).await {
+pub async fn connect_mcp_http(app: tauri::AppHandle, name: String, url: String, auth_token: Option<String>) -> Result<String, String> {
+    match supervisor::start(&app, McpServerConfig::http(&name, &url, auth_token)).await {
         Ok(_) => Ok(format!("Connected to {}", name)),
         Err(e) => Err(e.to_string()),
     }
@@ -24,38 +36,391 @@ pub fn list_mcp_servers() -> Vec<String> {
     McpClient::list_active_clients()
 }
 
+/// Status of every supervised server: connecting, ready, degraded or crashed
+#[tauri::command]
+pub fn mcp_server_statuses() -> HashMap<String, ServerStatus> {
+    supervisor::statuses()
+}
+
+#[derive(serde::Serialize)]
+pub struct ResourceInfo {
+    pub server: String,
+    #[serde(flatten)]
+    pub resource: Resource,
+}
+
+#[derive(serde::Serialize)]
+pub struct ResourceTemplateInfo {
+    pub server: String,
+    #[serde(flatten)]
+    pub template: ResourceTemplate,
+}
+
+fn connected_client(name: &str) -> Result<std::sync::Arc<McpClient>, String> {
+    McpClient::get_client(name).ok_or_else(|| format!("MCP server '{}' is not connected", name))
+}
+
+/// Servers to query: the one named, or every connected server with `capability`
+fn servers_with(capability: &str, server: Option<String>) -> Result<Vec<(String, std::sync::Arc<McpClient>)>, String> {
+    match server {
+        Some(name) => Ok(vec![(name.clone(), connected_client(&name)?)]),
+        None => Ok(McpClient::list_active_clients().into_iter()
+            .filter_map(|name| McpClient::get_client(&name).map(|c| (name, c)))
+            .filter(|(_, c)| c.capability(capability).is_some())
+            .collect()),
+    }
+}
+
+#[tauri::command]
+pub async fn mcp_list_resources(server: Option<String>) -> Result<Vec<ResourceInfo>, String> {
+    let mut listed = Vec::new();
+    for (name, client) in servers_with("resources", server)? {
+        match client.list_resources().await {
+            Ok(resources) => listed.extend(resources.into_iter().map(|resource| ResourceInfo { server: name.clone(), resource })),
+            Err(e) => eprintln!("Failed to list resources for {}: {}", name, e),
+        }
+    }
+    Ok(listed)
+}
+
+#[tauri::command]
+pub async fn mcp_list_resource_templates(server: Option<String>) -> Result<Vec<ResourceTemplateInfo>, String> {
+    let mut listed = Vec::new();
+    for (name, client) in servers_with("resources", server)? {
+        match client.list_resource_templates().await {
+            Ok(templates) => listed.extend(templates.into_iter().map(|template| ResourceTemplateInfo { server: name.clone(), template })),
+            Err(e) => eprintln!("Failed to list resource templates for {}: {}", name, e),
+        }
+    }
+    Ok(listed)
+}
+
+#[tauri::command]
+pub async fn mcp_read_resource(server: String, uri: String) -> Result<Vec<ResourceContents>, String> {
+    connected_client(&server)?.read_resource(&uri).await
+        .map_err(|e| format!("Failed to read {}: {}", uri, e))
+}
+
+#[tauri::command]
+pub async fn mcp_subscribe_resource(server: String, uri: String) -> Result<(), String> {
+    connected_client(&server)?.subscribe_resource(&uri).await
+        .map_err(|e| format!("Failed to subscribe to {}: {}", uri, e))
+}
+
+#[tauri::command]
+pub async fn mcp_unsubscribe_resource(server: String, uri: String) -> Result<(), String> {
+    connected_client(&server)?.unsubscribe_resource(&uri).await
+        .map_err(|e| format!("Failed to unsubscribe from {}: {}", uri, e))
+}
+
+/// Attach a resource to a chat; its contents go to the model as context on every turn
+#[tauri::command]
+pub async fn chat_attach_resource(chat_id: String, server: String, uri: String, name: Option<String>) -> Result<AttachedResource, String> {
+    resources::attach(&chat_id, &server, &uri, name.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn chat_detach_resource(chat_id: String, server: String, uri: String) -> Result<bool, String> {
+    resources::detach(&chat_id, &server, &uri).await
+}
+
+#[tauri::command]
+pub async fn chat_list_resources(chat_id: String) -> Result<Vec<AttachedResource>, String> {
+    resources::list(&chat_id).await
+}
+
+#[derive(serde::Serialize)]
+pub struct PromptInfo {
+    pub server: String,
+    /// Slash command for the prompt: `/<prompt>`, or `/<server>:<prompt>` when several servers
+    /// have a prompt of that name
+    pub command: String,
+    #[serde(flatten)]
+    pub prompt: Prompt,
+}
+
+#[tauri::command]
+pub async fn mcp_list_prompts(server: Option<String>) -> Result<Vec<PromptInfo>, String> {
+    let mut listed = Vec::new();
+    for (name, client) in servers_with("prompts", server)? {
+        match client.list_prompts().await {
+            Ok(prompts) => listed.extend(prompts.into_iter().map(|prompt| (name.clone(), prompt))),
+            Err(e) => eprintln!("Failed to list prompts for {}: {}", name, e),
+        }
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (_, prompt) in &listed {
+        *counts.entry(prompt.name.clone()).or_default() += 1;
+    }
+    Ok(listed.into_iter()
+        .map(|(server, prompt)| {
+            let command = if counts[&prompt.name] > 1 {
+                format!("/{}:{}", server, prompt.name)
+            } else {
+                format!("/{}", prompt.name)
+            };
+            PromptInfo { server, command, prompt }
+        })
+        .collect())
+}
+
+/// Fetch a prompt with its arguments filled in, e.g. to preview it
+#[tauri::command]
+pub async fn mcp_get_prompt(server: String, name: String, arguments: Option<HashMap<String, String>>) -> Result<GetPromptResult, String> {
+    connected_client(&server)?.get_prompt(&name, &arguments.unwrap_or_default()).await
+        .map_err(|e| format!("Failed to get prompt {}: {}", name, e))
+}
+
+/// Suggestions for an argument of a prompt (`{"type": "ref/prompt", "name": ...}`) or resource
+/// template (`{"type": "ref/resource", "uri": ...}`) while the user types `value`
+#[tauri::command]
+pub async fn mcp_complete(
+    server: String,
+    reference: CompletionRef,
+    argument: String,
+    value: String,
+    context: Option<HashMap<String, String>>,
+) -> Result<Completion, String> {
+    connected_client(&server)?.complete(&reference, &argument, &value, context.as_ref()).await
+        .map_err(|e| format!("Failed to complete {}: {}", argument, e))
+}
+
+/// Run a prompt and append the messages it returns to a chat
+#[tauri::command]
+pub async fn chat_insert_prompt(chat_id: String, server: String, name: String, arguments: Option<HashMap<String, String>>) -> Result<Vec<MessageRow>, String> {
+    prompts::insert(&chat_id, &server, &name, &arguments.unwrap_or_default()).await
+}
+
+/// Approve or reject a pending `mcp:sampling-request`; false if it is no longer waiting
+#[tauri::command]
+pub fn mcp_sampling_respond(id: String, approved: bool) -> bool {
+    sampling::respond(&id, approved)
+}
+
+/// Log of a server (stderr, its log messages and connection events), newer than sequence
+/// number `since` when given
+#[tauri::command]
+pub fn mcp_server_logs(name: String, since: Option<u64>) -> Vec<LogEntry> {
+    logs::since(&name, since)
+}
+
+/// Change the level of the log messages a connected server sends
+#[tauri::command]
+pub async fn mcp_set_log_level(name: String, level: String) -> Result<(), String> {
+    let client = McpClient::get_client(&name).ok_or_else(|| format!("MCP server '{}' is not connected", name))?;
+    client.set_log_level(&level).await.map_err(|e| format!("Failed to set log level of {}: {}", name, e))
+}
+
+#[tauri::command]
+pub async fn disconnect_mcp_server(name: String) -> Result<bool, String> {
+    supervisor::disconnect(&name).await
+        .map_err(|e| format!("Failed to disconnect MCP server {}: {}", name, e))
+}
+
+/// Reconnect a server with its saved configuration, or with the one it was connected with
+#[tauri::command]
+pub async fn restart_mcp_server(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    let saved = settings_get().await?.mcp_servers.into_iter().find(|s| s.name == name);
+    let config = saved
+        .or_else(|| McpClient::get_client(&name).map(|c| c.config().clone()))
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+
+    supervisor::disconnect(&name).await
+        .map_err(|e| format!("Failed to disconnect MCP server {}: {}", name, e))?;
+    supervisor::start(&app, config).await
+        .map_err(|e| format!("Failed to connect to MCP server {}: {}", name, e))?;
+    println!("Restarted MCP server: {}", name);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn mcp_server_list() -> Result<Vec<McpServerConfig>, String> {
+    Ok(settings_get().await?.mcp_servers)
+}
+
+#[tauri::command]
+pub async fn mcp_server_add(config: McpServerConfig) -> Result<Vec<McpServerConfig>, String> {
+    config.validate()?;
+    let mut settings = settings_get().await?;
+    if settings.mcp_servers.iter().any(|s| s.name == config.name) {
+        return Err(format!("MCP server '{}' already exists", config.name));
+    }
+
+    settings.mcp_servers.push(config);
+    settings_set(settings.clone()).await?;
+    Ok(settings.mcp_servers)
+}
+
+/// Save a changed configuration. The running connection keeps the old one until restarted.
+#[tauri::command]
+pub async fn mcp_server_update(config: McpServerConfig) -> Result<Vec<McpServerConfig>, String> {
+    config.validate()?;
+    let mut settings = settings_get().await?;
+    let pos = settings.mcp_servers.iter().position(|s| s.name == config.name)
+        .ok_or_else(|| format!("MCP server '{}' not found", config.name))?;
+
+    settings.mcp_servers[pos] = config;
+    settings_set(settings.clone()).await?;
+    Ok(settings.mcp_servers)
+}
+
+#[tauri::command]
+pub async fn mcp_server_delete(name: String) -> Result<Vec<McpServerConfig>, String> {
+    let mut settings = settings_get().await?;
+    settings.mcp_servers.retain(|s| s.name != name);
+    settings_set(settings.clone()).await?;
+
+    if let Err(e) = supervisor::disconnect(&name).await {
+        eprintln!("Failed to disconnect MCP server {}: {}", name, e);
+    }
+    Ok(settings.mcp_servers)
+}
+
+/// Read the file to import: `content` if given, else `path`, else the format's default location
+fn import_source(path: Option<String>, content: Option<String>, format: Option<ConfigFormat>) -> Result<(String, Option<PathBuf>), String> {
+    let path = path.map(PathBuf::from).or_else(|| format.and_then(ConfigFormat::default_path));
+    if let Some(content) = content {
+        return Ok((content, path));
+    }
+    let path = path.ok_or("A path to the config file is required")?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok((content, Some(path)))
+}
+
+/// Parse a Claude Desktop, Cursor or VS Code config without saving anything
+#[tauri::command]
+pub async fn mcp_import_preview(
+    path: Option<String>,
+    content: Option<String>,
+    format: Option<String>,
+    inputs: Option<HashMap<String, String>>,
+) -> Result<ImportPreview, String> {
+    let format = format.as_deref().map(ConfigFormat::parse).transpose()?;
+    let (content, path) = import_source(path, content, format)?;
+    let existing = settings_get().await?.mcp_servers;
+    config_files::parse(&content, path.as_deref(), format, &inputs.unwrap_or_default(), &existing)
+}
+
+/// Save the servers of a config file (only `names`, when given). Servers whose name is taken
+/// are skipped unless `replace_existing` is set.
+#[tauri::command]
+pub async fn mcp_import(
+    path: Option<String>,
+    content: Option<String>,
+    format: Option<String>,
+    inputs: Option<HashMap<String, String>>,
+    names: Option<Vec<String>>,
+    replace_existing: Option<bool>,
+) -> Result<Vec<McpServerConfig>, String> {
+    let format = format.as_deref().map(ConfigFormat::parse).transpose()?;
+    let (content, path) = import_source(path, content, format)?;
+    let mut settings = settings_get().await?;
+    let preview = config_files::parse(&content, path.as_deref(), format, &inputs.unwrap_or_default(), &settings.mcp_servers)?;
+
+    let selected = preview.servers.into_iter()
+        .filter(|s| names.as_ref().map_or(true, |names| names.contains(&s.config.name)));
+    for server in selected {
+        if let Some(placeholder) = server.unresolved.first() {
+            return Err(format!("MCP server '{}' still contains {}; provide a value for it", server.config.name, placeholder));
+        }
+        server.config.validate()?;
+        match settings.mcp_servers.iter().position(|s| s.name == server.config.name) {
+            Some(pos) if replace_existing.unwrap_or(false) => settings.mcp_servers[pos] = server.config,
+            Some(_) => println!("Skipping MCP server {}: already exists", server.config.name),
+            None => settings.mcp_servers.push(server.config),
+        }
+    }
+
+    settings_set(settings.clone()).await?;
+    Ok(settings.mcp_servers)
+}
+
+/// Render saved servers (only `names`, when given) in another client's format. With a `path`,
+/// the file is updated in place, keeping its other settings and servers.
+#[tauri::command]
+pub async fn mcp_export(format: String, names: Option<Vec<String>>, path: Option<String>) -> Result<ExportResult, String> {
+    let format = ConfigFormat::parse(&format)?;
+    let servers: Vec<McpServerConfig> = settings_get().await?.mcp_servers.into_iter()
+        .filter(|s| names.as_ref().map_or(true, |names| names.contains(&s.name)))
+        .collect();
+
+    let existing = match &path {
+        Some(path) if std::path::Path::new(path).exists() => Some(
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?,
+        ),
+        _ => None,
+    };
+    let mut result = config_files::export(&servers, format, existing.as_deref())?;
+
+    if let Some(path) = &path {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        std::fs::write(path, &result.content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    result.path = path;
+    Ok(result)
+}
+
+/// Connect every enabled saved server (at startup)
+pub async fn connect_saved_servers(app: tauri::AppHandle) {
+    let servers = match settings_get().await {
+        Ok(settings) => settings.mcp_servers,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let app = &app;
+    let connections = servers.iter().filter(|s| s.enabled).map(|config| async move {
+        match supervisor::start(app, config.clone()).await {
+            Ok(_) => println!("Connected to MCP server: {}", config.name),
+            Err(e) => eprintln!("Failed to connect to MCP server {}: {}", config.name, e),
+        }
+    });
+    futures::future::join_all(connections).await;
+}
+
 #[derive(serde::Serialize)]
 pub struct ToolInfo {
     pub server: String,
     pub name: String,
     pub description: Option<String>,
     pub schema: serde_json::Value,
+    /// Name the model sees, qualified by server
+    pub qualified_name: String,
+    pub display_name: String,
+    /// Other servers exposing a tool with the same name
+    pub conflicts_with: Vec<String>,
 }
 
 #[tauri::command]
 pub async fn list_tools() -> Result<Vec<ToolInfo>, String> {
-    let clients = McpClient::list_active_clients();
-    let mut all_tools = Vec::new();
-
-    for name in clients {
-        if let Some(client) = McpClient::get_client(&name) {
-            match client.list_tools().await {
-                Ok(tools) => {
-                    for tool in tools {
-                         all_tools.push(ToolInfo {
-                             server: name.clone(),
-                             name: tool.name,
-                             description: tool.description,
-                             schema: tool.input_schema,
-                         });
-                    }
-                }
-                Err(e) => {
-                    // Log error but continue
-                    eprintln!("Failed to list tools for {}: {}", name, e);
-                }
-            }
-        }
-    }
-    Ok(all_tools)
+    let reserved: Vec<&str> = crate::tools::all().iter().map(|t| t.name()).collect();
+    let tools = naming::list_named_tools(&reserved).await;
+    Ok(tools.into_iter().map(|named| ToolInfo {
+        server: named.server,
+        name: named.tool.name,
+        description: named.tool.description,
+        schema: named.tool.input_schema,
+        qualified_name: named.qualified_name,
+        display_name: named.display_name,
+        conflicts_with: named.conflicts_with,
+    }).collect())
+}
+
+/// Built-in tools, which can be enabled per chat through `ChatParams::native_tools`
+#[tauri::command]
+pub fn list_native_tools() -> Vec<ToolInfo> {
+    crate::tools::all().iter().map(|tool| ToolInfo {
+        server: "native".to_string(),
+        name: tool.name().to_string(),
+        description: Some(tool.description().to_string()),
+        schema: tool.input_schema(),
+        qualified_name: tool.name().to_string(),
+        display_name: tool.name().to_string(),
+        conflicts_with: Vec::new(),
+    }).collect()
 }
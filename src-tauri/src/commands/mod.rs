@@ -4,4 +4,5 @@ pub mod db;
 pub mod sys;
 pub mod settings;
 pub mod monitoring;
-pub mod mcp;
\ No newline at end of file
+pub mod mcp;
+pub mod workflows;
\ No newline at end of file
@@ -1,10 +1,13 @@
 use tauri::{AppHandle, Emitter};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::time;
 use sysinfo::System;
 use crate::commands::settings::get_ollama_url;
+use crate::providers::{scheduler, ProviderType};
 
 // System metrics structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,9 +47,44 @@ pub struct OllamaStatus {
     pub last_health_check: u64,
 }
 
+// Tool call telemetry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolMetrics {
+    pub tool_calls: u64,
+    pub validation_failures: u64,
+    pub validation_failures_by_tool: HashMap<String, u64>,
+}
+
 // Global monitoring state
 static MONITORING_ACTIVE: AtomicBool = AtomicBool::new(false);
 
+lazy_static::lazy_static! {
+    static ref TOOL_METRICS: Mutex<ToolMetrics> = Mutex::new(ToolMetrics::default());
+}
+
+// Count a tool call made by a model
+pub fn record_tool_call() {
+    if let Ok(mut metrics) = TOOL_METRICS.lock() {
+        metrics.tool_calls += 1;
+    }
+}
+
+// Count a tool call rejected because its arguments did not match the tool's schema
+pub fn record_tool_validation_failure(tool: &str) {
+    if let Ok(mut metrics) = TOOL_METRICS.lock() {
+        metrics.validation_failures += 1;
+        *metrics.validation_failures_by_tool.entry(tool.to_string()).or_insert(0) += 1;
+    }
+}
+
+// Get tool call telemetry since startup
+#[tauri::command]
+pub async fn get_tool_metrics() -> Result<ToolMetrics, String> {
+    TOOL_METRICS.lock()
+        .map(|m| m.clone())
+        .map_err(|e| e.to_string())
+}
+
 // Start system monitoring
 // Accept both snake_case (interval_ms) and camelCase (intervalMs) for convenience
 #[tauri::command]
@@ -216,24 +254,27 @@ async fn collect_ollama_status() -> Result<OllamaStatus, String> {
             } else {
                 vec![]
             };
+
+            let (active_streams, queue_length) = scheduler::stats(&ProviderType::Ollama);
             
             Ok(OllamaStatus {
                 version,
                 uptime: 3600, // Mock uptime - would need to track actual start time
                 models_loaded,
-                active_streams: 0, // Would need to track active streams
-                queue_length: 0,   // Would need to track queue
+                active_streams,
+                queue_length,
                 server_health: "healthy".to_string(),
                 last_health_check: timestamp,
             })
         }
         Err(_) => {
+            let (active_streams, queue_length) = scheduler::stats(&ProviderType::Ollama);
             Ok(OllamaStatus {
                 version: "unknown".to_string(),
                 uptime: 0,
                 models_loaded: vec![],
-                active_streams: 0,
-                queue_length: 0,
+                active_streams,
+                queue_length,
                 server_health: "error".to_string(),
                 last_health_check: timestamp,
             })
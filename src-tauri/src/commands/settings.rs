@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use crate::mcp::config::McpServerConfig;
 use crate::providers::ProviderConfig;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,6 +28,9 @@ pub struct Settings {
     /// Whether initial setup wizard has been completed
     #[serde(default)]
     pub setup_completed: bool,
+    /// Saved MCP servers; enabled ones are connected on startup
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerConfig>,
 }
 
 fn default_app_mode() -> String {
@@ -34,7 +38,7 @@ fn default_app_mode() -> String {
 }
 
 
-fn config_dir() -> Result<PathBuf, String> {
+pub fn config_dir() -> Result<PathBuf, String> {
     let home = std::env::var("HOME").map_err(|e| format!("Cannot read HOME: {}", e))?;
     let dir = PathBuf::from(home).join(".config").join("ollie");
     if !dir.exists() {
@@ -90,6 +94,7 @@ pub async fn settings_get() -> Result<Settings, String> {
             active_provider_id: Some("ollama-default".to_string()),
             app_mode: "local".to_string(),
             setup_completed: false,
+            mcp_servers: Vec::new(),
         });
 
     }
@@ -187,3 +192,13 @@ pub async fn provider_get_active() -> Result<ProviderConfig, String> {
         .find(|p| p.id == active_id)
         .ok_or_else(|| "Active provider not found".to_string())
 }
+
+/// Look up a configured provider by id, falling back to its display name
+pub async fn provider_get(id_or_name: &str) -> Result<ProviderConfig, String> {
+    let settings = settings_get().await?;
+    let providers = settings.providers;
+    let by_id = providers.iter().position(|p| p.id == id_or_name);
+    let index = by_id.or_else(|| providers.iter().position(|p| p.name.eq_ignore_ascii_case(id_or_name)));
+    index.map(|i| providers[i].clone())
+        .ok_or_else(|| format!("Provider '{}' not found", id_or_name))
+}
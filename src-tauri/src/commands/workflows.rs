@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, SqlitePool};
+use uuid::Uuid;
+use crate::db::get_pool;
+use crate::workflows::{engine, Workflow};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WorkflowRow {
+    pub id: String,
+    pub name: String,
+    /// Definition as written by the user (YAML or JSON)
+    pub definition: String,
+    pub format: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WorkflowRunRow {
+    pub id: String,
+    pub workflow_id: Option<String>,
+    pub workflow_name: String,
+    /// running, completed, failed, cancelled or interrupted (the app quit while it ran)
+    pub status: String,
+    pub inputs_json: Option<String>,
+    pub outputs_json: Option<String>,
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WorkflowStepRow {
+    pub id: String,
+    pub run_id: String,
+    pub step_id: String,
+    /// Index into the list of a `for_each` step
+    pub iteration: Option<i64>,
+    /// completed, skipped, failed or cancelled
+    pub status: String,
+    pub prompt: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkflowRunDetail {
+    pub run: WorkflowRunRow,
+    pub steps: Vec<WorkflowStepRow>,
+}
+
+pub async fn insert_run(pool: &SqlitePool, workflow_id: Option<&str>, workflow_name: &str, inputs: &Value) -> Result<String, String> {
+    let id = Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO workflow_runs (id, workflow_id, workflow_name, status, inputs_json, started_at) VALUES (?, ?, ?, 'running', ?, ?)")
+        .bind(&id)
+        .bind(workflow_id)
+        .bind(workflow_name)
+        .bind(inputs.to_string())
+        .bind(chrono::Utc::now().timestamp_millis())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("create workflow run failed: {}", e))?;
+    Ok(id)
+}
+
+pub async fn finish_run(pool: &SqlitePool, run_id: &str, status: &str, outputs: &Value, error: Option<&str>) -> Result<(), String> {
+    sqlx::query("UPDATE workflow_runs SET status = ?, outputs_json = ?, error = ?, finished_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(outputs.to_string())
+        .bind(error)
+        .bind(chrono::Utc::now().timestamp_millis())
+        .bind(run_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("finish workflow run failed: {}", e))?;
+    Ok(())
+}
+
+/// One execution of a step (or one iteration of a mapped step)
+pub struct StepRecord<'a> {
+    pub step_id: &'a str,
+    pub iteration: Option<usize>,
+    pub status: &'a str,
+    pub prompt: Option<&'a str>,
+    pub output: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub started_at: i64,
+}
+
+pub async fn insert_step(pool: &SqlitePool, run_id: &str, record: &StepRecord<'_>) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO workflow_run_steps (id, run_id, step_id, iteration, status, prompt, output, error, started_at, finished_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(run_id)
+    .bind(record.step_id)
+    .bind(record.iteration.map(|i| i as i64))
+    .bind(record.status)
+    .bind(record.prompt)
+    .bind(record.output)
+    .bind(record.error)
+    .bind(record.started_at)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("record workflow step failed: {}", e))?;
+    Ok(())
+}
+
+/// Save a workflow definition; updates the existing row when `id` is given
+#[tauri::command]
+pub async fn workflow_save(id: Option<String>, definition: String, format: Option<String>) -> Result<WorkflowRow, String> {
+    let workflow = Workflow::parse(&definition, format.as_deref())?;
+    let format = format.unwrap_or_else(|| if definition.trim_start().starts_with('{') { "json" } else { "yaml" }.to_string());
+    let pool = get_pool().await?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    if let Some(id) = id {
+        let res = sqlx::query("UPDATE workflows SET name = ?, definition = ?, format = ?, updated_at = ? WHERE id = ?")
+            .bind(&workflow.name)
+            .bind(&definition)
+            .bind(&format)
+            .bind(now)
+            .bind(&id)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("update workflow failed: {}", e))?;
+        if res.rows_affected() == 0 {
+            return Err(format!("Workflow '{}' not found", id));
+        }
+        return workflow_get(id).await;
+    }
+
+    let id = Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO workflows (id, name, definition, format, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(&id)
+        .bind(&workflow.name)
+        .bind(&definition)
+        .bind(&format)
+        .bind(now)
+        .bind(now)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("save workflow failed: {}", e))?;
+    Ok(WorkflowRow { id, name: workflow.name, definition, format, created_at: now, updated_at: now })
+}
+
+#[tauri::command]
+pub async fn workflow_get(id: String) -> Result<WorkflowRow, String> {
+    let pool = get_pool().await?;
+    sqlx::query_as::<_, WorkflowRow>("SELECT id, name, definition, format, created_at, updated_at FROM workflows WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| format!("get workflow failed: {}", e))?
+        .ok_or_else(|| format!("Workflow '{}' not found", id))
+}
+
+#[tauri::command]
+pub async fn workflow_list() -> Result<Vec<WorkflowRow>, String> {
+    let pool = get_pool().await?;
+    sqlx::query_as::<_, WorkflowRow>("SELECT id, name, definition, format, created_at, updated_at FROM workflows ORDER BY name ASC")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| format!("list workflows failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn workflow_delete(id: String) -> Result<bool, String> {
+    let pool = get_pool().await?;
+    let res = sqlx::query("DELETE FROM workflows WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("delete workflow failed: {}", e))?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Start a run of a saved workflow (`id`) or of an unsaved `definition`.
+/// Returns the run id; progress arrives as `workflow:*` events.
+#[tauri::command]
+pub async fn workflow_run(
+    app: tauri::AppHandle,
+    id: Option<String>,
+    definition: Option<String>,
+    format: Option<String>,
+    inputs: Option<serde_json::Map<String, Value>>,
+) -> Result<String, String> {
+    let (workflow_id, workflow) = match (id, definition) {
+        (Some(id), _) => {
+            let row = workflow_get(id).await?;
+            (Some(row.id), Workflow::parse(&row.definition, Some(&row.format))?)
+        }
+        (None, Some(definition)) => (None, Workflow::parse(&definition, format.as_deref())?),
+        (None, None) => return Err("Either a workflow id or a definition is required".to_string()),
+    };
+    engine::start(app, workflow_id, workflow, inputs.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn workflow_cancel(run_id: String) -> Result<bool, String> {
+    Ok(engine::cancel(&run_id).await)
+}
+
+#[tauri::command]
+pub async fn workflow_list_runs(workflow_id: Option<String>, limit: Option<i64>) -> Result<Vec<WorkflowRunRow>, String> {
+    let pool = get_pool().await?;
+    sqlx::query_as::<_, WorkflowRunRow>(
+        r#"SELECT id, workflow_id, workflow_name, status, inputs_json, outputs_json, error, started_at, finished_at
+           FROM workflow_runs WHERE (? IS NULL OR workflow_id = ?) ORDER BY started_at DESC LIMIT ?"#
+    )
+    .bind(&workflow_id)
+    .bind(&workflow_id)
+    .bind(limit.unwrap_or(50))
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("list workflow runs failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn workflow_get_run(run_id: String) -> Result<WorkflowRunDetail, String> {
+    let pool = get_pool().await?;
+    let run = sqlx::query_as::<_, WorkflowRunRow>(
+        r#"SELECT id, workflow_id, workflow_name, status, inputs_json, outputs_json, error, started_at, finished_at
+           FROM workflow_runs WHERE id = ?"#
+    )
+    .bind(&run_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| format!("get workflow run failed: {}", e))?
+    .ok_or_else(|| format!("Workflow run '{}' not found", run_id))?;
+    let steps = sqlx::query_as::<_, WorkflowStepRow>(
+        r#"SELECT id, run_id, step_id, iteration, status, prompt, output, error, started_at, finished_at
+           FROM workflow_run_steps WHERE run_id = ? ORDER BY started_at ASC, iteration ASC"#
+    )
+    .bind(&run_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("list workflow steps failed: {}", e))?;
+    Ok(WorkflowRunDetail { run, steps })
+}
@@ -73,6 +73,82 @@ pub async fn get_pool() -> Result<SqlitePool, String> {
 		)"#
 	).execute(&pool).await.map_err(|e| format!("DB migrate messages failed: {}", e))?;
 
+	// Migration: message status for crash-safe streaming drafts (NULL for rows written before it existed)
+	let _ = sqlx::query("ALTER TABLE messages ADD COLUMN status TEXT").execute(&pool).await;
+
+	// Migration: chats run by the delegate tool link back to the chat and assistant message that started them
+	let _ = sqlx::query("ALTER TABLE chats ADD COLUMN parent_chat_id TEXT REFERENCES chats(id) ON DELETE CASCADE").execute(&pool).await;
+	let _ = sqlx::query("ALTER TABLE chats ADD COLUMN parent_message_id TEXT").execute(&pool).await;
+
+	// Full text of truncated tool results, paged through with the read_tool_output tool.
+	// chat_id is NULL for conversations that are not persisted.
+	sqlx::query(
+		r#"CREATE TABLE IF NOT EXISTS tool_outputs (
+			id TEXT PRIMARY KEY,
+			chat_id TEXT,
+			tool_name TEXT NOT NULL,
+			content TEXT NOT NULL,
+			created_at INTEGER NOT NULL,
+			FOREIGN KEY(chat_id) REFERENCES chats(id) ON DELETE CASCADE
+		)"#
+	).execute(&pool).await.map_err(|e| format!("DB migrate tool_outputs failed: {}", e))?;
+
+	// MCP resources attached to a chat as context, refreshed when the server reports a change
+	sqlx::query(
+		r#"CREATE TABLE IF NOT EXISTS chat_resources (
+			chat_id TEXT NOT NULL,
+			server TEXT NOT NULL,
+			uri TEXT NOT NULL,
+			name TEXT,
+			contents_json TEXT NOT NULL,
+			attached_at INTEGER NOT NULL,
+			updated_at INTEGER NOT NULL,
+			PRIMARY KEY (chat_id, server, uri),
+			FOREIGN KEY(chat_id) REFERENCES chats(id) ON DELETE CASCADE
+		)"#
+	).execute(&pool).await.map_err(|e| format!("DB migrate chat_resources failed: {}", e))?;
+
+	// Workflow definitions and the record of every run and step
+	sqlx::query(
+		r#"CREATE TABLE IF NOT EXISTS workflows (
+			id TEXT PRIMARY KEY,
+			name TEXT NOT NULL,
+			definition TEXT NOT NULL,
+			format TEXT NOT NULL,
+			created_at INTEGER NOT NULL,
+			updated_at INTEGER NOT NULL
+		)"#
+	).execute(&pool).await.map_err(|e| format!("DB migrate workflows failed: {}", e))?;
+	sqlx::query(
+		r#"CREATE TABLE IF NOT EXISTS workflow_runs (
+			id TEXT PRIMARY KEY,
+			workflow_id TEXT,
+			workflow_name TEXT NOT NULL,
+			status TEXT NOT NULL,
+			inputs_json TEXT,
+			outputs_json TEXT,
+			error TEXT,
+			started_at INTEGER NOT NULL,
+			finished_at INTEGER,
+			FOREIGN KEY(workflow_id) REFERENCES workflows(id) ON DELETE SET NULL
+		)"#
+	).execute(&pool).await.map_err(|e| format!("DB migrate workflow_runs failed: {}", e))?;
+	sqlx::query(
+		r#"CREATE TABLE IF NOT EXISTS workflow_run_steps (
+			id TEXT PRIMARY KEY,
+			run_id TEXT NOT NULL,
+			step_id TEXT NOT NULL,
+			iteration INTEGER,
+			status TEXT NOT NULL,
+			prompt TEXT,
+			output TEXT,
+			error TEXT,
+			started_at INTEGER NOT NULL,
+			finished_at INTEGER,
+			FOREIGN KEY(run_id) REFERENCES workflow_runs(id) ON DELETE CASCADE
+		)"#
+	).execute(&pool).await.map_err(|e| format!("DB migrate workflow_run_steps failed: {}", e))?;
+
 	*guard = Some(pool.clone());
 	Ok(pool)
 }
@@ -87,3 +163,38 @@ pub async fn touch_chat_updated(pool: &SqlitePool, chat_id: &str) -> Result<(),
 		.map_err(|e| format!("Failed to update chat: {}", e))?;
 	Ok(())
 }
+
+/// Mark assistant drafts left in the `streaming` state by a crash or reload as `interrupted`,
+/// so the UI can offer to resume or regenerate them. Called once on startup.
+pub async fn mark_interrupted_drafts() -> Result<u64, String> {
+	let pool = get_pool().await?;
+	let res = sqlx::query("UPDATE messages SET status = 'interrupted' WHERE status = 'streaming'")
+		.execute(&pool)
+		.await
+		.map_err(|e| format!("Failed to mark interrupted drafts: {}", e))?;
+	Ok(res.rows_affected())
+}
+
+/// Mark workflow runs left `running` by a crash or quit as `interrupted`. Called once on startup.
+pub async fn mark_interrupted_workflow_runs() -> Result<u64, String> {
+	let pool = get_pool().await?;
+	let res = sqlx::query("UPDATE workflow_runs SET status = 'interrupted', finished_at = ? WHERE status = 'running'")
+		.bind(chrono::Utc::now().timestamp_millis())
+		.execute(&pool)
+		.await
+		.map_err(|e| format!("Failed to mark interrupted workflow runs: {}", e))?;
+	Ok(res.rows_affected())
+}
+
+/// Drop stored tool outputs of unsaved conversations older than `max_age_days`.
+/// Outputs of saved chats go away with the chat.
+pub async fn prune_tool_outputs(max_age_days: i64) -> Result<u64, String> {
+	let pool = get_pool().await?;
+	let cutoff = chrono::Utc::now().timestamp_millis() - max_age_days * 24 * 60 * 60 * 1000;
+	let res = sqlx::query("DELETE FROM tool_outputs WHERE chat_id IS NULL AND created_at < ?")
+		.bind(cutoff)
+		.execute(&pool)
+		.await
+		.map_err(|e| format!("Failed to prune tool outputs: {}", e))?;
+	Ok(res.rows_affected())
+}
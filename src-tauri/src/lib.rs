@@ -2,6 +2,8 @@ mod commands;
 mod db;
 mod mcp;
 mod providers;
+mod tools;
+mod workflows;
 
 use tauri::Manager;
 
@@ -16,6 +18,10 @@ pub fn run() {
       commands::sys::stop_ollama_service,
       commands::chat::chat_stream,
       commands::chat::chat_cancel,
+      commands::chat::chat_list_runs,
+      commands::chat::chat_run_buffer,
+      commands::chat::chat_reattach,
+      commands::chat::chat_resume,
       commands::models::models_list,
       commands::models::model_pull,
       commands::models::model_pull_cancel,
@@ -33,17 +39,55 @@ pub fn run() {
       commands::db::db_set_chat_model,
       commands::db::db_set_chat_title,
       commands::db::db_list_chats_with_flags,
+      commands::db::db_get_chat_params,
+      commands::db::db_set_chat_params,
+      commands::db::db_list_child_chats,
+      commands::workflows::workflow_save,
+      commands::workflows::workflow_get,
+      commands::workflows::workflow_list,
+      commands::workflows::workflow_delete,
+      commands::workflows::workflow_run,
+      commands::workflows::workflow_cancel,
+      commands::workflows::workflow_list_runs,
+      commands::workflows::workflow_get_run,
       commands::monitoring::start_system_monitoring,
       commands::monitoring::stop_system_monitoring,
       commands::monitoring::get_system_metrics,
       commands::monitoring::get_model_metrics,
       commands::monitoring::get_ollama_status,
+      commands::monitoring::get_tool_metrics,
       commands::monitoring::ollama_ps,
       commands::monitoring::stop_model,
       commands::mcp::connect_mcp_server,
       commands::mcp::connect_mcp_http,
       commands::mcp::list_mcp_servers,
+      commands::mcp::mcp_server_statuses,
+      commands::mcp::mcp_server_logs,
+      commands::mcp::mcp_set_log_level,
+      commands::mcp::mcp_list_resources,
+      commands::mcp::mcp_list_resource_templates,
+      commands::mcp::mcp_read_resource,
+      commands::mcp::mcp_subscribe_resource,
+      commands::mcp::mcp_unsubscribe_resource,
+      commands::mcp::chat_attach_resource,
+      commands::mcp::chat_detach_resource,
+      commands::mcp::chat_list_resources,
+      commands::mcp::mcp_list_prompts,
+      commands::mcp::mcp_get_prompt,
+      commands::mcp::mcp_complete,
+      commands::mcp::chat_insert_prompt,
+      commands::mcp::mcp_sampling_respond,
       commands::mcp::list_tools,
+      commands::mcp::list_native_tools,
+      commands::mcp::disconnect_mcp_server,
+      commands::mcp::restart_mcp_server,
+      commands::mcp::mcp_server_list,
+      commands::mcp::mcp_server_add,
+      commands::mcp::mcp_server_update,
+      commands::mcp::mcp_server_delete,
+      commands::mcp::mcp_import_preview,
+      commands::mcp::mcp_import,
+      commands::mcp::mcp_export,
       commands::settings::provider_add,
       commands::settings::provider_update,
       commands::settings::provider_delete,
@@ -60,6 +104,26 @@ pub fn run() {
         )?;
       }
 
+      // Before any command runs, so a reply or workflow that starts now is not caught by it
+      tauri::async_runtime::block_on(async {
+        match db::mark_interrupted_drafts().await {
+          Ok(n) if n > 0 => println!("Marked {} interrupted draft message(s)", n),
+          Ok(_) => {}
+          Err(e) => eprintln!("{}", e),
+        }
+        match db::mark_interrupted_workflow_runs().await {
+          Ok(n) if n > 0 => println!("Marked {} interrupted workflow run(s)", n),
+          Ok(_) => {}
+          Err(e) => eprintln!("{}", e),
+        }
+      });
+      tauri::async_runtime::spawn(async {
+        if let Err(e) = db::prune_tool_outputs(7).await {
+          eprintln!("{}", e);
+        }
+      });
+      tauri::async_runtime::spawn(commands::mcp::connect_saved_servers(app.handle().clone()));
+
       app.manage(std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::<String, std::sync::Arc<std::sync::atomic::AtomicBool>>::new())));
       Ok(())
     })
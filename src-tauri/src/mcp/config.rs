@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A saved MCP server: a command to spawn (stdio) or a URL (Streamable HTTP or HTTP+SSE)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Unique; also the key of the connected client and the prefix of its tool names
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for the spawned process
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Connected on startup when set
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Minimum level of the log messages the server should send (servers default to their own)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+    /// How `sampling/createMessage` requests of the server are answered
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Lets a server ask one of our models for a completion (MCP sampling)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// Advertise the sampling capability to the server
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Provider to generate with; the active provider when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    /// Model used when none of the server's model hints matches; the default model when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Ask the user before each request is sent to the model
+    #[serde(default = "default_enabled")]
+    pub require_approval: bool,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            provider_id: None,
+            model: None,
+            require_approval: true,
+        }
+    }
+}
+
+impl McpServerConfig {
+    pub fn stdio(name: &str, command: &str, args: &[String]) -> Self {
+        Self {
+            name: name.to_string(),
+            command: Some(command.to_string()),
+            args: args.to_vec(),
+            env: HashMap::new(),
+            cwd: None,
+            url: None,
+            auth_token: None,
+            enabled: true,
+            log_level: None,
+            sampling: SamplingConfig::default(),
+        }
+    }
+
+    pub fn http(name: &str, url: &str, auth_token: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            url: Some(url.to_string()),
+            auth_token,
+            enabled: true,
+            log_level: None,
+            sampling: SamplingConfig::default(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("MCP server needs a name".to_string());
+        }
+        match (&self.command, &self.url) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err(format!("MCP server '{}' needs either a command or a URL", self.name)),
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! MCP server definitions in other clients' config files: the `mcpServers` block of Claude
+//! Desktop's `claude_desktop_config.json` and Cursor's `mcp.json`, and the `servers` block
+//! (plus `inputs`) of VS Code's `.vscode/mcp.json`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::mcp::config::{McpServerConfig, SamplingConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    ClaudeDesktop,
+    Cursor,
+    VsCode,
+}
+
+impl ConfigFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "claude_desktop" | "claude" => Ok(Self::ClaudeDesktop),
+            "cursor" => Ok(Self::Cursor),
+            "vs_code" | "vscode" => Ok(Self::VsCode),
+            other => Err(format!("Unknown config format '{}' (expected claude_desktop, cursor or vscode)", other)),
+        }
+    }
+
+    /// Guess from the file name, then from the top-level keys
+    fn detect(path: Option<&Path>, root: &Value) -> Self {
+        let path = path.map(|p| p.to_string_lossy().replace('\\', "/")).unwrap_or_default();
+        if path.contains(".vscode/") || root.get("servers").is_some() || root.get("mcp").is_some() {
+            Self::VsCode
+        } else if path.contains(".cursor/") {
+            Self::Cursor
+        } else {
+            Self::ClaudeDesktop
+        }
+    }
+
+    /// Where the user-level file lives. VS Code's file belongs to a workspace, so it has none.
+    pub fn default_path(self) -> Option<PathBuf> {
+        let home = PathBuf::from(std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()?);
+        match self {
+            Self::ClaudeDesktop if cfg!(target_os = "macos") => {
+                Some(home.join("Library/Application Support/Claude/claude_desktop_config.json"))
+            }
+            Self::ClaudeDesktop if cfg!(target_os = "windows") => {
+                let appdata = std::env::var("APPDATA").map(PathBuf::from).unwrap_or_else(|_| home.join("AppData/Roaming"));
+                Some(appdata.join("Claude").join("claude_desktop_config.json"))
+            }
+            Self::ClaudeDesktop => Some(home.join(".config/Claude/claude_desktop_config.json")),
+            Self::Cursor => Some(home.join(".cursor").join("mcp.json")),
+            Self::VsCode => None,
+        }
+    }
+}
+
+/// A VS Code `inputs` entry: a value the user is asked for, referenced as `${input:id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigInput {
+    pub id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub password: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedServer {
+    pub config: McpServerConfig,
+    /// Placeholders left in the config because no value was given for them
+    pub unresolved: Vec<String>,
+    /// Settings that have no equivalent here and were dropped
+    pub warnings: Vec<String>,
+    /// A saved server already has this name
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub format: ConfigFormat,
+    pub path: Option<String>,
+    pub servers: Vec<ImportedServer>,
+    pub inputs: Vec<ConfigInput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportResult {
+    pub format: ConfigFormat,
+    /// Written to `path` when one was given
+    pub path: Option<String>,
+    pub content: String,
+    /// Servers the format cannot express
+    pub skipped: Vec<String>,
+    /// Secrets written into the file as plain text
+    pub warnings: Vec<String>,
+}
+
+/// Parse a config file's servers. `inputs` supplies values for `${input:id}` placeholders;
+/// `${env:NAME}`, `${userHome}` and `${workspaceFolder}` are resolved as well.
+pub fn parse(
+    text: &str,
+    path: Option<&Path>,
+    format: Option<ConfigFormat>,
+    inputs: &HashMap<String, String>,
+    existing: &[McpServerConfig],
+) -> Result<ImportPreview, String> {
+    let root: Value = serde_json::from_str(&strip_jsonc(text)).map_err(|e| format!("Invalid config JSON: {}", e))?;
+    let format = format.unwrap_or_else(|| ConfigFormat::detect(path, &root));
+
+    let (servers, declared_inputs) = match format {
+        ConfigFormat::VsCode => {
+            // Also accept a settings.json with an "mcp" section
+            let section = root.get("mcp").unwrap_or(&root);
+            (section.get("servers"), section.get("inputs"))
+        }
+        _ => (root.get("mcpServers"), None),
+    };
+    let servers = servers.and_then(|s| s.as_object())
+        .ok_or_else(|| format!("No {} block found", if format == ConfigFormat::VsCode { "\"servers\"" } else { "\"mcpServers\"" }))?;
+    let declared_inputs: Vec<ConfigInput> = declared_inputs
+        .and_then(|i| serde_json::from_value(i.clone()).ok())
+        .unwrap_or_default();
+
+    let resolver = Resolver { inputs, workspace: path.and_then(workspace_folder) };
+    let servers = servers.iter()
+        .map(|(name, entry)| {
+            let mut server = convert(name, entry, &resolver);
+            server.exists = existing.iter().any(|s| s.name == *name);
+            server
+        })
+        .collect();
+
+    Ok(ImportPreview {
+        format,
+        path: path.map(|p| p.to_string_lossy().to_string()),
+        servers,
+        inputs: declared_inputs,
+    })
+}
+
+fn convert(name: &str, entry: &Value, resolver: &Resolver) -> ImportedServer {
+    let mut unresolved = Vec::new();
+    let mut warnings = Vec::new();
+    let mut text = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(|s| resolver.resolve(s, &mut unresolved));
+
+    let command = text("command");
+    let url = text("url");
+    let cwd = text("cwd");
+    let args = entry.get("args").and_then(|a| a.as_array())
+        .map(|args| args.iter().filter_map(|a| a.as_str()).map(|a| resolver.resolve(a, &mut unresolved)).collect())
+        .unwrap_or_default();
+    let env = entry.get("env").and_then(|e| e.as_object())
+        .map(|env| env.iter()
+            .map(|(k, v)| (k.clone(), resolver.resolve(&value_text(v), &mut unresolved)))
+            .collect())
+        .unwrap_or_default();
+
+    let mut auth_token = None;
+    for (header, value) in entry.get("headers").and_then(|h| h.as_object()).into_iter().flatten() {
+        let value = resolver.resolve(&value_text(value), &mut unresolved);
+        match value.strip_prefix("Bearer ") {
+            Some(token) if header.eq_ignore_ascii_case("authorization") => auth_token = Some(token.to_string()),
+            _ => warnings.push(format!("Header '{}' is not supported and was dropped", header)),
+        }
+    }
+    if entry.get("envFile").is_some() {
+        warnings.push("envFile is not supported; copy its variables into env".to_string());
+    }
+    if command.is_none() && url.is_none() {
+        warnings.push("Entry has neither a command nor a url".to_string());
+    }
+
+    unresolved.sort();
+    unresolved.dedup();
+    ImportedServer {
+        config: McpServerConfig {
+            name: name.to_string(),
+            command,
+            args,
+            env,
+            cwd,
+            // A command wins if an entry has both
+            url: url.filter(|_| entry.get("command").is_none()),
+            auth_token,
+            enabled: !entry.get("disabled").and_then(|d| d.as_bool()).unwrap_or(false),
+            log_level: None,
+            sampling: SamplingConfig::default(),
+        },
+        unresolved,
+        warnings,
+        exists: false,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct Resolver<'a> {
+    inputs: &'a HashMap<String, String>,
+    workspace: Option<String>,
+}
+
+impl Resolver<'_> {
+    /// Replace the `${...}` placeholders we can; the rest stay and are recorded in `unresolved`
+    fn resolve(&self, text: &str, unresolved: &mut Vec<String>) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            let Some(len) = rest[start + 2..].find('}') else { break };
+            out.push_str(&rest[..start]);
+            let placeholder = &rest[start..start + 2 + len + 1];
+            let key = &rest[start + 2..start + 2 + len];
+            let value = if let Some(id) = key.strip_prefix("input:") {
+                self.inputs.get(id).cloned()
+            } else if let Some(var) = key.strip_prefix("env:") {
+                std::env::var(var).ok()
+            } else if key == "userHome" {
+                std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()
+            } else if key == "workspaceFolder" {
+                self.workspace.clone()
+            } else {
+                None
+            };
+            match value {
+                Some(value) => out.push_str(&value),
+                None => {
+                    out.push_str(placeholder);
+                    unresolved.push(placeholder.to_string());
+                }
+            }
+            rest = &rest[start + 2 + len + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// The folder a `.vscode/mcp.json` or `.cursor/mcp.json` belongs to
+fn workspace_folder(path: &Path) -> Option<String> {
+    let dir = path.parent()?;
+    let name = dir.file_name()?.to_string_lossy();
+    (name == ".vscode" || name == ".cursor").then(|| dir.parent().map(|p| p.to_string_lossy().to_string()))?
+}
+
+/// Whether an environment variable probably holds a secret, judging by its name
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    ["KEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL", "AUTH"].iter().any(|part| name.contains(part))
+}
+
+/// Render servers in a client's format. When `existing` (the current file) is given, its other
+/// settings and servers are kept and same-named servers are replaced. VS Code gets secrets (auth
+/// tokens and env vars named like one) as password `inputs` it asks the user for; the other
+/// formats have no such thing, so they are written as they are and reported in `warnings`.
+pub fn export(servers: &[McpServerConfig], format: ConfigFormat, existing: Option<&str>) -> Result<ExportResult, String> {
+    let mut root = match existing.map(strip_jsonc) {
+        Some(text) if !text.trim().is_empty() => serde_json::from_str::<Value>(&text)
+            .map_err(|e| format!("Existing config is not valid JSON: {}", e))?,
+        _ => json!({}),
+    };
+    let root_map = root.as_object_mut().ok_or("Existing config is not a JSON object")?;
+    let key = if format == ConfigFormat::VsCode { "servers" } else { "mcpServers" };
+    let mut block = match root_map.remove(key) {
+        Some(Value::Object(block)) => block,
+        Some(_) => return Err(format!("\"{}\" is not a JSON object", key)),
+        None => Map::new(),
+    };
+    let mut inputs = match root_map.remove("inputs") {
+        Some(Value::Array(inputs)) => inputs,
+        Some(_) => return Err("\"inputs\" is not a JSON array".to_string()),
+        None => Vec::new(),
+    };
+
+    let mut skipped = Vec::new();
+    let mut warnings = Vec::new();
+    for server in servers {
+        // Replace a secret by an `${input:id}` reference (VS Code) or warn that it is exported
+        let mut secret = |id: String, description: String, value: &str| -> String {
+            if format != ConfigFormat::VsCode {
+                warnings.push(format!("{} is written as plain text", description));
+                return value.to_string();
+            }
+            inputs.retain(|input| input.get("id").and_then(|i| i.as_str()) != Some(id.as_str()));
+            let reference = format!("${{input:{}}}", id);
+            inputs.push(json!({ "type": "promptString", "id": id, "description": description, "password": true }));
+            reference
+        };
+
+        let mut entry = Map::new();
+        if let Some(command) = &server.command {
+            if format == ConfigFormat::VsCode {
+                entry.insert("type".to_string(), json!("stdio"));
+            }
+            entry.insert("command".to_string(), json!(command));
+            entry.insert("args".to_string(), json!(server.args));
+            if !server.env.is_empty() {
+                // Sorted, so the inputs come out in a stable order
+                let mut vars: Vec<_> = server.env.iter().collect();
+                vars.sort();
+                let env: Map<String, Value> = vars.into_iter()
+                    .map(|(name, value)| {
+                        let value = if is_secret_name(name) && !value.contains("${") {
+                            secret(format!("{}-{}", server.name, name.to_lowercase()), format!("{} of {}", name, server.name), value)
+                        } else {
+                            value.clone()
+                        };
+                        (name.clone(), json!(value))
+                    })
+                    .collect();
+                entry.insert("env".to_string(), Value::Object(env));
+            }
+            if let Some(cwd) = &server.cwd {
+                entry.insert("cwd".to_string(), json!(cwd));
+            }
+        } else if let Some(url) = &server.url {
+            // Claude Desktop only launches local commands
+            if format == ConfigFormat::ClaudeDesktop {
+                skipped.push(server.name.clone());
+                continue;
+            }
+            if format == ConfigFormat::VsCode {
+                entry.insert("type".to_string(), json!("http"));
+            }
+            entry.insert("url".to_string(), json!(url));
+            if let Some(token) = &server.auth_token {
+                let token = secret(format!("{}-token", server.name), format!("Auth token of {}", server.name), token);
+                entry.insert("headers".to_string(), json!({ "Authorization": format!("Bearer {}", token) }));
+            }
+        }
+        if !server.enabled {
+            entry.insert("disabled".to_string(), json!(true));
+        }
+        block.insert(server.name.clone(), Value::Object(entry));
+    }
+
+    root_map.insert(key.to_string(), Value::Object(block));
+    if !inputs.is_empty() {
+        root_map.insert("inputs".to_string(), Value::Array(inputs));
+    }
+    let content = serde_json::to_string_pretty(&root).map_err(|e| format!("Serialize config failed: {}", e))?;
+    Ok(ExportResult { format, path: None, content, skipped, warnings })
+}
+
+/// Drop `//` and `/* */` comments and trailing commas, which VS Code's JSONC files allow
+fn strip_jsonc(text: &str) -> String {
+    let without_comments = scan_outside_strings(text, |chars, i, out| {
+        match (chars[i], chars.get(i + 1)) {
+            ('/', Some('/')) => chars[i..].iter().position(|c| *c == '\n').map_or(chars.len(), |n| i + n),
+            ('/', Some('*')) => chars[i + 2..].windows(2).position(|w| w == ['*', '/']).map_or(chars.len(), |n| i + 2 + n + 2),
+            (c, _) => {
+                out.push(c);
+                i + 1
+            }
+        }
+    });
+    scan_outside_strings(&without_comments, |chars, i, out| {
+        let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+        if chars[i] != ',' || !matches!(next, Some('}') | Some(']')) {
+            out.push(chars[i]);
+        }
+        i + 1
+    })
+}
+
+/// Copy `text`, letting `step` handle everything outside string literals. `step` gets the
+/// characters, the current index and the output, and returns the index to continue at.
+fn scan_outside_strings(text: &str, mut step: impl FnMut(&[char], usize, &mut String) -> usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '"' {
+            i = step(&chars, i, &mut out);
+            continue;
+        }
+        // Copy the whole string literal, escapes included
+        out.push('"');
+        i += 1;
+        while i < chars.len() {
+            out.push(chars[i]);
+            if chars[i] == '\\' && i + 1 < chars.len() {
+                out.push(chars[i + 1]);
+                i += 1;
+            } else if chars[i] == '"' {
+                break;
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(config: Value) -> McpServerConfig {
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn vscode_export_turns_secrets_into_inputs() {
+        let servers = [
+            server(json!({ "name": "gh", "command": "gh-mcp", "env": { "GITHUB_TOKEN": "ghp_1", "LOG": "debug" }, "enabled": false })),
+            server(json!({ "name": "remote", "url": "https://mcp.example.com", "auth_token": "t0k" })),
+        ];
+        let existing = r#"{ "inputs": [{ "type": "promptString", "id": "other" }, { "type": "promptString", "id": "gh-github_token" }] }"#;
+        let result = export(&servers, ConfigFormat::VsCode, Some(existing)).unwrap();
+        let root: Value = serde_json::from_str(&result.content).unwrap();
+
+        assert!(!result.content.contains("ghp_1") && !result.content.contains("t0k"));
+        assert!(result.warnings.is_empty());
+        assert_eq!(root["servers"]["gh"]["env"], json!({ "GITHUB_TOKEN": "${input:gh-github_token}", "LOG": "debug" }));
+        assert_eq!(root["servers"]["gh"]["disabled"], true);
+        assert_eq!(root["servers"]["remote"]["headers"]["Authorization"], "Bearer ${input:remote-token}");
+        let ids: Vec<&str> = root["inputs"].as_array().unwrap().iter().map(|i| i["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["other", "gh-github_token", "remote-token"]);
+        assert_eq!(root["inputs"][2]["password"], true);
+
+        // Imported back, the inputs resolve to the same values
+        let values: HashMap<String, String> = [("gh-github_token", "ghp_1"), ("remote-token", "t0k")].iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let preview = parse(&result.content, None, None, &values, &[]).unwrap();
+        let gh = preview.servers.iter().find(|s| s.config.name == "gh").unwrap();
+        assert_eq!(gh.config.env["GITHUB_TOKEN"], "ghp_1");
+        assert!(!gh.config.enabled);
+    }
+
+    #[test]
+    fn other_formats_warn_about_plain_text_secrets() {
+        let servers = [server(json!({ "name": "gh", "command": "gh-mcp", "env": { "GITHUB_TOKEN": "ghp_1", "LOG": "debug" } }))];
+        let result = export(&servers, ConfigFormat::Cursor, None).unwrap();
+        assert!(result.content.contains("ghp_1"));
+        assert_eq!(result.warnings, ["GITHUB_TOKEN of gh is written as plain text"]);
+    }
+}
@@ -0,0 +1,141 @@
+//! Per-server MCP log: the server's stderr, its `notifications/message` logging and our own
+//! connection events. The recent entries are kept in memory for `mcp_server_logs`, and every
+//! entry is appended to `~/.config/ollie/logs/mcp/<server>.log`.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::commands::settings::config_dir;
+
+/// Entries kept in memory per server
+const RING_CAPACITY: usize = 1000;
+/// A log file growing bigger than this is moved to `<server>.log.1`
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSource {
+    /// A line the server process wrote to stderr
+    Stderr,
+    /// A `notifications/message` from the server
+    Server,
+    /// Connection events recorded by Ollie
+    Client,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    /// Increases by one per entry of a server; pass the last one seen as `since` to get newer entries
+    pub seq: u64,
+    pub timestamp: i64,
+    pub source: LogSource,
+    /// Syslog level name as used by MCP (debug, info, notice, warning, error...)
+    pub level: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logger: Option<String>,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct ServerLog {
+    entries: VecDeque<LogEntry>,
+    next_seq: u64,
+    /// Locked on its own so disk writes do not hold up `SERVER_LOGS`
+    file: Arc<Mutex<LogFile>>,
+}
+
+#[derive(Default)]
+struct LogFile {
+    file: Option<File>,
+    /// Bytes in the file, to know when to rotate it
+    len: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref SERVER_LOGS: Mutex<HashMap<String, ServerLog>> = Mutex::new(HashMap::new());
+}
+
+pub fn append(server: &str, source: LogSource, level: &str, logger: Option<&str>, message: &str) {
+    let now = chrono::Utc::now();
+    let message = message.trim_end();
+    let line = format!(
+        "{} [{:?}] {}{}: {}\n",
+        now.to_rfc3339(),
+        source,
+        level,
+        logger.map(|l| format!(" {}", l)).unwrap_or_default(),
+        message,
+    );
+
+    let log_file = {
+        let Ok(mut logs) = SERVER_LOGS.lock() else { return };
+        let log = logs.entry(server.to_string()).or_default();
+        log.next_seq += 1;
+        if log.entries.len() == RING_CAPACITY {
+            log.entries.pop_front();
+        }
+        log.entries.push_back(LogEntry {
+            seq: log.next_seq,
+            timestamp: now.timestamp_millis(),
+            source,
+            level: level.to_string(),
+            logger: logger.map(str::to_string),
+            message: message.to_string(),
+        });
+        log.file.clone()
+    };
+    let Ok(mut file) = log_file.lock() else { return };
+    file.write(server, &line);
+}
+
+/// Entries of a server newer than sequence number `since` (all kept entries without it)
+pub fn since(server: &str, since: Option<u64>) -> Vec<LogEntry> {
+    let Ok(logs) = SERVER_LOGS.lock() else { return Vec::new() };
+    logs.get(server)
+        .map(|log| log.entries.iter().filter(|e| e.seq > since.unwrap_or(0)).cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn file_path(server: &str) -> Option<PathBuf> {
+    let dir = config_dir().ok()?.join("logs").join("mcp");
+    // Server names are user-chosen; keep them from escaping the directory
+    let safe: String = server.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Some(dir.join(format!("{}.log", safe)))
+}
+
+impl LogFile {
+    /// Append a line, rotating the file once it has grown past `MAX_FILE_BYTES`
+    fn write(&mut self, server: &str, line: &str) {
+        if self.file.is_none() {
+            self.file = open_file(server);
+            self.len = self.file.as_ref().and_then(|f| f.metadata().ok()).map(|m| m.len()).unwrap_or(0);
+        }
+        let Some(file) = &mut self.file else { return };
+        if file.write_all(line.as_bytes()).is_err() {
+            self.file = None;
+            return;
+        }
+        self.len += line.len() as u64;
+        if self.len > MAX_FILE_BYTES {
+            // Reopened (as a new file) by the next write
+            self.file = None;
+            if let Some(path) = file_path(server) {
+                let _ = std::fs::rename(&path, path.with_extension("log.1"));
+            }
+        }
+    }
+}
+
+fn open_file(server: &str) -> Option<File> {
+    let path = file_path(server)?;
+    std::fs::create_dir_all(path.parent()?).ok()?;
+    if std::fs::metadata(&path).map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(false) {
+        let _ = std::fs::rename(&path, path.with_extension("log.1"));
+    }
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
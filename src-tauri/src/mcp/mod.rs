@@ -1,12 +1,30 @@
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::collections::HashMap;
 use anyhow::Result;
-use serde_json::Value; 
-use crate::mcp::protocol::{JsonRpcRequest, JsonRpcResponse, Tool, ListToolsResult, CallToolRequest, CallToolResult};
-use crate::mcp::transport::{Transport, StdioTransport, SseTransport};
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use crate::mcp::config::McpServerConfig;
+use crate::mcp::logs::LogSource;
+use crate::mcp::protocol::{
+    CallToolRequest, CallToolResult, CompleteResult, Completion, CompletionRef, GetPromptResult, InitializeResult,
+    JsonRpcError, JsonRpcRequest, JsonRpcResponse, ListToolsResult, Prompt, ReadResourceResult, Resource,
+    ResourceContents, ResourceTemplate, Tool,
+};
+use crate::mcp::transport::{is_legacy_sse_rejection, Transport, StdioTransport, SseTransport, StreamableHttpTransport};
 use lazy_static::lazy_static;
-use tokio::sync::Mutex as TokioMutex;
+use tauri::AppHandle;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
 
+pub mod config;
+pub mod config_files;
+pub mod logs;
+pub mod naming;
+pub mod prompts;
+pub mod resources;
+pub mod sampling;
+pub mod supervisor;
 pub mod protocol;
 pub mod transport;
 
@@ -14,58 +32,235 @@ lazy_static! {
     static ref ACTIVE_MCP_CLIENTS: Arc<Mutex<HashMap<String, Arc<McpClient>>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
+/// JSON-RPC "method not found"
+const METHOD_NOT_FOUND: i32 = -32601;
+/// Guards against servers that keep returning a cursor
+const MAX_PAGES: usize = 100;
+
+/// Called with the params of a server notification
+pub type NotificationHandler = Arc<dyn Fn(Option<Value>) + Send + Sync>;
+/// Answers a server-initiated request; the result or error is sent back to the server
+pub type RequestHandler = Arc<dyn Fn(Option<Value>) -> BoxFuture<'static, std::result::Result<Value, JsonRpcError>> + Send + Sync>;
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value>>>>>;
+
+#[derive(Default)]
+struct Handlers {
+    notifications: Mutex<HashMap<String, NotificationHandler>>,
+    requests: Mutex<HashMap<String, RequestHandler>>,
+}
+
+/// A connection to one MCP server. Any number of requests can be in flight at once: a reader
+/// task receives every message, hands responses to the caller waiting on that id and passes
+/// notifications and server requests to the registered handlers.
 pub struct McpClient {
-    transport: Arc<TokioMutex<Transport>>,
-    next_id: Arc<Mutex<u64>>,
+    config: McpServerConfig,
+    transport: Arc<Transport>,
+    next_id: AtomicU64,
+    pending: PendingRequests,
+    handlers: Arc<Handlers>,
+    reader: JoinHandle<()>,
+    /// Becomes true when the reader stops, i.e. the connection is gone
+    closed: watch::Receiver<bool>,
+    /// The server's answer to `initialize`
+    server: OnceLock<InitializeResult>,
 }
 
-impl McpClient {
-    pub async fn connect(name: &str, command: &str, args: &[String]) -> Result<Arc<Self>> {
-        let transport = StdioTransport::new(command, args)?;
-        
-        let client = Arc::new(Self {
-            transport: Arc::new(TokioMutex::new(Transport::Stdio(transport))),
-            next_id: Arc::new(Mutex::new(1)),
-        });
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
 
-        Self::initialize(&client).await?;
+/// Removes a request from the pending map if its caller stops waiting
+struct PendingGuard<'a> {
+    pending: &'a PendingRequests,
+    id: u64,
+}
 
-        // Register in global map
-        {
-            if let Ok(mut clients) = ACTIVE_MCP_CLIENTS.lock() {
-                clients.insert(name.to_string(), client.clone());
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+impl McpClient {
+    /// Connect to a server and register it under its name, closing any client it replaces
+    pub async fn connect_config(app: &AppHandle, config: &McpServerConfig) -> Result<Arc<Self>> {
+        config.validate().map_err(|e| anyhow::anyhow!(e))?;
+        let client = match (&config.command, &config.url) {
+            (Some(command), _) => {
+                let transport = StdioTransport::new(&config.name, command, &config.args, &config.env, config.cwd.as_deref())?;
+                let client = Arc::new(Self::start(app, Transport::Stdio(transport), config.clone()));
+                Self::initialize(&client).await?;
+                client
+            }
+            (None, Some(url)) => Self::open_http(app, config, url).await?,
+            (None, None) => unreachable!("validated above"),
+        };
+        resources::watch(&client).await;
+        if let Some(level) = &config.log_level {
+            if let Err(e) = client.set_log_level(level).await {
+                logs::append(&config.name, LogSource::Client, "warning", None, &format!("Could not set log level: {}", e));
             }
         }
 
+        let replaced = ACTIVE_MCP_CLIENTS.lock().ok()
+            .and_then(|mut clients| clients.insert(config.name.clone(), client.clone()));
+        if let Some(old) = replaced {
+            if let Err(e) = old.close().await {
+                eprintln!("Failed to close replaced MCP server {}: {}", config.name, e);
+            }
+        }
         Ok(client)
     }
 
-    pub async fn connect_http(name: &str, url: &str, auth_token: Option<String>) -> Result<Arc<Self>> {
-        let transport = SseTransport::new(url, auth_token)?;
+    /// Streamable HTTP is tried first; a server that rejects the POST gets the older HTTP+SSE transport
+    async fn open_http(app: &AppHandle, config: &McpServerConfig, url: &str) -> Result<Arc<Self>> {
+        let transport = StreamableHttpTransport::new(url, config.auth_token.clone())?;
+        let client = Arc::new(Self::start(app, Transport::StreamableHttp(transport), config.clone()));
 
-        let client = Arc::new(Self {
-            transport: Arc::new(TokioMutex::new(Transport::Sse(transport))),
-            next_id: Arc::new(Mutex::new(1)),
-        });
+        match Self::initialize(&client).await {
+            Ok(()) => Ok(client),
+            Err(e) if is_legacy_sse_rejection(&e) => {
+                logs::append(&config.name, LogSource::Client, "info", None,
+                    &format!("{} does not accept Streamable HTTP ({}), using HTTP+SSE", url, e));
+                let transport = SseTransport::new(url, config.auth_token.clone())?;
+                let client = Arc::new(Self::start(app, Transport::Sse(transport), config.clone()));
+                Self::initialize(&client).await?;
+                Ok(client)
+            }
+            Err(e) => Err(e),
+        }
+    }
 
-        Self::initialize(&client).await?;
+    /// Remove a server from the active clients and close its connection
+    pub async fn disconnect(name: &str) -> Result<bool> {
+        let client = ACTIVE_MCP_CLIENTS.lock().ok().and_then(|mut clients| clients.remove(name));
+        match client {
+            Some(client) => {
+                client.close().await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 
-        // Register in global map
-        {
-            if let Ok(mut clients) = ACTIVE_MCP_CLIENTS.lock() {
-                clients.insert(name.to_string(), client.clone());
+    /// Stop the reader, fail requests still waiting and close the transport
+    /// (which kills a stdio server's process)
+    pub async fn close(&self) -> Result<()> {
+        self.reader.abort();
+        if let Ok(mut pending) = self.pending.lock() {
+            for (_, waiter) in pending.drain() {
+                let _ = waiter.send(Err(anyhow::anyhow!("Connection closed")));
             }
         }
+        self.transport.close().await
+    }
 
-        Ok(client)
+    /// A capability the server declared in `initialize` (e.g. "logging", "resources")
+    pub fn capability(&self, name: &str) -> Option<&Value> {
+        self.server.get()?.capabilities.get(name)
+    }
+
+    /// Ask the server to send log messages at `level` and above (`logging/setLevel`)
+    pub async fn set_log_level(&self, level: &str) -> Result<()> {
+        if self.capability("logging").is_none() {
+            anyhow::bail!("Server does not support logging");
+        }
+        self.send_request("logging/setLevel", Some(json!({ "level": level }))).await.map(|_| ())
+    }
+
+    /// Resolves once the connection has closed
+    pub async fn closed(&self) {
+        let mut closed = self.closed.clone();
+        // An error means the reader is gone, which is closed as well
+        let _ = closed.wait_for(|c| *c).await;
+    }
+
+    /// Whether a stdio server's process has exited
+    pub async fn has_exited(&self) -> bool {
+        self.transport.has_exited().await
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        self.send_request("ping", None).await.map(|_| ())
+    }
+
+    /// How this client was connected
+    pub fn config(&self) -> &McpServerConfig {
+        &self.config
+    }
+
+    /// Wrap a transport and start its reader task
+    fn start(app: &AppHandle, transport: Transport, config: McpServerConfig) -> Self {
+        let transport = Arc::new(transport);
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let handlers = Arc::new(Handlers::default());
+
+        let (closed_tx, closed) = watch::channel(false);
+        let reader = tokio::spawn(read_loop(config.name.clone(), transport.clone(), pending.clone(), handlers.clone(), closed_tx));
+        let client = Self {
+            config,
+            transport,
+            next_id: AtomicU64::new(1),
+            pending,
+            handlers,
+            reader,
+            closed,
+            server: OnceLock::new(),
+        };
+
+        client.on_request("ping", |_| async { Ok(json!({})) });
+        // We advertise the roots capability but expose no directories yet
+        client.on_request("roots/list", |_| async { Ok(json!({ "roots": [] })) });
+        // Before `initialize`, as servers may sample as soon as they are initialized
+        sampling::register(app, &client);
+        let name = client.config.name.clone();
+        client.on_notification("notifications/message", move |params| {
+            let params = params.unwrap_or(Value::Null);
+            let level = params.get("level").and_then(|l| l.as_str()).unwrap_or("info");
+            let logger = params.get("logger").and_then(|l| l.as_str());
+            let message = match params.get("data") {
+                Some(Value::String(text)) => text.clone(),
+                Some(data) => data.to_string(),
+                None => String::new(),
+            };
+            logs::append(&name, LogSource::Server, level, logger, &message);
+        });
+        client
+    }
+
+    /// Handle the server notification `method` (replaces an earlier handler)
+    pub fn on_notification<F>(&self, method: &str, handler: F)
+    where
+        F: Fn(Option<Value>) + Send + Sync + 'static,
+    {
+        if let Ok(mut handlers) = self.handlers.notifications.lock() {
+            handlers.insert(method.to_string(), Arc::new(handler));
+        }
+    }
+
+    /// Answer the server request `method` (replaces an earlier handler)
+    pub fn on_request<F, Fut>(&self, method: &str, handler: F)
+    where
+        F: Fn(Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = std::result::Result<Value, JsonRpcError>> + Send + 'static,
+    {
+        let handler: RequestHandler = Arc::new(move |params| Box::pin(handler(params)));
+        if let Ok(mut handlers) = self.handlers.requests.lock() {
+            handlers.insert(method.to_string(), handler);
+        }
     }
 
     async fn initialize(client: &Arc<Self>) -> Result<()> {
         let init_params = serde_json::to_value(crate::mcp::protocol::InitializeParams {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: client.transport.protocol_version().to_string(),
             capabilities: crate::mcp::protocol::ClientCapabilities {
                 roots: Some(crate::mcp::protocol::RootsCapability { list_changed: Some(false) }),
-                sampling: Some(serde_json::json!({})),
+                sampling: client.config.sampling.enabled.then(|| serde_json::json!({})),
             },
             client_info: crate::mcp::protocol::ClientInfo {
                 name: "Ollie".to_string(),
@@ -73,7 +268,17 @@ impl McpClient {
             },
         })?;
 
-        let _init_result: Value = client.send_request("initialize", Some(init_params)).await?;
+        let result = client.send_request("initialize", Some(init_params)).await?;
+        match serde_json::from_value::<InitializeResult>(result) {
+            Ok(init) => {
+                logs::append(&client.config.name, LogSource::Client, "info", None, &format!(
+                    "Connected to {} {} (protocol {})",
+                    init.server_info.name, init.server_info.version, init.protocol_version
+                ));
+                let _ = client.server.set(init);
+            }
+            Err(e) => logs::append(&client.config.name, LogSource::Client, "warning", None, &format!("Unexpected initialize result: {}", e)),
+        }
         client.send_notification("notifications/initialized", None).await?;
         Ok(())
     }
@@ -89,14 +294,7 @@ impl McpClient {
     }
 
     async fn send_request(&self, method: &str, params: Option<Value>) -> Result<Value> {
-        let id = {
-            let mut id_lock = self.next_id.lock()
-                .map_err(|_| anyhow::anyhow!("Failed to acquire lock"))?;
-            let id = *id_lock;
-            *id_lock += 1;
-            id
-        };
-
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         let request = JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
             id: Some(id),
@@ -104,31 +302,15 @@ impl McpClient {
             params,
         };
 
-        {
-            let mut transport = self.transport.lock().await;
-            let req_value = serde_json::to_value(&request)?;
-            transport.send(req_value).await?;
-        }
+        // Register before sending so a fast response cannot arrive unclaimed
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire lock"))?
+            .insert(id, tx);
+        let _guard = PendingGuard { pending: &self.pending, id };
 
-        loop {
-            let response_value = {
-                let mut transport = self.transport.lock().await;
-                transport.receive().await?
-            };
-
-            if let Some(val) = response_value {
-                if let Ok(resp) = serde_json::from_value::<JsonRpcResponse>(val.clone()) {
-                    if resp.id == Some(id) {
-                        if let Some(error) = resp.error {
-                            return Err(anyhow::anyhow!("RPC Error {}: {}", error.code, error.message));
-                        }
-                        return Ok(resp.result.unwrap_or(Value::Null));
-                    }
-                }
-            } else {
-                return Err(anyhow::anyhow!("Connection closed"));
-            }
-        }
+        self.transport.send(serde_json::to_value(&request)?).await?;
+        rx.await.unwrap_or_else(|_| Err(anyhow::anyhow!("Connection closed")))
     }
 
     async fn send_notification(&self, method: &str, params: Option<Value>) -> Result<()> {
@@ -139,10 +321,7 @@ impl McpClient {
             params,
         };
 
-        let mut transport = self.transport.lock().await;
-        let req_value = serde_json::to_value(&request)?;
-        transport.send(req_value).await?;
-        Ok(())
+        self.transport.send(serde_json::to_value(&request)?).await
     }
 
     pub async fn list_tools(&self) -> Result<Vec<Tool>> {
@@ -161,4 +340,154 @@ impl McpClient {
         let call_result: CallToolResult = serde_json::from_value(result)?;
         Ok(call_result)
     }
+
+    /// Collect the `key` array of a paginated list method, following `nextCursor`
+    async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let params = cursor.as_ref().map(|c| json!({ "cursor": c }));
+            let result = self.send_request(method, params).await?;
+            if let Some(Value::Array(page)) = result.get(key) {
+                items.extend(page.iter().cloned());
+            }
+            cursor = result.get("nextCursor").and_then(|c| c.as_str()).map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn list_resources(&self) -> Result<Vec<Resource>> {
+        let items = self.list_all("resources/list", "resources").await?;
+        Ok(serde_json::from_value(Value::Array(items))?)
+    }
+
+    pub async fn list_resource_templates(&self) -> Result<Vec<ResourceTemplate>> {
+        let items = self.list_all("resources/templates/list", "resourceTemplates").await?;
+        Ok(serde_json::from_value(Value::Array(items))?)
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        let result = self.send_request("resources/read", Some(json!({ "uri": uri }))).await?;
+        let read: ReadResourceResult = serde_json::from_value(result)?;
+        Ok(read.contents)
+    }
+
+    pub fn can_subscribe(&self) -> bool {
+        self.capability("resources").and_then(|r| r.get("subscribe")) == Some(&Value::Bool(true))
+    }
+
+    /// Ask for `notifications/resources/updated` when the resource changes
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<()> {
+        if !self.can_subscribe() {
+            anyhow::bail!("Server does not support resource subscriptions");
+        }
+        self.send_request("resources/subscribe", Some(json!({ "uri": uri }))).await.map(|_| ())
+    }
+
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<()> {
+        self.send_request("resources/unsubscribe", Some(json!({ "uri": uri }))).await.map(|_| ())
+    }
+
+    pub async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        let items = self.list_all("prompts/list", "prompts").await?;
+        Ok(serde_json::from_value(Value::Array(items))?)
+    }
+
+    pub async fn get_prompt(&self, name: &str, arguments: &HashMap<String, String>) -> Result<GetPromptResult> {
+        let result = self.send_request("prompts/get", Some(json!({ "name": name, "arguments": arguments }))).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Suggestions for argument `argument` of a prompt or resource template, given its partial `value`.
+    /// `context` holds the arguments already filled in.
+    pub async fn complete(
+        &self,
+        reference: &CompletionRef,
+        argument: &str,
+        value: &str,
+        context: Option<&HashMap<String, String>>,
+    ) -> Result<Completion> {
+        let mut params = json!({
+            "ref": reference,
+            "argument": { "name": argument, "value": value },
+        });
+        if let Some(arguments) = context.filter(|c| !c.is_empty()) {
+            params["context"] = json!({ "arguments": arguments });
+        }
+        let result = self.send_request("completion/complete", Some(params)).await?;
+        let complete: CompleteResult = serde_json::from_value(result)?;
+        Ok(complete.completion)
+    }
+}
+
+/// Receive messages until the connection closes, routing each one. Requests still waiting
+/// when it closes fail with "Connection closed".
+async fn read_loop(server: String, transport: Arc<Transport>, pending: PendingRequests, handlers: Arc<Handlers>, closed: watch::Sender<bool>) {
+    loop {
+        let message = match transport.receive().await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("MCP connection error: {}", e);
+                break;
+            }
+        };
+
+        let method = message.get("method").and_then(|m| m.as_str()).map(str::to_string);
+        match (method, message.get("id").filter(|id| !id.is_null()).cloned()) {
+            // Server-initiated request: answer it without blocking the reader
+            (Some(method), Some(id)) => {
+                let handler = handlers.requests.lock().ok().and_then(|h| h.get(&method).cloned());
+                let params = message.get("params").cloned();
+                let transport = transport.clone();
+                tokio::spawn(async move {
+                    let result = match handler {
+                        Some(handler) => handler(params).await,
+                        None => Err(JsonRpcError {
+                            code: METHOD_NOT_FOUND,
+                            message: format!("Method not found: {}", method),
+                            data: None,
+                        }),
+                    };
+                    let response = match result {
+                        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+                    };
+                    if let Err(e) = transport.send(response).await {
+                        eprintln!("Failed to answer MCP request {}: {}", method, e);
+                    }
+                });
+            }
+            (Some(method), None) => {
+                let handler = handlers.notifications.lock().ok().and_then(|h| h.get(&method).cloned());
+                match handler {
+                    Some(handler) => handler(message.get("params").cloned()),
+                    None => logs::append(&server, LogSource::Client, "debug", None, &format!("Unhandled MCP notification: {}", method)),
+                }
+            }
+            (None, Some(_)) => match serde_json::from_value::<JsonRpcResponse>(message) {
+                Ok(resp) => {
+                    let waiter = resp.id.and_then(|id| pending.lock().ok()?.remove(&id));
+                    let Some(waiter) = waiter else { continue };
+                    let result = match resp.error {
+                        Some(error) => Err(anyhow::anyhow!("RPC Error {}: {}", error.code, error.message)),
+                        None => Ok(resp.result.unwrap_or(Value::Null)),
+                    };
+                    let _ = waiter.send(result);
+                }
+                Err(e) => eprintln!("Invalid MCP response: {}", e),
+            },
+            (None, None) => eprintln!("Ignoring MCP message without method or id"),
+        }
+    }
+
+    if let Ok(mut pending) = pending.lock() {
+        for (_, waiter) in pending.drain() {
+            let _ = waiter.send(Err(anyhow::anyhow!("Connection closed")));
+        }
+    }
+    closed.send_replace(true);
 }
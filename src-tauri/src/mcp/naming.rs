@@ -0,0 +1,113 @@
+//! Server-qualified tool names.
+//!
+//! Tools from different MCP servers often share names (`search`, `read_file`), so every
+//! MCP tool is offered to the model as `<server>__<tool>`. Providers restrict tool names
+//! (OpenAI: `^[a-zA-Z0-9_-]{1,64}$`, Gemini additionally wants a leading letter or
+//! underscore), so both parts are sanitized and over-long or clashing results get a
+//! short hash suffix.
+
+use std::collections::{HashMap, HashSet};
+use crate::mcp::protocol::Tool;
+use crate::mcp::McpClient;
+
+const SEPARATOR: &str = "__";
+const MAX_NAME_LEN: usize = 64;
+
+/// An MCP tool together with the names it is known by
+pub struct NamedTool {
+    pub server: String,
+    pub tool: Tool,
+    /// Provider-safe name sent to the model
+    pub qualified_name: String,
+    /// Human readable name for the UI
+    pub display_name: String,
+    /// Other servers exposing a tool with the same name
+    pub conflicts_with: Vec<String>,
+}
+
+/// Hands out unique, provider-safe tool names
+#[derive(Default)]
+pub struct ToolNamer {
+    used: HashSet<String>,
+}
+
+impl ToolNamer {
+    /// Claim a name verbatim, e.g. for built-in tools
+    pub fn reserve(&mut self, name: &str) {
+        self.used.insert(name.to_string());
+    }
+
+    pub fn assign(&mut self, server: &str, tool: &str) -> String {
+        let mut name = format!("{}{}{}", sanitize(server), SEPARATOR, sanitize(tool));
+        if name.len() > MAX_NAME_LEN || self.used.contains(&name) {
+            let suffix = format!("_{:08x}", fnv1a(&format!("{}\u{0}{}", server, tool)));
+            name.truncate(MAX_NAME_LEN - suffix.len());
+            name.push_str(&suffix);
+        }
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+fn sanitize(part: &str) -> String {
+    let mut out: String = part.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// Stable 32-bit FNV-1a hash, so qualified names survive restarts
+fn fnv1a(s: &str) -> u32 {
+    s.bytes().fold(0x811c9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x01000193))
+}
+
+/// List the tools of every active MCP server with qualified names and collision info.
+/// Names in `reserved` (built-in tools) are never handed out.
+pub async fn list_named_tools(reserved: &[&str]) -> Vec<NamedTool> {
+    let mut namer = ToolNamer::default();
+    for name in reserved {
+        namer.reserve(name);
+    }
+
+    let mut clients = McpClient::list_active_clients();
+    // Deterministic order so hash suffixes land on the same tool every time
+    clients.sort();
+
+    // Servers are asked concurrently; results keep the sorted order
+    let results = futures::future::join_all(clients.iter().map(|client_name| async move {
+        let mcp_client = McpClient::get_client(client_name)?;
+        Some(mcp_client.list_tools().await)
+    })).await;
+
+    let mut listed: Vec<(String, Tool)> = Vec::new();
+    for (client_name, result) in clients.iter().zip(results) {
+        match result {
+            Some(Ok(tools)) => listed.extend(tools.into_iter().map(|t| (client_name.clone(), t))),
+            Some(Err(e)) => eprintln!("Failed to list tools for {}: {}", client_name, e),
+            None => {}
+        }
+    }
+
+    let mut servers_by_tool: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (server, tool) in &listed {
+        servers_by_tool.entry(tool.name.as_str()).or_default().push(server.as_str());
+    }
+    let conflicts: Vec<Vec<String>> = listed.iter()
+        .map(|(server, tool)| servers_by_tool[tool.name.as_str()].iter()
+            .filter(|s| **s != server.as_str())
+            .map(|s| s.to_string())
+            .collect())
+        .collect();
+
+    listed.into_iter().zip(conflicts).map(|((server, tool), conflicts_with)| {
+        let qualified_name = namer.assign(&server, &tool.name);
+        let display_name = format!("{} ({})", tool.name, server);
+        if !conflicts_with.is_empty() {
+            eprintln!("Tool {} of {} is also exposed by {}; offered as {}", tool.name, server, conflicts_with.join(", "), qualified_name);
+        }
+        NamedTool { server, tool, qualified_name, display_name, conflicts_with }
+    }).collect()
+}
@@ -0,0 +1,67 @@
+//! MCP prompts used as slash-command templates. A prompt is fetched with the arguments the
+//! user filled in and the messages it returns are added to the chat: text as is, embedded
+//! resources as `<resource>` blocks and images as message images.
+
+use std::collections::HashMap;
+use crate::commands::db::{insert_message, MessageMeta, MessageRow};
+use crate::db::get_pool;
+use crate::mcp::protocol::{Content, GetPromptResult};
+use crate::mcp::resources;
+use crate::mcp::McpClient;
+use crate::providers::ChatMessage;
+
+/// Chat messages for the messages of a prompt. Consecutive messages of the same role are
+/// merged, so that e.g. an image and the text about it end up in one message.
+pub fn to_chat_messages(prompt: &GetPromptResult) -> Vec<ChatMessage> {
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    for message in &prompt.messages {
+        if messages.last().map(|m| m.role != message.role).unwrap_or(true) {
+            messages.push(ChatMessage {
+                role: message.role.clone(),
+                content: String::new(),
+                images: None,
+                tool_calls: None,
+                tool_call_id: None,
+            });
+        }
+        let Some(current) = messages.last_mut() else { continue };
+        let text = match &message.content {
+            Content::Text { text } => text.clone(),
+            Content::Image { data, .. } => {
+                current.images.get_or_insert_with(Vec::new).push(data.clone());
+                continue;
+            }
+            Content::Resource { resource } => {
+                let is_image = resource.mime_type.as_deref().is_some_and(|m| m.starts_with("image/"));
+                match &resource.blob {
+                    Some(blob) if is_image => {
+                        current.images.get_or_insert_with(Vec::new).push(blob.clone());
+                        continue;
+                    }
+                    _ => resources::render(resource, None),
+                }
+            }
+            Content::Unsupported => continue,
+        };
+        if !current.content.is_empty() {
+            current.content.push_str("\n\n");
+        }
+        current.content.push_str(text.trim_end());
+    }
+    messages
+}
+
+/// Get a prompt from a connected server and append its messages to a chat
+pub async fn insert(chat_id: &str, server: &str, name: &str, arguments: &HashMap<String, String>) -> Result<Vec<MessageRow>, String> {
+    let client = McpClient::get_client(server).ok_or_else(|| format!("MCP server '{}' is not connected", server))?;
+    let prompt = client.get_prompt(name, arguments).await
+        .map_err(|e| format!("Failed to get prompt {}: {}", name, e))?;
+
+    let pool = get_pool().await?;
+    let mut rows = Vec::new();
+    for message in to_chat_messages(&prompt) {
+        let meta = MessageMeta::from_message(&message).to_json();
+        rows.push(insert_message(&pool, chat_id, &message.role, &message.content, meta).await?);
+    }
+    Ok(rows)
+}
@@ -0,0 +1,282 @@
+//! MCP resources attached to a chat as context. A copy of each resource is stored with the
+//! chat and sent to the model with every turn; servers that support subscriptions tell us
+//! when a resource changes (`notifications/resources/updated`) and the copies are refreshed.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, Weak};
+use crate::db::get_pool;
+use crate::mcp::logs::{self, LogSource};
+use crate::mcp::protocol::ResourceContents;
+use crate::mcp::McpClient;
+use crate::tools::tool_output;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachedResource {
+    pub server: String,
+    pub uri: String,
+    pub name: Option<String>,
+    pub contents: Vec<ResourceContents>,
+    pub attached_at: i64,
+    pub updated_at: i64,
+}
+
+type ResourceRow = (String, String, Option<String>, String, i64, i64);
+
+fn from_row((server, uri, name, contents_json, attached_at, updated_at): ResourceRow) -> AttachedResource {
+    AttachedResource {
+        server,
+        uri,
+        name,
+        contents: serde_json::from_str(&contents_json).unwrap_or_default(),
+        attached_at,
+        updated_at,
+    }
+}
+
+/// Read a resource and attach it to a chat (re-reading it if it is already attached)
+pub async fn attach(chat_id: &str, server: &str, uri: &str, name: Option<&str>) -> Result<AttachedResource, String> {
+    let client = McpClient::get_client(server).ok_or_else(|| format!("MCP server '{}' is not connected", server))?;
+    let contents = client.read_resource(uri).await.map_err(|e| format!("Failed to read {}: {}", uri, e))?;
+    let contents_json = serde_json::to_string(&contents).map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let pool = get_pool().await?;
+    sqlx::query(
+        r#"INSERT INTO chat_resources (chat_id, server, uri, name, contents_json, attached_at, updated_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?)
+           ON CONFLICT(chat_id, server, uri) DO UPDATE SET name = excluded.name, contents_json = excluded.contents_json, updated_at = excluded.updated_at"#
+    )
+    .bind(chat_id)
+    .bind(server)
+    .bind(uri)
+    .bind(name)
+    .bind(&contents_json)
+    .bind(now)
+    .bind(now)
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("attach resource failed: {}", e))?;
+
+    subscribe(&client, uri).await;
+    Ok(AttachedResource {
+        server: server.to_string(),
+        uri: uri.to_string(),
+        name: name.map(str::to_string),
+        contents,
+        attached_at: now,
+        updated_at: now,
+    })
+}
+
+pub async fn detach(chat_id: &str, server: &str, uri: &str) -> Result<bool, String> {
+    let pool = get_pool().await?;
+    let res = sqlx::query("DELETE FROM chat_resources WHERE chat_id = ? AND server = ? AND uri = ?")
+        .bind(chat_id)
+        .bind(server)
+        .bind(uri)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("detach resource failed: {}", e))?;
+
+    // Stop updates once no chat uses the resource any more
+    let (remaining,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chat_resources WHERE server = ? AND uri = ?")
+        .bind(server)
+        .bind(uri)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| format!("count resource attachments failed: {}", e))?;
+    if remaining == 0 {
+        if let Some(client) = McpClient::get_client(server) {
+            let _ = client.unsubscribe_resource(uri).await;
+        }
+    }
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn list(chat_id: &str) -> Result<Vec<AttachedResource>, String> {
+    let pool = get_pool().await?;
+    let rows = sqlx::query_as::<_, ResourceRow>(
+        "SELECT server, uri, name, contents_json, attached_at, updated_at FROM chat_resources WHERE chat_id = ? ORDER BY attached_at ASC"
+    )
+    .bind(chat_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("list chat resources failed: {}", e))?;
+    Ok(rows.into_iter().map(from_row).collect())
+}
+
+/// The attached resources of a chat as one block of text for the system prompt. All their
+/// texts together are cut to `max_tokens`, the budget of a tool result, so attachments cannot
+/// fill the context of every turn.
+pub async fn context(chat_id: &str, max_tokens: usize) -> Result<Option<String>, String> {
+    let attached = list(chat_id).await?;
+    if attached.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(render_context(&attached, max_tokens)))
+}
+
+fn render_context(attached: &[AttachedResource], max_tokens: usize) -> String {
+    let items: Vec<(&AttachedResource, &ResourceContents)> = attached.iter()
+        .flat_map(|resource| resource.contents.iter().map(move |contents| (resource, contents)))
+        .collect();
+    let sizes: Vec<usize> = items.iter()
+        .map(|(_, contents)| contents.text.as_deref().map(tool_output::estimate_tokens).unwrap_or(0))
+        .collect();
+    let shares = split_budget(&sizes, max_tokens);
+
+    let mut text = String::from("The user attached these resources as context:\n");
+    for ((resource, contents), share) in items.into_iter().zip(shares) {
+        text.push('\n');
+        text.push_str(&render(&truncate(contents, share), Some(resource.name.as_deref().unwrap_or(&resource.uri))));
+    }
+    text
+}
+
+/// Split `budget` tokens over items of the given sizes: the smallest get all they need, and
+/// what they leave is shared evenly by the bigger ones
+fn split_budget(sizes: &[usize], budget: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| sizes[i]);
+    let mut shares = vec![0; sizes.len()];
+    let mut left = budget;
+    for (done, &i) in order.iter().enumerate() {
+        let share = sizes[i].min(left / (sizes.len() - done)).max(1);
+        shares[i] = share;
+        left = left.saturating_sub(share);
+    }
+    shares
+}
+
+fn truncate(contents: &ResourceContents, max_tokens: usize) -> ResourceContents {
+    let mut contents = contents.clone();
+    if let Some(text) = &mut contents.text {
+        let cut = tool_output::truncation_point(text, max_tokens);
+        if cut < text.len() {
+            let note = format!(
+                "\n\n[... Resource truncated. Showing {}/{} characters.]",
+                text[..cut].chars().count(),
+                text.chars().count()
+            );
+            text.truncate(cut);
+            text.push_str(&note);
+        }
+    }
+    contents
+}
+
+/// Resource contents as a `<resource>` block for the model; binary contents are only described
+pub fn render(contents: &ResourceContents, name: Option<&str>) -> String {
+    let body = match (&contents.text, &contents.blob) {
+        (Some(text), _) => text.clone(),
+        (None, Some(blob)) => format!(
+            "[binary content, {}, about {} bytes]",
+            contents.mime_type.as_deref().unwrap_or("unknown type"),
+            blob.len() * 3 / 4
+        ),
+        (None, None) => String::new(),
+    };
+    format!(
+        "<resource uri=\"{}\" name=\"{}\">\n{}\n</resource>\n",
+        contents.uri,
+        name.unwrap_or(&contents.uri),
+        body.trim_end()
+    )
+}
+
+/// Follow updates for a newly connected client: refresh attached copies when the server
+/// reports a change, and renew the subscriptions of resources attached before it (re)connected
+pub async fn watch(client: &Arc<McpClient>) {
+    let weak: Weak<McpClient> = Arc::downgrade(client);
+    client.on_notification("notifications/resources/updated", move |params| {
+        let Some(uri) = params.as_ref().and_then(|p| p.get("uri")).and_then(Value::as_str).map(str::to_string) else { return };
+        let Some(client) = weak.upgrade() else { return };
+        tokio::spawn(async move {
+            if let Err(e) = refresh(&client, &uri).await {
+                logs::append(&client.config().name, LogSource::Client, "warning", None, &e);
+            }
+        });
+    });
+
+    let server = &client.config().name;
+    let uris: Vec<(String,)> = match get_pool().await {
+        Ok(pool) => sqlx::query_as("SELECT DISTINCT uri FROM chat_resources WHERE server = ?")
+            .bind(server)
+            .fetch_all(&pool)
+            .await
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    for (uri,) in uris {
+        subscribe(client, &uri).await;
+    }
+}
+
+/// Re-read a resource and update every chat's copy of it
+async fn refresh(client: &McpClient, uri: &str) -> Result<(), String> {
+    let server = &client.config().name;
+    let contents = client.read_resource(uri).await.map_err(|e| format!("Failed to refresh {}: {}", uri, e))?;
+    let contents_json = serde_json::to_string(&contents).map_err(|e| e.to_string())?;
+    let pool = get_pool().await?;
+    sqlx::query("UPDATE chat_resources SET contents_json = ?, updated_at = ? WHERE server = ? AND uri = ?")
+        .bind(contents_json)
+        .bind(chrono::Utc::now().timestamp_millis())
+        .bind(server)
+        .bind(uri)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("refresh resource failed: {}", e))?;
+    Ok(())
+}
+
+/// Subscribe where the server supports it; attached copies of other servers' resources stay as read
+async fn subscribe(client: &McpClient, uri: &str) {
+    if !client.can_subscribe() {
+        return;
+    }
+    if let Err(e) = client.subscribe_resource(uri).await {
+        logs::append(&client.config().name, LogSource::Client, "warning", None, &format!("Subscribing to {} failed: {}", uri, e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(text: &str) -> ResourceContents {
+        ResourceContents { uri: "file:///a.txt".to_string(), mime_type: None, text: Some(text.to_string()), blob: None }
+    }
+
+    #[test]
+    fn truncates_text_over_the_budget() {
+        assert_eq!(truncate(&contents("short"), 10).text.as_deref(), Some("short"));
+        let long = "x".repeat(100);
+        let cut = truncate(&contents(&long), 10).text.unwrap();
+        assert!(cut.starts_with(&"x".repeat(40)));
+        assert!(cut.ends_with("[... Resource truncated. Showing 40/100 characters.]"));
+    }
+
+    #[test]
+    fn attachments_share_one_budget() {
+        assert_eq!(split_budget(&[10, 1000, 1000], 210), vec![10, 100, 100]);
+        assert_eq!(split_budget(&[5, 5], 100), vec![5, 5]);
+
+        let resource = |name: &str, text: &str| AttachedResource {
+            server: "fs".to_string(),
+            uri: format!("file:///{}", name),
+            name: Some(name.to_string()),
+            contents: vec![contents(text)],
+            attached_at: 0,
+            updated_at: 0,
+        };
+        let mut attached = vec![resource("small.txt", "tiny")];
+        attached.extend((0..10).map(|i| resource(&format!("big{}.txt", i), &"y".repeat(10_000))));
+        let text = render_context(&attached, 1000);
+
+        assert!(text.contains("tiny"));
+        assert_eq!(text.matches("[... Resource truncated.").count(), 10);
+        // About 1000 tokens of content plus the tags and notes, not 10 x 1000
+        assert!(tool_output::estimate_tokens(&text) < 1500, "{} tokens", tool_output::estimate_tokens(&text));
+    }
+}
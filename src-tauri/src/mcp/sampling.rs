@@ -0,0 +1,232 @@
+//! MCP sampling: a server asks for a completion with `sampling/createMessage` and we answer it
+//! with one of our own providers, so agentic servers can run on the user's (local) models.
+//! Unless the server's `SamplingConfig` says otherwise, every request is first shown to the
+//! user as an `mcp:sampling-request` event and waits for `mcp_sampling_respond`. A request that
+//! ends unanswered (timed out, or its server went away) is withdrawn with `mcp:sampling-request-closed`.
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+use crate::commands::models::models_list;
+use crate::commands::settings::{provider_get, provider_get_active, settings_get};
+use crate::mcp::config::SamplingConfig;
+use crate::mcp::logs::{self, LogSource};
+use crate::mcp::protocol::{Content, CreateMessageParams, CreateMessageResult, JsonRpcError, ModelPreferences};
+use crate::mcp::resources;
+use crate::mcp::McpClient;
+use crate::providers::traits::{FinishReason, ProviderEvent};
+use crate::providers::{create_provider, scheduler, ChatMessage, ChatOptions, ProviderConfig, ProviderType};
+
+/// How long a request waits for the user before it is rejected
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+/// Error code of a request the user rejected, as in the MCP specification's examples
+const USER_REJECTED: i32 = -1;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+
+lazy_static::lazy_static! {
+    static ref PENDING_APPROVALS: Mutex<HashMap<String, oneshot::Sender<bool>>> = Mutex::new(HashMap::new());
+}
+
+/// Answer the sampling requests of `client`, if its configuration allows sampling
+pub fn register(app: &AppHandle, client: &McpClient) {
+    let config = client.config().sampling.clone();
+    if !config.enabled {
+        return;
+    }
+    let app = app.clone();
+    let server = client.config().name.clone();
+    let closed = client.closed.clone();
+    client.on_request("sampling/createMessage", move |params| {
+        let (app, server, config, mut closed) = (app.clone(), server.clone(), config.clone(), closed.clone());
+        async move {
+            // Stop waiting for the user (or the model) once the server is gone
+            let result = tokio::select! {
+                result = create_message(&app, &server, &config, params) => result,
+                _ = closed.wait_for(|c| *c) => Err(error(INTERNAL_ERROR, "Connection closed")),
+            };
+            if let Err(e) = &result {
+                logs::append(&server, LogSource::Client, "warning", None, &format!("Sampling request failed: {}", e.message));
+            }
+            result
+        }
+    });
+}
+
+/// Resolve a pending `mcp:sampling-request`. Returns false if it is no longer waiting.
+pub fn respond(id: &str, approved: bool) -> bool {
+    let sender = PENDING_APPROVALS.lock().ok().and_then(|mut pending| pending.remove(id));
+    sender.map(|s| s.send(approved).is_ok()).unwrap_or(false)
+}
+
+/// Removes a pending approval however its request ends, and withdraws the request from the
+/// frontend if the user had not answered it
+struct ApprovalGuard<'a> {
+    app: &'a AppHandle,
+    id: String,
+}
+
+impl Drop for ApprovalGuard<'_> {
+    fn drop(&mut self) {
+        let unanswered = PENDING_APPROVALS.lock().ok().and_then(|mut pending| pending.remove(&self.id)).is_some();
+        if unanswered {
+            let _ = self.app.emit("mcp:sampling-request-closed", json!({ "id": self.id }));
+        }
+    }
+}
+
+fn error(code: i32, message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError { code, message: message.into(), data: None }
+}
+
+async fn create_message(app: &AppHandle, server: &str, config: &SamplingConfig, params: Option<Value>) -> Result<Value, JsonRpcError> {
+    let params: CreateMessageParams = serde_json::from_value(params.unwrap_or(Value::Null))
+        .map_err(|e| error(INVALID_PARAMS, format!("Invalid sampling request: {}", e)))?;
+
+    let provider_config = match &config.provider_id {
+        Some(id) => provider_get(id).await,
+        None => provider_get_active().await,
+    }
+    .map_err(|e| error(INTERNAL_ERROR, e))?;
+    let model = choose_model(&provider_config, config, params.model_preferences.as_ref()).await
+        .map_err(|e| error(INTERNAL_ERROR, e))?;
+    let messages = to_chat_messages(&params).map_err(|e| error(INVALID_PARAMS, e))?;
+
+    if config.require_approval && !approve(app, server, &provider_config, &model, &params).await {
+        return Err(error(USER_REJECTED, "User rejected sampling request"));
+    }
+
+    let request_id = format!("mcp-sampling-{}", Uuid::new_v4());
+    let Some(_permit) = scheduler::acquire(app, &provider_config, &request_id, 0, std::future::pending()).await else {
+        return Err(error(INTERNAL_ERROR, "Provider queue closed"));
+    };
+    let options = ChatOptions {
+        temperature: params.temperature,
+        top_k: None,
+        top_p: None,
+        max_tokens: Some(params.max_tokens),
+    };
+    let provider = create_provider(&provider_config.provider_type);
+    let mut stream = provider.stream_chat(&provider_config, &model, &messages, None, Some(options)).await
+        .map_err(|e| error(INTERNAL_ERROR, format!("Generation failed: {}", e)))?;
+
+    let mut text = String::new();
+    let mut finish = None;
+    while let Some(event) = stream.next().await {
+        match event {
+            ProviderEvent::Content(chunk) => text.push_str(&chunk),
+            ProviderEvent::Finish(reason) => finish = Some(reason),
+            ProviderEvent::Error(e) => return Err(error(INTERNAL_ERROR, format!("Generation failed: {}", e))),
+            ProviderEvent::ToolCall(_) | ProviderEvent::Usage(_) => {}
+        }
+    }
+
+    // Providers take no stop sequences, so the reply is cut at the first one here
+    let mut stop_reason = finish.map(|reason| match reason {
+        FinishReason::Stop => "endTurn".to_string(),
+        FinishReason::Length => "maxTokens".to_string(),
+        FinishReason::ToolUse => "toolUse".to_string(),
+        FinishReason::ContentFilter => "contentFilter".to_string(),
+        FinishReason::Other(other) => other,
+    });
+    let cut = params.stop_sequences.iter().flatten()
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| text.find(s.as_str()))
+        .min();
+    if let Some(at) = cut {
+        text.truncate(at);
+        stop_reason = Some("stopSequence".to_string());
+    }
+
+    logs::append(server, LogSource::Client, "info", None, &format!(
+        "Answered sampling request with {} ({} characters)", model, text.len()
+    ));
+    let result = CreateMessageResult {
+        role: "assistant".to_string(),
+        content: Content::Text { text },
+        model,
+        stop_reason,
+    };
+    serde_json::to_value(result).map_err(|e| error(INTERNAL_ERROR, e.to_string()))
+}
+
+/// The first model hint matching an installed model (hints are name substrings, so
+/// "llama" picks e.g. "llama3.2:latest"), otherwise the configured model, otherwise the
+/// default model. Installed models can only be listed for Ollama; the priorities carry no
+/// information we can match models against and are not used.
+async fn choose_model(provider: &ProviderConfig, config: &SamplingConfig, preferences: Option<&ModelPreferences>) -> Result<String, String> {
+    let hints: Vec<String> = preferences.into_iter()
+        .flat_map(|p| p.hints.iter())
+        .filter_map(|h| h.name.as_ref())
+        .map(|n| n.to_lowercase())
+        .collect();
+    if !hints.is_empty() && provider.provider_type == ProviderType::Ollama {
+        let installed = models_list(Some(provider.get_base_url())).await.map(|r| r.models).unwrap_or_default();
+        let matched = hints.iter()
+            .find_map(|hint| installed.iter().find(|m| m.name.to_lowercase().contains(hint.as_str())));
+        if let Some(model) = matched {
+            return Ok(model.name.clone());
+        }
+    }
+
+    if let Some(model) = &config.model {
+        return Ok(model.clone());
+    }
+    settings_get().await?.default_model
+        .ok_or_else(|| "No model configured for sampling; set one for the server or a default model".to_string())
+}
+
+fn to_chat_messages(params: &CreateMessageParams) -> Result<Vec<ChatMessage>, String> {
+    let mut messages = Vec::new();
+    if let Some(system) = params.system_prompt.as_ref().filter(|s| !s.trim().is_empty()) {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: system.clone(),
+            images: None,
+            tool_calls: None,
+            tool_call_id: None,
+        });
+    }
+    for message in &params.messages {
+        let (content, images) = match &message.content {
+            Content::Text { text } => (text.clone(), None),
+            Content::Image { data, .. } => (String::new(), Some(vec![data.clone()])),
+            Content::Resource { resource } => (resources::render(resource, None), None),
+            Content::Unsupported => return Err("Only text and image content can be sampled".to_string()),
+        };
+        messages.push(ChatMessage {
+            role: message.role.clone(),
+            content,
+            images,
+            tool_calls: None,
+            tool_call_id: None,
+        });
+    }
+    Ok(messages)
+}
+
+/// Show the request to the user and wait for their decision
+async fn approve(app: &AppHandle, server: &str, provider: &ProviderConfig, model: &str, params: &CreateMessageParams) -> bool {
+    let id = Uuid::new_v4().to_string();
+    let (tx, rx) = oneshot::channel();
+    if let Ok(mut pending) = PENDING_APPROVALS.lock() {
+        pending.insert(id.clone(), tx);
+    }
+    let _guard = ApprovalGuard { app, id: id.clone() };
+    let _ = app.emit("mcp:sampling-request", json!({
+        "id": id,
+        "server": server,
+        "provider": provider.name,
+        "model": model,
+        "system_prompt": params.system_prompt,
+        "messages": params.messages,
+        "max_tokens": params.max_tokens,
+    }));
+
+    matches!(tokio::time::timeout(APPROVAL_TIMEOUT, rx).await, Ok(Ok(true)))
+}
@@ -0,0 +1,194 @@
+//! Keeps connected MCP servers alive. Each server gets a watcher that notices a closed
+//! connection or an exited process, pings the server periodically and reconnects it with
+//! exponential backoff. Status changes are emitted as `mcp:server-status` events.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use crate::mcp::config::McpServerConfig;
+use crate::mcp::logs::{self, LogSource};
+use crate::mcp::McpClient;
+
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Missed pings in a row before the server is treated as crashed
+const MAX_PING_FAILURES: u32 = 3;
+/// Reconnect attempts in a row before giving up
+const MAX_RESTARTS: u32 = 6;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerStatus {
+    Connecting,
+    Ready,
+    /// Connected, but not answering pings
+    Degraded,
+    /// Gone; being restarted, or given up on after `MAX_RESTARTS`
+    Crashed,
+}
+
+struct Supervised {
+    status: watch::Receiver<ServerStatus>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+lazy_static::lazy_static! {
+    static ref SUPERVISED: Mutex<HashMap<String, Supervised>> = Mutex::new(HashMap::new());
+}
+
+/// Connect a server and keep it supervised. Replaces (and closes) a server of the same name.
+pub async fn start(app: &AppHandle, config: McpServerConfig) -> anyhow::Result<Arc<McpClient>> {
+    stop(&config.name);
+    let (status_tx, status_rx) = watch::channel(ServerStatus::Connecting);
+    if let Ok(mut supervised) = SUPERVISED.lock() {
+        supervised.insert(config.name.clone(), Supervised { status: status_rx, watcher: None });
+    }
+    emit(app, &config.name, ServerStatus::Connecting, None);
+
+    let client = match McpClient::connect_config(app, &config).await {
+        Ok(client) => client,
+        Err(e) => {
+            if let Ok(mut supervised) = SUPERVISED.lock() {
+                supervised.remove(&config.name);
+            }
+            emit(app, &config.name, ServerStatus::Crashed, Some(&e.to_string()));
+            return Err(e);
+        }
+    };
+    status_tx.send_replace(ServerStatus::Ready);
+    emit(app, &config.name, ServerStatus::Ready, None);
+
+    let name = config.name.clone();
+    let watcher = tokio::spawn(watch_server(app.clone(), config, client.clone(), status_tx));
+    if let Ok(mut supervised) = SUPERVISED.lock() {
+        if let Some(entry) = supervised.get_mut(&name) {
+            entry.watcher = Some(watcher);
+        }
+    }
+    Ok(client)
+}
+
+/// Stop supervising a server, so that closing it does not trigger a restart
+pub fn stop(name: &str) {
+    let entry = SUPERVISED.lock().ok().and_then(|mut supervised| supervised.remove(name));
+    if let Some(watcher) = entry.and_then(|e| e.watcher) {
+        watcher.abort();
+    }
+}
+
+/// Stop supervising a server and close its connection
+pub async fn disconnect(name: &str) -> anyhow::Result<bool> {
+    stop(name);
+    McpClient::disconnect(name).await
+}
+
+pub fn statuses() -> HashMap<String, ServerStatus> {
+    SUPERVISED.lock().ok()
+        .map(|supervised| supervised.iter().map(|(name, s)| (name.clone(), *s.status.borrow())).collect())
+        .unwrap_or_default()
+}
+
+/// The client for `name`, waiting up to `timeout` while the server is being (re)connected.
+/// Unsupervised servers are returned as they are.
+pub async fn wait_ready(name: &str, timeout: Duration) -> Option<Arc<McpClient>> {
+    let status = SUPERVISED.lock().ok()?.get(name).map(|s| s.status.clone());
+    if let Some(mut status) = status {
+        let usable = |s: &ServerStatus| matches!(s, ServerStatus::Ready | ServerStatus::Degraded);
+        if *status.borrow() == ServerStatus::Crashed && !is_restarting(name) {
+            return None;
+        }
+        tokio::time::timeout(timeout, status.wait_for(usable)).await.ok()?.ok()?;
+    }
+    McpClient::get_client(name)
+}
+
+fn is_restarting(name: &str) -> bool {
+    SUPERVISED.lock().ok()
+        .and_then(|supervised| supervised.get(name).and_then(|s| s.watcher.as_ref().map(|w| !w.is_finished())))
+        .unwrap_or(false)
+}
+
+fn emit(app: &AppHandle, name: &str, status: ServerStatus, error: Option<&str>) {
+    let _ = app.emit("mcp:server-status", json!({ "name": name, "status": status, "error": error }));
+}
+
+async fn watch_server(app: AppHandle, config: McpServerConfig, mut client: Arc<McpClient>, status: watch::Sender<ServerStatus>) {
+    let name = config.name.clone();
+    let set = |new: ServerStatus, error: Option<&str>| {
+        if *status.borrow() != new || error.is_some() {
+            status.send_replace(new);
+            emit(&app, &name, new, error);
+        }
+    };
+
+    loop {
+        // Healthy phase: watch the connection and ping until it fails
+        let mut failures = 0;
+        let reason = loop {
+            tokio::select! {
+                _ = client.closed() => break "connection closed".to_string(),
+                _ = tokio::time::sleep(PING_INTERVAL) => {}
+            }
+            if client.has_exited().await {
+                break "process exited".to_string();
+            }
+            match tokio::time::timeout(PING_TIMEOUT, client.ping()).await {
+                Ok(Ok(())) => {
+                    failures = 0;
+                    set(ServerStatus::Ready, None);
+                }
+                result => {
+                    failures += 1;
+                    let error = match result {
+                        Ok(Err(e)) => e.to_string(),
+                        _ => "ping timed out".to_string(),
+                    };
+                    if failures >= MAX_PING_FAILURES {
+                        break format!("no answer to {} pings ({})", failures, error);
+                    }
+                    set(ServerStatus::Degraded, Some(&error));
+                }
+            }
+        };
+
+        eprintln!("MCP server {} crashed: {}", name, reason);
+        logs::append(&name, LogSource::Client, "error", None, &format!("Crashed: {}", reason));
+        set(ServerStatus::Crashed, Some(&reason));
+        // Take it out of the active clients (unless something replaced it meanwhile) and make
+        // sure the process is gone; errors are expected here, the server is already dead
+        if McpClient::get_client(&name).is_some_and(|current| Arc::ptr_eq(&current, &client)) {
+            let _ = McpClient::disconnect(&name).await;
+        } else {
+            let _ = client.close().await;
+        }
+
+        // Restart phase
+        let mut attempt = 0;
+        client = loop {
+            attempt += 1;
+            if attempt > MAX_RESTARTS {
+                eprintln!("Giving up on MCP server {} after {} restarts", name, MAX_RESTARTS);
+                return;
+            }
+            let backoff = Duration::from_secs(1 << (attempt - 1)).min(MAX_BACKOFF);
+            tokio::time::sleep(backoff).await;
+            set(ServerStatus::Connecting, None);
+            match McpClient::connect_config(&app, &config).await {
+                Ok(client) => break client,
+                Err(e) => {
+                    eprintln!("Restarting MCP server {} failed (attempt {}): {}", name, attempt, e);
+                    logs::append(&name, LogSource::Client, "error", None, &format!("Restart attempt {} failed: {}", attempt, e));
+                    set(ServerStatus::Crashed, Some(&e.to_string()));
+                }
+            }
+        };
+        println!("Restarted MCP server {}", name);
+        set(ServerStatus::Ready, None);
+    }
+}
@@ -4,63 +4,122 @@ use tokio::process::{Child, Command};
 use anyhow::{Result, Context};
 use serde_json::Value;
 use futures_util::StreamExt;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use eventsource_stream::Eventsource;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::StatusCode;
 use reqwest_eventsource::{Event, EventSource, RequestBuilderExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinHandle;
+use crate::mcp::logs::{self, LogSource};
+
+/// How long a send waits for an SSE server to announce its POST endpoint
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Times in a row a Streamable HTTP event stream is resumed after dropping
+const MAX_RESUME_ATTEMPTS: u32 = 5;
+const SESSION_HEADER: &str = "mcp-session-id";
 
 // ============================================================================
 // Stdio Transport
 // ============================================================================
 
+// The reading and writing halves are locked separately so that the client's reader task can
+// wait on `receive` while requests are being sent.
 pub struct StdioTransport {
-    process: Child,
-    reader: BufReader<tokio::process::ChildStdout>,
-    writer: tokio::process::ChildStdin,
+    /// Server name, for its log
+    name: String,
+    process: Mutex<Child>,
+    reader: Mutex<BufReader<tokio::process::ChildStdout>>,
+    writer: Mutex<tokio::process::ChildStdin>,
 }
 
 impl StdioTransport {
-    pub fn new(command: &str, args: &[String]) -> Result<Self> {
-        let mut process = Command::new(command)
-            .args(args)
+    pub fn new(name: &str, command: &str, args: &[String], env: &HashMap<String, String>, cwd: Option<&str>) -> Result<Self> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .envs(env)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
+            .stderr(Stdio::piped())
+            // Never leave a server running once its client is gone
+            .kill_on_drop(true);
+        if let Some(dir) = cwd {
+            cmd.current_dir(dir);
+        }
+        let mut process = cmd.spawn()
             .context(format!("Failed to spawn command: {}", command))?;
 
         let stdin = process.stdin.take().context("Failed to open stdin")?;
         let stdout = process.stdout.take().context("Failed to open stdout")?;
         let reader = BufReader::new(stdout);
 
+        // Keep stderr in the server's log; it ends by itself when the process exits
+        if let Some(stderr) = process.stderr.take() {
+            let name = name.to_string();
+            tokio::spawn(async move {
+                // Read bytes, not text: a server printing invalid UTF-8 must not end the log
+                let mut stderr = BufReader::new(stderr);
+                let mut line = Vec::new();
+                while matches!(stderr.read_until(b'\n', &mut line).await, Ok(n) if n > 0) {
+                    logs::append(&name, LogSource::Stderr, "info", None, &String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            });
+        }
+
         Ok(Self {
-            process,
-            reader,
-            writer: stdin,
+            name: name.to_string(),
+            process: Mutex::new(process),
+            reader: Mutex::new(reader),
+            writer: Mutex::new(stdin),
         })
     }
 
-    pub async fn send(&mut self, message: Value) -> Result<()> {
-        let json = serde_json::to_string(&message)?;
-        self.writer.write_all(json.as_bytes()).await?;
-        self.writer.write_all(b"\n").await?;
-        self.writer.flush().await?;
+    pub async fn send(&self, message: Value) -> Result<()> {
+        let mut json = serde_json::to_string(&message)?;
+        json.push('\n');
+        // One write per message so concurrent senders never interleave lines
+        let mut writer = self.writer.lock().await;
+        writer.write_all(json.as_bytes()).await?;
+        writer.flush().await?;
         Ok(())
     }
 
-    pub async fn receive(&mut self) -> Result<Option<Value>> {
-        let mut line = String::new();
-        let bytes_read = self.reader.read_line(&mut line).await?;
-        if bytes_read == 0 {
-            return Ok(None);
+    pub async fn receive(&self) -> Result<Option<Value>> {
+        let mut reader = self.reader.lock().await;
+        loop {
+            let mut line = String::new();
+            let bytes_read = reader.read_line(&mut line).await?;
+            if bytes_read == 0 {
+                return Ok(None);
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Servers sometimes print logging to stdout; skip it rather than drop the connection
+            match serde_json::from_str::<Value>(&line) {
+                Ok(message) => return Ok(Some(message)),
+                Err(e) => logs::append(
+                    &self.name,
+                    LogSource::Client,
+                    "warning",
+                    None,
+                    &format!("Ignored non-JSON output on stdout ({}): {}", e, line.trim_end()),
+                ),
+            }
         }
-        let message: Value = serde_json::from_str(&line).context("Failed to parse JSON")?;
-        Ok(Some(message))
     }
 
-    #[allow(dead_code)]
-    pub async fn close(&mut self) -> Result<()> {
-        self.process.kill().await?;
+    pub async fn close(&self) -> Result<()> {
+        self.process.lock().await.kill().await?;
         Ok(())
     }
+
+    pub async fn has_exited(&self) -> bool {
+        matches!(self.process.lock().await.try_wait(), Ok(Some(_)))
+    }
 }
 
 // ============================================================================
@@ -68,9 +127,10 @@ impl StdioTransport {
 // ============================================================================
 
 pub struct SseTransport {
-    event_source: EventSource,
+    event_source: Mutex<EventSource>,
     client: reqwest::Client,
-    post_url: Option<String>,
+    /// POST endpoint announced by the server's `endpoint` event
+    post_url: watch::Sender<Option<String>>,
     headers: HeaderMap,
 }
 
@@ -89,35 +149,40 @@ impl SseTransport {
             .eventsource()?;
 
         Ok(Self {
-            event_source,
+            event_source: Mutex::new(event_source),
             client,
-            post_url: None,
+            post_url: watch::Sender::new(None),
             headers,
         })
     }
 
-    pub async fn send(&mut self, message: Value) -> Result<()> {
-        if let Some(url) = &self.post_url {
-            self.client.post(url)
-                .headers(self.headers.clone())
-                .json(&message)
-                .send()
-                .await?
-                .error_for_status()?;
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("No POST endpoint discovered yet"))
-        }
+    pub async fn send(&self, message: Value) -> Result<()> {
+        // The endpoint arrives on the event stream, which the reader task is consuming
+        let mut endpoint = self.post_url.subscribe();
+        let url = tokio::time::timeout(ENDPOINT_TIMEOUT, endpoint.wait_for(|url| url.is_some()))
+            .await
+            .map_err(|_| anyhow::anyhow!("No POST endpoint discovered yet"))?
+            .map_err(|_| anyhow::anyhow!("Connection closed"))?
+            .clone()
+            .unwrap_or_default();
+        self.client.post(&url)
+            .headers(self.headers.clone())
+            .json(&message)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
     }
 
-    pub async fn receive(&mut self) -> Result<Option<Value>> {
-        while let Some(event) = self.event_source.next().await {
+    pub async fn receive(&self) -> Result<Option<Value>> {
+        let mut event_source = self.event_source.lock().await;
+        while let Some(event) = event_source.next().await {
             match event {
                 Ok(Event::Open) => continue,
                 Ok(Event::Message(message)) => {
                     // Check for endpoint event first
                     if message.event == "endpoint" {
-                        self.post_url = Some(message.data.trim().to_string());
+                        self.post_url.send_replace(Some(message.data.trim().to_string()));
                         continue;
                     }
                     // Try to parse as JSON-RPC message
@@ -131,11 +196,273 @@ impl SseTransport {
         Ok(None)
     }
 
-    #[allow(dead_code)]
-    pub async fn close(&mut self) -> Result<()> {
-        self.event_source.close();
+    pub async fn close(&self) -> Result<()> {
+        self.event_source.lock().await.close();
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Streamable HTTP Transport (protocol 2025-03-26)
+// ============================================================================
+
+/// State shared with the tasks reading event streams
+#[derive(Clone)]
+struct HttpShared {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    /// Assigned by the server in its answer to `initialize`, sent back on every request
+    session_id: Arc<std::sync::Mutex<Option<String>>>,
+    incoming: mpsc::UnboundedSender<Value>,
+    /// Set once the transport is closed; `receive` then returns None
+    closed: Arc<watch::Sender<bool>>,
+}
+
+/// One endpoint: every message is POSTed, and the server answers with JSON, with an SSE
+/// stream carrying the response (and any requests it makes meanwhile), or with 202 for
+/// notifications and responses. A GET stream carries messages the server sends unprompted.
+pub struct StreamableHttpTransport {
+    shared: HttpShared,
+    incoming: Mutex<mpsc::UnboundedReceiver<Value>>,
+    streams: std::sync::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl StreamableHttpTransport {
+    pub fn new(url: &str, auth_token: Option<String>) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = auth_token {
+            let mut val = HeaderValue::from_str(&format!("Bearer {}", token))?;
+            val.set_sensitive(true);
+            headers.insert(AUTHORIZATION, val);
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        Ok(Self {
+            shared: HttpShared {
+                client: reqwest::Client::new(),
+                url: url.to_string(),
+                headers,
+                session_id: Arc::new(std::sync::Mutex::new(None)),
+                incoming: tx,
+                closed: Arc::new(watch::Sender::new(false)),
+            },
+            incoming: Mutex::new(rx),
+            streams: std::sync::Mutex::new(Vec::new()),
+        })
+    }
+
+    pub async fn send(&self, message: Value) -> Result<()> {
+        let initialized = message.get("method").and_then(|m| m.as_str()) == Some("notifications/initialized");
+        let request_id = message.get("method").and(message.get("id")).cloned();
+
+        let response = self.shared.request(reqwest::Method::POST)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(&message)
+            .send()
+            .await?;
+        // The server dropped our session. A new one needs a new `initialize`, which the
+        // supervisor sends when it reconnects after the transport closes.
+        if response.status() == StatusCode::NOT_FOUND && self.shared.session().is_some() {
+            self.close().await?;
+            anyhow::bail!("MCP session expired");
+        }
+        let response = response.error_for_status()?;
+        if let Some(session) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            self.shared.set_session(Some(session.to_string()));
+        }
+
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if content_type.starts_with("text/event-stream") {
+            self.spawn_stream(self.shared.clone().pump(response, request_id));
+        } else if content_type.starts_with("application/json") {
+            match response.json::<Value>().await? {
+                Value::Array(batch) => batch.into_iter().for_each(|m| { let _ = self.shared.incoming.send(m); }),
+                single => { let _ = self.shared.incoming.send(single); }
+            }
+        }
+
+        // Once the session is set up, listen for messages the server sends on its own
+        if initialized {
+            self.spawn_stream(self.shared.clone().listen());
+        }
+        Ok(())
+    }
+
+    pub async fn receive(&self) -> Result<Option<Value>> {
+        let mut incoming = self.incoming.lock().await;
+        let mut closed = self.shared.closed.subscribe();
+        // Messages that arrived before the transport closed are still delivered
+        tokio::select! {
+            biased;
+            message = incoming.recv() => Ok(message),
+            _ = closed.wait_for(|c| *c) => Ok(None),
+        }
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        self.shared.closed.send_replace(true);
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.drain(..).for_each(|task| task.abort());
+        }
+        // Tell the server it can drop the session; servers that do not allow this answer 405
+        if self.shared.session().is_some() {
+            let _ = self.shared.request(reqwest::Method::DELETE).send().await;
+            self.shared.set_session(None);
+        }
         Ok(())
     }
+
+    fn spawn_stream<F>(&self, stream: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let task = tokio::spawn(stream);
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.retain(|t| !t.is_finished());
+            streams.push(task);
+        }
+    }
+}
+
+impl HttpShared {
+    fn session(&self) -> Option<String> {
+        self.session_id.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn set_session(&self, session: Option<String>) {
+        if let Ok(mut current) = self.session_id.lock() {
+            *current = session;
+        }
+    }
+
+    fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let mut request = self.client.request(method, &self.url).headers(self.headers.clone());
+        if let Some(session) = self.session() {
+            request = request.header(SESSION_HEADER, session);
+        }
+        request
+    }
+
+    /// GET the endpoint as an event stream, resuming after `last_event_id` when given
+    async fn open_stream(&self, last_event_id: Option<&str>) -> Result<reqwest::Response> {
+        let mut request = self.request(reqwest::Method::GET).header(ACCEPT, "text/event-stream");
+        if let Some(id) = last_event_id {
+            request = request.header("last-event-id", id);
+        }
+        Ok(request.send().await?.error_for_status()?)
+    }
+
+    /// Forward the messages of an event stream. A stream that drops before the response to
+    /// `request_id` arrived is resumed from the last event id the server gave.
+    async fn pump(self, response: reqwest::Response, request_id: Option<Value>) {
+        let mut response = response;
+        let mut last_event_id: Option<String> = None;
+        let mut retry = Duration::from_secs(1);
+        let mut attempts = 0;
+
+        loop {
+            let mut answered = false;
+            let mut events = response.bytes_stream().eventsource();
+            while let Some(event) = events.next().await {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        eprintln!("MCP event stream error: {}", e);
+                        break;
+                    }
+                };
+                attempts = 0;
+                if !event.id.is_empty() {
+                    last_event_id = Some(event.id.clone());
+                }
+                if let Some(ms) = event.retry {
+                    retry = ms;
+                }
+                if event.data.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&event.data) {
+                    Ok(message) => {
+                        answered |= request_id.is_some()
+                            && message.get("method").is_none()
+                            && message.get("id") == request_id.as_ref();
+                        let _ = self.incoming.send(message);
+                    }
+                    Err(e) => eprintln!("Ignoring invalid MCP event ({}): {}", e, event.data),
+                }
+            }
+
+            let Some(id) = &request_id else { return };
+            if answered {
+                return;
+            }
+            attempts += 1;
+            let resumed = match &last_event_id {
+                Some(last) if attempts <= MAX_RESUME_ATTEMPTS => {
+                    tokio::time::sleep(retry).await;
+                    self.open_stream(Some(last)).await.ok()
+                }
+                _ => None,
+            };
+            match resumed {
+                Some(next) => response = next,
+                None => {
+                    // Fail the request instead of leaving its caller waiting
+                    let _ = self.incoming.send(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32000, "message": "Event stream closed before the response arrived" }
+                    }));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Keep a GET stream open for server-initiated messages. Servers without one answer 405.
+    async fn listen(self) {
+        let mut last_event_id: Option<String> = None;
+        let mut attempts = 0;
+        loop {
+            let response = match self.open_stream(last_event_id.as_deref()).await {
+                Ok(response) => response,
+                Err(e) => {
+                    let status = e.downcast_ref::<reqwest::Error>().and_then(|e| e.status());
+                    if status != Some(StatusCode::METHOD_NOT_ALLOWED) {
+                        eprintln!("MCP listening stream unavailable: {}", e);
+                    }
+                    return;
+                }
+            };
+            let mut events = response.bytes_stream().eventsource();
+            while let Some(Ok(event)) = events.next().await {
+                attempts = 0;
+                if !event.id.is_empty() {
+                    last_event_id = Some(event.id.clone());
+                }
+                if let Ok(message) = serde_json::from_str::<Value>(&event.data) {
+                    let _ = self.incoming.send(message);
+                }
+            }
+            attempts += 1;
+            if attempts > MAX_RESUME_ATTEMPTS {
+                eprintln!("MCP listening stream closed");
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs(attempts as u64)).await;
+        }
+    }
+}
+
+/// Whether a failed Streamable HTTP `initialize` means the server only speaks the older
+/// HTTP+SSE protocol (it rejects the POST with 400, 404 or 405)
+pub fn is_legacy_sse_rejection(error: &anyhow::Error) -> bool {
+    let status = error.downcast_ref::<reqwest::Error>().and_then(|e| e.status());
+    matches!(status, Some(StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED))
 }
 
 // ============================================================================
@@ -145,28 +472,271 @@ impl SseTransport {
 pub enum Transport {
     Stdio(StdioTransport),
     Sse(SseTransport),
+    StreamableHttp(StreamableHttpTransport),
 }
 
 impl Transport {
-    pub async fn send(&mut self, message: Value) -> Result<()> {
+    /// Protocol revision offered in `initialize`
+    pub fn protocol_version(&self) -> &'static str {
+        match self {
+            Transport::StreamableHttp(_) => "2025-03-26",
+            _ => "2024-11-05",
+        }
+    }
+
+    pub async fn send(&self, message: Value) -> Result<()> {
         match self {
             Transport::Stdio(t) => t.send(message).await,
             Transport::Sse(t) => t.send(message).await,
+            Transport::StreamableHttp(t) => t.send(message).await,
         }
     }
 
-    pub async fn receive(&mut self) -> Result<Option<Value>> {
+    pub async fn receive(&self) -> Result<Option<Value>> {
         match self {
             Transport::Stdio(t) => t.receive().await,
             Transport::Sse(t) => t.receive().await,
+            Transport::StreamableHttp(t) => t.receive().await,
+        }
+    }
+
+    /// Whether a stdio server's process has exited; HTTP servers have no process to watch
+    pub async fn has_exited(&self) -> bool {
+        match self {
+            Transport::Stdio(t) => t.has_exited().await,
+            _ => false,
         }
     }
 
-    #[allow(dead_code)]
-    pub async fn close(&mut self) -> Result<()> {
+    pub async fn close(&self) -> Result<()> {
         match self {
             Transport::Stdio(t) => t.close().await,
             Transport::Sse(t) => t.close().await,
+            Transport::StreamableHttp(t) => t.close().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// A request as the mock server received it
+    #[derive(Debug, Clone)]
+    struct Seen {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: Value,
+    }
+
+    enum Reply {
+        /// Written as is, then the connection is closed
+        Full(String),
+        /// A response head, then chunks as they come until the sender is dropped
+        Stream(String, mpsc::UnboundedReceiver<String>),
+    }
+
+    type Handler = Arc<dyn Fn(&Seen) -> Reply + Send + Sync>;
+    type SeenLog = Arc<std::sync::Mutex<Vec<Seen>>>;
+
+    /// Answer every connection to a local port with `handler`, one request per connection.
+    /// Returns the base URL and the requests received.
+    async fn mock_server(handler: Handler) -> (String, SeenLog) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let seen: SeenLog = Arc::default();
+        let log = seen.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, handler.clone(), log.clone()));
+            }
+        });
+        (base, seen)
+    }
+
+    async fn serve(socket: TcpStream, handler: Handler, log: SeenLog) {
+        let (read, mut write) = socket.into_split();
+        let mut reader = BufReader::new(read);
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            match line.trim_end().split_once(':') {
+                Some((name, value)) => { headers.insert(name.to_lowercase(), value.trim().to_string()); }
+                None => break,
+            }
+        }
+        let length = headers.get("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await.unwrap();
+        let seen = Seen { method, path, headers, body: serde_json::from_slice(&body).unwrap_or(Value::Null) };
+        log.lock().unwrap().push(seen.clone());
+
+        match handler(&seen) {
+            Reply::Full(response) => { let _ = write.write_all(response.as_bytes()).await; }
+            Reply::Stream(head, mut chunks) => {
+                let _ = write.write_all(head.as_bytes()).await;
+                while let Some(chunk) = chunks.recv().await {
+                    let _ = write.write_all(chunk.as_bytes()).await;
+                }
+            }
         }
+        let _ = write.shutdown().await;
+    }
+
+    fn status(code: u16) -> Reply {
+        Reply::Full(format!("HTTP/1.1 {} Mock\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", code))
+    }
+
+    fn json_reply(body: Value, extra_headers: &str) -> Reply {
+        let body = body.to_string();
+        Reply::Full(format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+            body.len(), extra_headers, body
+        ))
+    }
+
+    const SSE_HEAD: &str = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n";
+
+    fn sse_reply(events: &str) -> Reply {
+        Reply::Full(format!("{}{}", SSE_HEAD, events))
+    }
+
+    fn rpc_method(seen: &Seen) -> &str {
+        seen.body.get("method").and_then(|m| m.as_str()).unwrap_or_default()
+    }
+
+    async fn next(transport: &StreamableHttpTransport) -> Option<Value> {
+        tokio::time::timeout(Duration::from_secs(5), transport.receive()).await
+            .expect("no message within 5s")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn streamable_http_session_json_sse_and_resume() {
+        let (base, seen) = mock_server(Arc::new(|req: &Seen| {
+            match (req.method.as_str(), rpc_method(req)) {
+                ("POST", "initialize") => json_reply(
+                    json!({ "jsonrpc": "2.0", "id": req.body["id"], "result": { "protocolVersion": "2025-03-26" } }),
+                    "Mcp-Session-Id: s1\r\n",
+                ),
+                ("POST", method) if method.starts_with("notifications/") => status(202),
+                // The stream drops before the response; it comes when the client resumes
+                ("POST", "tools/list") => sse_reply(
+                    "id: e1\nretry: 10\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+                ),
+                ("POST", "ping") => sse_reply(&format!("data: {}\n\n", json!({ "jsonrpc": "2.0", "id": req.body["id"], "result": {} }))),
+                ("GET", _) if req.headers.get("last-event-id").map(String::as_str) == Some("e1") => {
+                    sse_reply("id: e2\ndata: {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"tools\":[]}}\n\n")
+                }
+                // No stream for unprompted messages
+                ("GET", _) => status(405),
+                ("DELETE", _) => status(200),
+                _ => status(500),
+            }
+        })).await;
+        let transport = StreamableHttpTransport::new(&format!("{}/mcp", base), None).unwrap();
+
+        // JSON answer, which assigns the session
+        transport.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })).await.unwrap();
+        assert_eq!(next(&transport).await.unwrap()["result"]["protocolVersion"], "2025-03-26");
+
+        // Notifications get 202 and no message
+        transport.send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await.unwrap();
+
+        // SSE answer, resumed with Last-Event-ID after the stream dropped
+        transport.send(json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" })).await.unwrap();
+        assert_eq!(next(&transport).await.unwrap()["method"], "notifications/progress");
+        assert_eq!(next(&transport).await.unwrap()["result"], json!({ "tools": [] }));
+
+        // SSE answer carrying the response directly
+        transport.send(json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" })).await.unwrap();
+        assert_eq!(next(&transport).await.unwrap()["id"], 3);
+
+        transport.close().await.unwrap();
+        assert_eq!(next(&transport).await, None);
+
+        let seen = seen.lock().unwrap().clone();
+        assert!(seen.iter().all(|r| r.path == "/mcp"));
+        let session = |r: &Seen| r.headers.get(SESSION_HEADER).cloned();
+        let initialize = seen.iter().find(|r| rpc_method(r) == "initialize").unwrap();
+        assert_eq!(session(initialize), None);
+        assert!(seen.iter().filter(|r| rpc_method(r) != "initialize").all(|r| session(r).as_deref() == Some("s1")));
+        assert!(seen.iter().any(|r| r.method == "GET" && r.headers.get("last-event-id").map(String::as_str) == Some("e1")));
+        assert!(seen.iter().any(|r| r.method == "DELETE"));
+    }
+
+    #[tokio::test]
+    async fn streamable_http_closes_when_the_session_expires() {
+        let (base, _) = mock_server(Arc::new(|req: &Seen| match rpc_method(req) {
+            "initialize" => json_reply(json!({ "jsonrpc": "2.0", "id": 1, "result": {} }), "Mcp-Session-Id: s1\r\n"),
+            "ping" => status(404),
+            _ => status(405),
+        })).await;
+        let transport = StreamableHttpTransport::new(&format!("{}/mcp", base), None).unwrap();
+
+        transport.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })).await.unwrap();
+        assert!(next(&transport).await.is_some());
+        let error = transport.send(json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" })).await.unwrap_err();
+        assert!(error.to_string().contains("session expired"));
+        // The reader sees the end of the connection, so the supervisor reconnects
+        assert_eq!(next(&transport).await, None);
+    }
+
+    #[tokio::test]
+    async fn legacy_servers_are_recognised_and_spoken_to_over_sse() {
+        for code in [400, 404, 405] {
+            let (base, _) = mock_server(Arc::new(move |_: &Seen| status(code))).await;
+            let transport = StreamableHttpTransport::new(&format!("{}/mcp", base), None).unwrap();
+            let error = transport.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })).await.unwrap_err();
+            assert!(is_legacy_sse_rejection(&error), "{} should fall back", code);
+        }
+        let (base, _) = mock_server(Arc::new(|_: &Seen| status(500))).await;
+        let transport = StreamableHttpTransport::new(&format!("{}/mcp", base), None).unwrap();
+        let error = transport.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })).await.unwrap_err();
+        assert!(!is_legacy_sse_rejection(&error));
+
+        // An HTTP+SSE server: the GET stream announces the POST endpoint and carries the answers
+        let (events, stream) = mpsc::unbounded_channel::<String>();
+        let stream = std::sync::Mutex::new(Some(stream));
+        let endpoint = Arc::new(std::sync::Mutex::new(String::new()));
+        let announced = endpoint.clone();
+        let (base, _) = mock_server(Arc::new(move |req: &Seen| match (req.method.as_str(), req.path.as_str()) {
+            ("POST", "/mcp") => status(405),
+            ("GET", "/mcp") => {
+                let _ = events.send(format!("event: endpoint\ndata: {}\n\n", announced.lock().unwrap()));
+                Reply::Stream(SSE_HEAD.to_string(), stream.lock().unwrap().take().unwrap())
+            }
+            ("POST", "/messages") => {
+                let answer = json!({ "jsonrpc": "2.0", "id": req.body["id"], "result": { "protocolVersion": "2024-11-05" } });
+                let _ = events.send(format!("event: message\ndata: {}\n\n", answer));
+                status(202)
+            }
+            _ => status(500),
+        })).await;
+        *endpoint.lock().unwrap() = format!("{}/messages", base);
+        let url = format!("{}/mcp", base);
+
+        let http = StreamableHttpTransport::new(&url, None).unwrap();
+        let error = http.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })).await.unwrap_err();
+        assert!(is_legacy_sse_rejection(&error));
+
+        let sse = Arc::new(SseTransport::new(&url, None).unwrap());
+        let reader = sse.clone();
+        let answer = tokio::spawn(async move { reader.receive().await });
+        sse.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })).await.unwrap();
+        let answer = tokio::time::timeout(Duration::from_secs(5), answer).await.unwrap().unwrap().unwrap();
+        assert_eq!(answer.unwrap()["result"]["protocolVersion"], "2024-11-05");
     }
 }
@@ -8,8 +8,8 @@ use serde_json::json;
 use std::collections::VecDeque;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use crate::providers::traits::{LLMProvider, ProviderEvent, Usage};
-use crate::providers::{ChatMessage, ProviderConfig, ChatOptions};
+use crate::providers::traits::{FinishReason, LLMProvider, ProviderEvent, Usage};
+use crate::providers::{image_mime_type, ChatMessage, ProviderConfig, ChatOptions};
 
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 
@@ -49,6 +49,8 @@ struct AnthropicDelta {
     delta_type: Option<String>,
     text: Option<String>,
     partial_json: Option<String>,
+    /// Set on `message_delta`
+    stop_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -69,6 +71,11 @@ pub struct AnthropicProvider;
 
 #[async_trait]
 impl LLMProvider for AnthropicProvider {
+    async fn supports_images(&self, _config: &ProviderConfig, _model: &str) -> bool {
+        // Every Claude 3+ model accepts images
+        true
+    }
+
     async fn stream_chat(
         &self,
         config: &ProviderConfig,
@@ -93,7 +100,7 @@ impl LLMProvider for AnthropicProvider {
         let mut request_body = AnthropicRequest {
             model: model.to_string(),
             messages: anthropic_messages,
-            max_tokens: 4096,
+            max_tokens: max_output_tokens(model),
             stream: true,
             system: system_prompt,
             tools: convert_tools(tools),
@@ -126,6 +133,23 @@ impl LLMProvider for AnthropicProvider {
     }
 }
 
+/// The most output tokens `model` can generate, used when the chat sets no `max_tokens`.
+/// Unknown (newer) models get the limit of the current generation.
+fn max_output_tokens(model: &str) -> i32 {
+    let model = model.to_lowercase();
+    if model.starts_with("claude-3-5") {
+        8192
+    } else if model.starts_with("claude-3-7") || model.starts_with("claude-sonnet-4") {
+        64000
+    } else if model.starts_with("claude-opus-4") {
+        32000
+    } else if model.starts_with("claude-3") || model.starts_with("claude-2") || model.starts_with("claude-instant") {
+        4096
+    } else {
+        32000
+    }
+}
+
 fn convert_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<AnthropicMessage>) {
     let mut system_prompt = None;
     let mut anthropic_messages = Vec::new();
@@ -143,12 +167,29 @@ fn convert_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<AnthropicM
             _ => "user",
         };
 
-        // Handle tool results
+        // Handle tool results (images returned by the tool go inside the result block)
         if msg.role == "tool" {
+            let result_content = match msg.images.as_ref().filter(|i| !i.is_empty()) {
+                Some(images) => {
+                    let mut parts = vec![json!({"type": "text", "text": msg.content})];
+                    for image in images {
+                        parts.push(json!({
+                            "type": "image",
+                            "source": {
+                                "type": "base64",
+                                "media_type": image_mime_type(image),
+                                "data": image
+                            }
+                        }));
+                    }
+                    serde_json::Value::Array(parts)
+                }
+                None => serde_json::Value::String(msg.content.clone()),
+            };
             let content = json!([{
                 "type": "tool_result",
                 "tool_use_id": msg.tool_call_id,
-                "content": msg.content
+                "content": result_content
             }]);
             anthropic_messages.push(AnthropicMessage {
                 role: role.to_string(),
@@ -168,7 +209,7 @@ fn convert_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<AnthropicM
                         "type": "image",
                         "source": {
                             "type": "base64",
-                            "media_type": "image/jpeg",
+                            "media_type": image_mime_type(image),
                             "data": image
                         }
                     }));
@@ -321,6 +362,9 @@ impl AnthropicStream {
                     }
                 }
                 "message_delta" => {
+                    if let Some(reason) = event.delta.as_ref().and_then(|d| d.stop_reason.as_deref()) {
+                        self.queue.push_back(ProviderEvent::Finish(FinishReason::from_provider(reason)));
+                    }
                     if let Some(usage) = event.usage {
                         if let Some(ot) = usage.output_tokens {
                             self.output_tokens += ot;
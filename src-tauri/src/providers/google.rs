@@ -8,8 +8,9 @@ use serde_json::json;
 use std::collections::VecDeque;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use crate::providers::traits::{LLMProvider, ProviderEvent, Usage};
-use crate::providers::{ChatMessage, ProviderConfig, ChatOptions};
+use crate::providers::traits::{FinishReason, LLMProvider, ProviderEvent, Usage};
+use crate::providers::{image_mime_type, ChatMessage, ProviderConfig, ChatOptions};
+use crate::providers::schema::SchemaDialect;
 
 #[derive(Debug, Serialize)]
 struct GeminiRequest {
@@ -75,7 +76,9 @@ struct GeminiTool {
 struct GeminiFunctionDeclaration {
     name: String,
     description: String,
-    parameters: serde_json::Value,
+    /// Omitted for tools without arguments: Gemini rejects an object schema with no properties
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -97,7 +100,6 @@ struct GeminiStreamResponse {
 struct GeminiCandidate {
     content: Option<GeminiContent>,
     #[serde(rename = "finishReason")]
-    #[allow(dead_code)]
     finish_reason: Option<String>,
 }
 
@@ -115,6 +117,14 @@ pub struct GoogleProvider;
 
 #[async_trait]
 impl LLMProvider for GoogleProvider {
+    fn schema_dialect(&self) -> SchemaDialect {
+        SchemaDialect::Gemini
+    }
+
+    async fn supports_images(&self, _config: &ProviderConfig, _model: &str) -> bool {
+        true
+    }
+
     async fn stream_chat(
         &self,
         config: &ProviderConfig,
@@ -173,8 +183,14 @@ impl LLMProvider for GoogleProvider {
 fn convert_messages(messages: &[ChatMessage]) -> (Option<GeminiContent>, Vec<GeminiContent>) {
     let mut system_instruction = None;
     let mut gemini_contents = Vec::new();
+    // Images returned by tools; function responses only carry JSON, so they follow as a user turn
+    let mut tool_images: Vec<GeminiPart> = Vec::new();
 
     for msg in messages {
+        if msg.role != "tool" && !tool_images.is_empty() {
+            gemini_contents.push(tool_images_content(std::mem::take(&mut tool_images)));
+        }
+
         if msg.role == "system" {
             system_instruction = Some(GeminiContent {
                 role: "user".to_string(), // System instructions in Gemini are separate, but fallback to user if not supported? 
@@ -202,6 +218,14 @@ fn convert_messages(messages: &[ChatMessage]) -> (Option<GeminiContent>, Vec<Gem
                     }
                 }],
             });
+            for image in msg.images.iter().flatten() {
+                tool_images.push(GeminiPart::InlineData {
+                    inline_data: GeminiInlineData {
+                        mime_type: image_mime_type(image).to_string(),
+                        data: image.clone(),
+                    }
+                });
+            }
             continue;
         }
 
@@ -211,7 +235,7 @@ fn convert_messages(messages: &[ChatMessage]) -> (Option<GeminiContent>, Vec<Gem
              for image in images {
                 parts.push(GeminiPart::InlineData {
                     inline_data: GeminiInlineData {
-                        mime_type: "image/jpeg".to_string(),
+                        mime_type: image_mime_type(image).to_string(),
                         data: image.clone(),
                     }
                 });
@@ -240,9 +264,19 @@ fn convert_messages(messages: &[ChatMessage]) -> (Option<GeminiContent>, Vec<Gem
         });
     }
 
+    if !tool_images.is_empty() {
+        gemini_contents.push(tool_images_content(tool_images));
+    }
+
     (system_instruction, gemini_contents)
 }
 
+fn tool_images_content(images: Vec<GeminiPart>) -> GeminiContent {
+    let mut parts = vec![GeminiPart::Text { text: "Images returned by the tool calls above:".to_string() }];
+    parts.extend(images);
+    GeminiContent { role: "user".to_string(), parts }
+}
+
 fn convert_tools(tools: Option<Vec<serde_json::Value>>) -> Option<Vec<GeminiTool>> {
     tools.map(|tool_list| {
         let declarations: Vec<GeminiFunctionDeclaration> = tool_list.into_iter().filter_map(|tool| {
@@ -250,7 +284,9 @@ fn convert_tools(tools: Option<Vec<serde_json::Value>>) -> Option<Vec<GeminiTool
                 GeminiFunctionDeclaration {
                     name: func.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string(),
                     description: func.get("description").and_then(|d| d.as_str()).unwrap_or("").to_string(),
-                    parameters: func.get("parameters").cloned().unwrap_or(json!({})),
+                    parameters: func.get("parameters")
+                        .filter(|p| p.get("properties").is_some())
+                        .cloned(),
                 }
             })
         }).collect();
@@ -298,6 +334,9 @@ impl GeminiStream {
                              }
                          }
                      }
+                     if let Some(reason) = &candidate.finish_reason {
+                         self.queue.push_back(ProviderEvent::Finish(FinishReason::from_provider(reason)));
+                     }
                  }
              }
              
@@ -31,6 +31,9 @@ pub struct ProviderConfig {
     pub api_key: Option<String>,
     pub base_url: Option<String>,
     pub enabled: bool,
+    /// Maximum number of generations running at once; extra requests are queued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<u32>,
 }
 
 impl ProviderConfig {
@@ -42,9 +45,20 @@ impl ProviderConfig {
             api_key: None,
             base_url: Some("http://localhost:11434".to_string()),
             enabled: true,
+            max_concurrent: None,
         }
     }
 
+    /// Concurrency limit used by the scheduler. A single local Ollama instance thrashes
+    /// when several chats load models at once, so it defaults to one generation at a time.
+    pub fn concurrency_limit(&self) -> usize {
+        let default = match self.provider_type {
+            ProviderType::Ollama => 1,
+            _ => 4,
+        };
+        self.max_concurrent.map(|m| m.max(1) as usize).unwrap_or(default)
+    }
+
     pub fn get_base_url(&self) -> String {
         match self.provider_type {
             ProviderType::Ollama => {
@@ -67,6 +81,30 @@ impl ProviderConfig {
     }
 }
 
+/// Adapter for a provider type
+pub fn create_provider(provider_type: &ProviderType) -> Box<dyn traits::LLMProvider + Send + Sync> {
+    match provider_type {
+        ProviderType::Ollama => Box::new(ollama::OllamaProvider),
+        ProviderType::OpenAI | ProviderType::Other => Box::new(openai::OpenAIProvider),
+        ProviderType::Anthropic => Box::new(anthropic::AnthropicProvider),
+        ProviderType::Google => Box::new(google::GoogleProvider),
+    }
+}
+
+/// Guess the MIME type of base64 image data from its magic bytes.
+/// Images reach us without a type (user uploads) or with one we can't always trust.
+pub fn image_mime_type(data: &str) -> &'static str {
+    if data.starts_with("iVBORw0KGgo") {
+        "image/png"
+    } else if data.starts_with("R0lGOD") {
+        "image/gif"
+    } else if data.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
+
 /// Unified message format across providers
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -106,3 +144,7 @@ pub struct ChatOptions {
 
 pub mod traits;
 pub mod orchestrator; // Pre-emptively adding this as next step
+pub mod scheduler;
+pub mod schema;
+pub mod tool_emulation;
+pub mod tool_recovery;
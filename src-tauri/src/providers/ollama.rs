@@ -4,11 +4,13 @@ use futures::{stream::BoxStream, Stream};
 use reqwest::Client;
 use serde::Deserialize;
 use serde_json::json;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
+use std::sync::Mutex;
 use std::task::{Context, Poll};
-use crate::providers::traits::{LLMProvider, ProviderEvent, Usage};
-use crate::providers::{ChatMessage, ProviderConfig, ChatOptions};
+use std::time::Duration;
+use crate::providers::traits::{FinishReason, LLMProvider, ProviderEvent, Usage};
+use crate::providers::{tool_emulation, tool_recovery, ChatMessage, ProviderConfig, ChatOptions};
 
 #[derive(Debug, Deserialize, Clone)]
 struct OllamaMessage {
@@ -22,15 +24,52 @@ struct OllamaMessage {
 struct OllamaResponse {
     message: Option<OllamaMessage>,
     done: bool,
+    done_reason: Option<String>,
     prompt_eval_count: Option<i32>,
     eval_count: Option<i32>,
     // timestamps...
 }
 
+/// How long to wait for `/api/show` before guessing vision support from the model name
+const SHOW_TIMEOUT: Duration = Duration::from_secs(3);
+
+lazy_static::lazy_static! {
+    /// Vision support reported by `/api/show`, keyed by server URL and model
+    static ref VISION_SUPPORT: Mutex<HashMap<String, bool>> = Mutex::new(HashMap::new());
+}
+
 pub struct OllamaProvider;
 
 #[async_trait]
 impl LLMProvider for OllamaProvider {
+    async fn supports_images(&self, config: &ProviderConfig, model: &str) -> bool {
+        let key = format!("{}|{}", config.get_base_url(), model);
+        if let Some(known) = VISION_SUPPORT.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+            return *known;
+        }
+
+        // Newer Ollama versions list "vision" among the model capabilities
+        let endpoint = format!("{}/api/show", config.get_base_url());
+        let shown = async {
+            Client::builder().timeout(SHOW_TIMEOUT).build()?
+                .post(&endpoint)
+                .json(&json!({ "model": model }))
+                .send()
+                .await?
+                .json::<serde_json::Value>()
+                .await
+        }.await;
+        if let Ok(info) = shown {
+            if let Some(capabilities) = info.get("capabilities").and_then(|c| c.as_array()) {
+                let vision = capabilities.iter().any(|c| c.as_str() == Some("vision"));
+                VISION_SUPPORT.lock().unwrap_or_else(|e| e.into_inner()).insert(key, vision);
+                return vision;
+            }
+        }
+        let model_lower = model.to_lowercase();
+        ["llava", "vision", "moondream", "bakllava", "minicpm-v", "-vl"].iter().any(|p| model_lower.contains(p))
+    }
+
     async fn stream_chat(
         &self,
         config: &ProviderConfig,
@@ -45,62 +84,53 @@ impl LLMProvider for OllamaProvider {
         // Use a default client or one from config
         let client = Client::builder().build()?;
         
-        let mut final_messages = messages.to_vec();
-        let has_tools = tools.as_ref().map(|t| !t.is_empty()).unwrap_or(false);
+        let tools = tools.filter(|t| !t.is_empty());
 
+        // Models that rejected native tools before get the tools through the prompt
+        if let Some(ref t) = tools {
+            if tool_emulation::is_required(&url, model) {
+                return send_emulated(&client, &endpoint, model, messages, t, &options).await;
+            }
+        }
+
+        let mut final_messages = messages.to_vec();
         let mut payload = json!({
             "model": model,
             "stream": true,
         });
         
         if let Some(ref t) = tools {
-            if !t.is_empty() {
-                // Inject System Prompt for tool usage support on small models
-                let instruction = "\nYou have access to tools/functions. If the user asks for something that requires a tool, please use the available tools to verify or retrieve information. Ensure you use the correct tool name and arguments.";
+            // Inject System Prompt for tool usage support on small models
+            let instruction = "\nYou have access to tools/functions. If the user asks for something that requires a tool, please use the available tools to verify or retrieve information. Ensure you use the correct tool name and arguments.";
 
-                if let Some(first) = final_messages.first_mut() {
-                    if first.role == "system" {
-                        first.content.push_str(instruction);
-                    } else {
-                        final_messages.insert(0, ChatMessage {
-                            role: "system".to_string(),
-                            content: instruction.trim().to_string(),
-                            images: None,
-                            tool_calls: None,
-                            tool_call_id: None,
-                        });
-                    }
+            if let Some(first) = final_messages.first_mut() {
+                if first.role == "system" {
+                    first.content.push_str(instruction);
                 } else {
-                     final_messages.push(ChatMessage {
-                            role: "system".to_string(),
-                            content: instruction.trim().to_string(),
-                            images: None,
-                            tool_calls: None,
-                            tool_call_id: None,
-                        });
+                    final_messages.insert(0, ChatMessage {
+                        role: "system".to_string(),
+                        content: instruction.trim().to_string(),
+                        images: None,
+                        tool_calls: None,
+                        tool_call_id: None,
+                    });
                 }
-                
-                payload["tools"] = json!(t);
+            } else {
+                 final_messages.push(ChatMessage {
+                        role: "system".to_string(),
+                        content: instruction.trim().to_string(),
+                        images: None,
+                        tool_calls: None,
+                        tool_call_id: None,
+                    });
             }
+            
+            payload["tools"] = json!(t);
         }
 
         payload["messages"] = json!(final_messages);
-        
-        if let Some(ref opts) = options {
-             let mut options_map = serde_json::Map::new();
-             if let Some(temp) = opts.temperature { 
-                 options_map.insert("temperature".to_string(), json!(temp)); 
-             }
-             if let Some(top_k) = opts.top_k { 
-                 options_map.insert("top_k".to_string(), json!(top_k)); 
-             }
-             if let Some(top_p) = opts.top_p { 
-                 options_map.insert("top_p".to_string(), json!(top_p)); 
-             }
-             if let Some(max_tokens) = opts.max_tokens { 
-                 options_map.insert("num_predict".to_string(), json!(max_tokens)); 
-             }
-             payload["options"] = json!(options_map);
+        if let Some(options_map) = ollama_options(&options) {
+            payload["options"] = options_map;
         }
 
         let response = client.post(&endpoint)
@@ -111,54 +141,70 @@ impl LLMProvider for OllamaProvider {
         if !response.status().is_success() {
             let text = response.text().await.unwrap_or_default();
             
-            // Check if the error is about tools not being supported
-            if has_tools && text.contains("does not support tools") {
-                // Retry without tools - rebuild payload without tools field
-                let mut retry_payload = json!({
-                    "model": model,
-                    "stream": true,
-                    "messages": json!(messages), // Use original messages without tool instruction
-                });
-                
-                if let Some(ref opts) = options {
-                    let mut options_map = serde_json::Map::new();
-                    if let Some(temp) = opts.temperature { 
-                        options_map.insert("temperature".to_string(), json!(temp)); 
-                    }
-                    if let Some(top_k) = opts.top_k { 
-                        options_map.insert("top_k".to_string(), json!(top_k)); 
-                    }
-                    if let Some(top_p) = opts.top_p { 
-                        options_map.insert("top_p".to_string(), json!(top_p)); 
-                    }
-                    if let Some(max_tokens) = opts.max_tokens { 
-                        options_map.insert("num_predict".to_string(), json!(max_tokens)); 
-                    }
-                    retry_payload["options"] = json!(options_map);
-                }
-                
-                let retry_response = client.post(&endpoint)
-                    .json(&retry_payload)
-                    .send()
-                    .await?;
-                
-                if !retry_response.status().is_success() {
-                    let retry_text = retry_response.text().await.unwrap_or_default();
-                    return Err(anyhow::anyhow!("Ollama error: {}", retry_text));
+            // The model has no tool support in its template: describe the tools in the prompt instead
+            if let Some(ref t) = tools {
+                if text.contains("does not support tools") {
+                    println!("Model {} does not support native tools; emulating tool calls", model);
+                    tool_emulation::mark_required(&url, model);
+                    return send_emulated(&client, &endpoint, model, messages, t, &options).await;
                 }
-                
-                // Create stream with a warning message prepended
-                let warning_msg = format!("**Note:** The model `{}` does not support MCP tools. Continuing without tool access.\n\n", model);
-                let stream = retry_response.bytes_stream();
-                return Ok(Box::pin(OllamaStream::new_with_warning(Box::pin(stream), warning_msg)));
             }
             
             return Err(anyhow::anyhow!("Ollama error: {}", text));
         }
 
         let stream = response.bytes_stream();
-        Ok(Box::pin(OllamaStream::new(Box::pin(stream))))
+        Ok(tool_recovery::recover_tool_calls(Box::pin(OllamaStream::new(Box::pin(stream))), tools.as_deref(), false))
+    }
+}
+
+/// Send a chat request with prompt-based tool calling; tool calls are parsed out of the reply text
+async fn send_emulated(
+    client: &Client,
+    endpoint: &str,
+    model: &str,
+    messages: &[ChatMessage],
+    tools: &[serde_json::Value],
+    options: &Option<ChatOptions>,
+) -> anyhow::Result<BoxStream<'static, ProviderEvent>> {
+    let mut payload = json!({
+        "model": model,
+        "stream": true,
+        "messages": tool_emulation::rewrite_messages(messages, tools),
+    });
+    if let Some(options_map) = ollama_options(options) {
+        payload["options"] = options_map;
+    }
+
+    let response = client.post(endpoint)
+        .json(&payload)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow::anyhow!("Ollama error: {}", text));
     }
+
+    let stream = response.bytes_stream();
+    Ok(tool_recovery::recover_tool_calls(Box::pin(OllamaStream::new(Box::pin(stream))), Some(tools), false))
+}
+
+fn ollama_options(options: &Option<ChatOptions>) -> Option<serde_json::Value> {
+    let opts = options.as_ref()?;
+    let mut options_map = serde_json::Map::new();
+    if let Some(temp) = opts.temperature { 
+        options_map.insert("temperature".to_string(), json!(temp)); 
+    }
+    if let Some(top_k) = opts.top_k { 
+        options_map.insert("top_k".to_string(), json!(top_k)); 
+    }
+    if let Some(top_p) = opts.top_p { 
+        options_map.insert("top_p".to_string(), json!(top_p)); 
+    }
+    if let Some(max_tokens) = opts.max_tokens { 
+        options_map.insert("num_predict".to_string(), json!(max_tokens)); 
+    }
+    Some(json!(options_map))
 }
 
 struct OllamaStream {
@@ -176,16 +222,6 @@ impl OllamaStream {
         }
     }
     
-    fn new_with_warning(inner: Pin<Box<dyn Stream<Item = reqwest::Result<bytes::Bytes>> + Send>>, warning: String) -> Self {
-        let mut queue = VecDeque::new();
-        queue.push_back(ProviderEvent::Content(warning));
-        Self {
-            inner,
-            buffer: String::new(),
-            queue,
-        }
-    }
-    
     fn process_line(&mut self, line: &str) {
         if line.trim().is_empty() { return; }
         
@@ -213,6 +249,9 @@ impl OllamaStream {
                          total_tokens: Some(chunk.prompt_eval_count.unwrap_or(0) + chunk.eval_count.unwrap_or(0)),
                      };
                      self.queue.push_back(ProviderEvent::Usage(usage));
+                     if let Some(reason) = chunk.done_reason.as_deref() {
+                         self.queue.push_back(ProviderEvent::Finish(FinishReason::from_provider(reason)));
+                     }
                 }
             }
             Err(_e) => {
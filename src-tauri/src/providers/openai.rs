@@ -8,8 +8,10 @@ use serde_json::json;
 use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use crate::providers::traits::{LLMProvider, ProviderEvent, Usage};
-use crate::providers::{ChatMessage, ProviderConfig, ChatOptions};
+use crate::providers::traits::{FinishReason, LLMProvider, ProviderEvent, Usage};
+use crate::providers::tool_recovery;
+use crate::providers::{image_mime_type, ChatMessage, ProviderConfig, ChatOptions};
+use crate::providers::schema::SchemaDialect;
 
 #[derive(Debug, Serialize)]
 struct OpenAIRequest {
@@ -67,6 +69,14 @@ pub struct OpenAIProvider;
 
 #[async_trait]
 impl LLMProvider for OpenAIProvider {
+    fn schema_dialect(&self) -> SchemaDialect {
+        SchemaDialect::OpenAI
+    }
+
+    async fn supports_images(&self, _config: &ProviderConfig, model: &str) -> bool {
+        model_supports_vision(model)
+    }
+
     async fn stream_chat(
         &self,
         config: &ProviderConfig,
@@ -93,14 +103,7 @@ impl LLMProvider for OpenAIProvider {
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
         // Detect if the model supports vision based on common naming patterns
-        let model_lower = model.to_lowercase();
-        let supports_vision = model_lower.contains("vision") 
-            || model_lower.contains("gpt-4o")
-            || model_lower.contains("gpt-4-turbo")
-            || model_lower.contains("claude-3")
-            || model_lower.contains("gemini")
-            || model_lower.contains("llava")
-            || model_lower.contains("moondream");
+        let supports_vision = model_supports_vision(model);
 
         let mut converted_messages = convert_messages(messages, supports_vision);
         
@@ -153,20 +156,33 @@ impl LLMProvider for OpenAIProvider {
         }
 
         let stream = response.bytes_stream();
-        Ok(Box::pin(OpenAIStream::new(Box::pin(stream))))
+        Ok(tool_recovery::recover_tool_calls(Box::pin(OpenAIStream::new(Box::pin(stream))), tools.as_deref(), true))
     }
 }
 
 fn convert_messages(messages: &[ChatMessage], supports_vision: bool) -> Vec<OpenAIMessage> {
-    messages.iter().map(|msg| {
+    let mut converted = Vec::new();
+    // Tool messages are text-only, so images returned by tools follow as one user message
+    // after the whole block of tool results (nothing may come between those)
+    let mut tool_images: Vec<serde_json::Value> = Vec::new();
+
+    for msg in messages {
+        if msg.role != "tool" && !tool_images.is_empty() {
+            converted.push(tool_images_message(std::mem::take(&mut tool_images)));
+        }
+
         // Handle tool responses
         if msg.role == "tool" {
-            return OpenAIMessage {
+            converted.push(OpenAIMessage {
                 role: "tool".to_string(),
                 content: serde_json::Value::String(msg.content.clone()),
                 tool_calls: None,
                 tool_call_id: msg.tool_call_id.clone(),
-            };
+            });
+            if supports_vision {
+                tool_images.extend(msg.images.iter().flatten().map(|img| image_part(img)));
+            }
+            continue;
         }
 
         // Handle images/content - only send images if model supports vision
@@ -175,10 +191,7 @@ fn convert_messages(messages: &[ChatMessage], supports_vision: bool) -> Vec<Open
                 if !images.is_empty() {
                     let mut parts = vec![json!({"type": "text", "text": msg.content})];
                     for img in images {
-                        parts.push(json!({
-                            "type": "image_url",
-                            "image_url": { "url": format!("data:image/jpeg;base64,{}", img) }
-                        }));
+                        parts.push(image_part(img));
                     }
                     serde_json::Value::Array(parts)
                 } else {
@@ -192,13 +205,48 @@ fn convert_messages(messages: &[ChatMessage], supports_vision: bool) -> Vec<Open
             serde_json::Value::String(msg.content.clone())
         };
 
-        OpenAIMessage {
+        converted.push(OpenAIMessage {
             role: msg.role.clone(),
             content,
             tool_calls: msg.tool_calls.clone(),
             tool_call_id: None,
-        }
-    }).collect()
+        });
+    }
+
+    if !tool_images.is_empty() {
+        converted.push(tool_images_message(tool_images));
+    }
+    converted
+}
+
+fn image_part(img: &str) -> serde_json::Value {
+    json!({
+        "type": "image_url",
+        "image_url": { "url": format!("data:{};base64,{}", image_mime_type(img), img) }
+    })
+}
+
+fn tool_images_message(images: Vec<serde_json::Value>) -> OpenAIMessage {
+    let mut parts = vec![json!({"type": "text", "text": "Images returned by the tool calls above:"})];
+    parts.extend(images);
+    OpenAIMessage {
+        role: "user".to_string(),
+        content: serde_json::Value::Array(parts),
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+/// Guess vision support from common model naming patterns
+fn model_supports_vision(model: &str) -> bool {
+    let model_lower = model.to_lowercase();
+    model_lower.contains("vision") 
+        || model_lower.contains("gpt-4o")
+        || model_lower.contains("gpt-4-turbo")
+        || model_lower.contains("claude-3")
+        || model_lower.contains("gemini")
+        || model_lower.contains("llava")
+        || model_lower.contains("moondream")
 }
 
 struct OpenAIStream {
@@ -230,8 +278,11 @@ impl OpenAIStream {
             if let Some(error) = error_response.get("error") {
                 if error.get("code").and_then(|c| c.as_str()) == Some("tool_use_failed") {
                     if let Some(failed_gen) = error.get("failed_generation").and_then(|f| f.as_str()) {
-                        if let Some(tool_call) = self.parse_groq_xml_tool_call(failed_gen) {
-                            self.queue.push_back(ProviderEvent::ToolCall(tool_call));
+                        let (calls, _) = tool_recovery::parse(failed_gen, &[]);
+                        if !calls.is_empty() {
+                            for call in calls {
+                                self.queue.push_back(ProviderEvent::ToolCall(call.to_tool_call(true)));
+                            }
                             return;
                         }
                     }
@@ -289,8 +340,9 @@ impl OpenAIStream {
                  }
                  
                  // 3. Finish Reason
-                 if choice.finish_reason.is_some() {
+                 if let Some(reason) = choice.finish_reason {
                      self.flush_tool_calls();
+                     self.queue.push_back(ProviderEvent::Finish(FinishReason::from_provider(&reason)));
                  }
              }
         }
@@ -308,47 +360,6 @@ impl OpenAIStream {
             }
         }
     }
-    
-    /// Parse Groq's XML-style tool call format: <function=name{json_args}></function>
-    fn parse_groq_xml_tool_call(&self, input: &str) -> Option<serde_json::Value> {
-        // Pattern: <function=tool_name{...json...}></function>
-        // Can also be: <function=tool_name{"arg": "value"}></function>
-        
-        let start_marker = "<function=";
-        let end_marker = "</function>";
-        
-        let start = input.find(start_marker)?;
-        let end = input.find(end_marker)?;
-        
-        if end <= start {
-            return None;
-        }
-        
-        let inner = &input[start + start_marker.len()..end];
-        // inner should be like: list_directory{"path": "./"}> or list_directory{"path":"./"}
-        // Remove trailing > if present
-        let inner = inner.trim_end_matches('>');
-        
-        // Find where the function name ends and JSON begins
-        let json_start = inner.find('{')?;
-        let function_name = inner[..json_start].trim(); // Trim whitespace from function name
-        let json_str = &inner[json_start..];
-        
-        // Parse the JSON arguments to validate
-        let _args: serde_json::Value = serde_json::from_str(json_str).ok()?;
-        
-        // Generate a unique ID
-        let call_id = format!("groq_call_{}", uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("0"));
-        
-        Some(json!({
-            "id": call_id,
-            "type": "function",
-            "function": {
-                "name": function_name,
-                "arguments": json_str
-            }
-        }))
-    }
 }
 
 impl Stream for OpenAIStream {
@@ -1,22 +1,349 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 use tauri::{AppHandle, Emitter};
+use futures::stream::BoxStream;
 use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::providers::traits::{LLMProvider, ProviderEvent};
+use crate::providers::traits::{FinishReason, LLMProvider, ProviderEvent};
 use crate::providers::{ChatMessage, ProviderConfig, ChatOptions};
-use crate::mcp::McpClient;
+use crate::providers::{scheduler, schema};
+use crate::mcp::{naming, resources, supervisor};
+use crate::mcp::protocol::{CallToolResult, Content};
+use crate::tools::{self, tool_output, validation, ToolContext};
+use crate::commands::monitoring;
+use crate::commands::db::{get_chat_params, insert_message, insert_draft, update_draft, ChatParams, MessageMeta};
+use crate::db::get_pool;
+
+/// Checkpoint a streaming reply after this many content chunks...
+const DRAFT_CHECKPOINT_CHUNKS: usize = 32;
+/// ...or after this much time, whichever comes first
+const DRAFT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(2);
+/// Image types passed on from tool results; anything else is described in text
+const FORWARDED_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/jpg", "image/gif", "image/webp"];
+/// How long a tool call waits for an MCP server that is restarting
+const MCP_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where a tool offered to the model is dispatched
+#[derive(Debug, Clone)]
+enum ToolSource {
+    Native,
+    /// `tool` is the server's own (unqualified) name for it
+    Mcp { server: String, tool: String },
+}
+
+/// A tool offered to the model, keyed by the name the model calls it by
+#[derive(Debug, Clone)]
+struct ToolEntry {
+    source: ToolSource,
+    display_name: String,
+    /// Arguments schema, used to validate calls before dispatch
+    schema: Value,
+}
+
+/// Live state of a background chat run, shared between the orchestrator task and the
+/// `chat_*` commands that list, inspect, cancel or reattach to it.
+pub struct ChatRun {
+    pub chat_id: Option<String>,
+    pub model: String,
+    /// Scheduler priority; higher runs first when the provider is busy
+    pub priority: i32,
+    /// Unix epoch millis
+    pub started_at: i64,
+    started: Instant,
+    cancel: AtomicBool,
+    /// Woken by `cancel`, for tasks that wait on `cancelled`
+    cancel_notify: Notify,
+    /// Approximate token count (one per streamed chunk)
+    tokens: AtomicU64,
+    /// Assistant content streamed so far, across all tool-call turns
+    buffer: Mutex<String>,
+    /// Run that delegated this one; cancelling it cancels this run too
+    parent: Option<Arc<ChatRun>>,
+}
+
+impl ChatRun {
+    pub fn new(chat_id: Option<String>, model: String, priority: i32) -> Self {
+        Self {
+            chat_id,
+            model,
+            priority,
+            started_at: chrono::Utc::now().timestamp_millis(),
+            started: Instant::now(),
+            cancel: AtomicBool::new(false),
+            cancel_notify: Notify::new(),
+            tokens: AtomicU64::new(0),
+            buffer: Mutex::new(String::new()),
+            parent: None,
+        }
+    }
+
+    /// Run of a sub-conversation started by the `delegate` tool
+    pub fn child(parent: Arc<ChatRun>, chat_id: Option<String>, model: String) -> Self {
+        let priority = parent.priority;
+        Self { parent: Some(parent), ..Self::new(chat_id, model, priority) }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel_notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed) || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+
+    /// Resolves once this run or one of the runs that delegated it is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            let mut waits = Vec::new();
+            let mut run = Some(self);
+            while let Some(r) = run {
+                waits.push(Box::pin(r.cancel_notify.notified()));
+                run = r.parent.as_deref();
+            }
+            // Register before checking, so a cancel in between is not missed
+            for wait in waits.iter_mut() {
+                wait.as_mut().enable();
+            }
+            if self.is_cancelled() {
+                return;
+            }
+            futures::future::select_all(waits).await;
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    pub fn tokens(&self) -> u64 {
+        self.tokens.load(Ordering::Relaxed)
+    }
+
+    /// Content buffered so far with the sequence number of the last chunk it includes.
+    /// Chunk events carry the same `seq`, so a reattaching UI can skip what it already has.
+    pub fn snapshot(&self) -> (String, u64) {
+        let buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        (buffer.clone(), self.tokens())
+    }
+
+    /// Append a chunk and return its sequence number
+    fn push_content(&self, chunk: &str) -> u64 {
+        let mut buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        buffer.push_str(chunk);
+        self.tokens.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Budgets that stop the agent loop. Set per chat in `ChatParams::limits`, or per request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoopLimits {
+    /// Model generations per run, counting each round of tool calls
+    pub max_iterations: u32,
+    pub max_tool_calls: Option<u32>,
+    /// Wall-clock budget for the whole run
+    pub max_duration_secs: Option<u64>,
+    /// Tokens across all generations, as reported by the provider (estimated when it does not)
+    pub max_tokens: Option<u64>,
+    /// When a limit stops the loop, give the model one last turn without tools to summarize its work
+    pub summarize_on_limit: bool,
+}
+
+impl Default for LoopLimits {
+    fn default() -> Self {
+        Self {
+            max_iterations: 10,
+            max_tool_calls: None,
+            max_duration_secs: None,
+            max_tokens: None,
+            summarize_on_limit: true,
+        }
+    }
+}
+
+/// Progress of a run against its `LoopLimits`
+#[derive(Default)]
+struct LoopUsage {
+    iterations: u32,
+    tool_calls: u32,
+    tokens: u64,
+}
+
+/// Which limit stopped the loop, with the configured maximum and the amount used
+struct LimitHit {
+    limit: &'static str,
+    max: u64,
+    used: u64,
+}
+
+impl LoopLimits {
+    fn exceeded(&self, usage: &LoopUsage, elapsed: Duration) -> Option<LimitHit> {
+        if usage.iterations >= self.max_iterations {
+            return Some(LimitHit { limit: "max_iterations", max: self.max_iterations as u64, used: usage.iterations as u64 });
+        }
+        if let Some(max) = self.max_tool_calls {
+            if usage.tool_calls >= max {
+                return Some(LimitHit { limit: "max_tool_calls", max: max as u64, used: usage.tool_calls as u64 });
+            }
+        }
+        if let Some(max) = self.max_duration_secs {
+            if elapsed.as_secs() >= max {
+                return Some(LimitHit { limit: "max_duration_secs", max, used: elapsed.as_secs() });
+            }
+        }
+        if let Some(max) = self.max_tokens {
+            if usage.tokens >= max {
+                return Some(LimitHit { limit: "max_tokens", max, used: usage.tokens });
+            }
+        }
+        None
+    }
+}
+
+/// Continuations requested for one reply that keeps hitting the output token limit
+const MAX_CONTINUATIONS: u32 = 3;
+/// Sent after a reply cut off by the token limit when auto-continue is on
+const CONTINUE_PROMPT: &str = "Your previous reply was cut off by the output length limit. \
+    Continue exactly where it stopped, without repeating anything or adding a preamble.";
+
+/// Draft row of an assistant reply that is still streaming.
+/// `id` is `None` when the conversation is not persisted.
+struct Draft {
+    id: Option<String>,
+    chunks_since_save: usize,
+    last_save: Instant,
+}
 
 pub struct ChatOrchestrator {
     app: AppHandle,
     provider: Box<dyn LLMProvider + Send + Sync>,
+    /// When set, every message produced by the conversation is saved to this chat
+    chat_id: Option<String>,
+    /// Overrides the chat's own loop limits
+    limits: Option<LoopLimits>,
+    /// Set for delegated sub-conversations, which cannot delegate further
+    delegated: bool,
+    /// Tools offered to a delegated conversation, built-in ones included
+    allowed_tools: Vec<String>,
+    /// Interrupted draft to continue instead of starting a new reply: its row id and content
+    resume: Option<(String, String)>,
 }
 
 impl ChatOrchestrator {
-    pub fn new(app: AppHandle, provider: Box<dyn LLMProvider + Send + Sync>) -> Self {
-        Self { app, provider }
+    pub fn new(app: AppHandle, provider: Box<dyn LLMProvider + Send + Sync>, chat_id: Option<String>) -> Self {
+        Self { app, provider, chat_id, limits: None, delegated: false, allowed_tools: Vec::new(), resume: None }
+    }
+
+    /// Configure this orchestrator for a sub-conversation run by the `delegate` tool
+    pub fn delegated(mut self, allowed_tools: Vec<String>) -> Self {
+        self.delegated = true;
+        self.allowed_tools = allowed_tools;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Option<LoopLimits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Continue the interrupted reply stored in `draft_id` from `partial`, its saved content,
+    /// instead of generating a new one
+    pub fn resume_draft(mut self, draft_id: String, partial: String) -> Self {
+        self.resume = Some((draft_id, partial));
+        self
+    }
+
+    /// Save a message to the chat transcript. Returns the stored row id.
+    /// Persistence failures are logged but never abort the conversation.
+    async fn persist(&self, message: &ChatMessage) -> Option<String> {
+        let chat_id = self.chat_id.as_ref()?;
+        let meta_json = MessageMeta::from_message(message).to_json();
+        let result = match get_pool().await {
+            Ok(pool) => insert_message(&pool, chat_id, &message.role, &message.content, meta_json).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(row) => Some(row.id),
+            Err(e) => {
+                eprintln!("Failed to persist {} message for chat {}: {}", message.role, chat_id, e);
+                None
+            }
+        }
+    }
+
+    /// A draft with no row yet; the row is created with the first content
+    fn start_draft(&self) -> Draft {
+        Draft { id: None, chunks_since_save: 0, last_save: Instant::now() }
+    }
+
+    /// Create the `streaming` row of a draft. Returns false when the conversation is not persisted.
+    async fn create_draft_row(&self, draft: &mut Draft) -> bool {
+        let Some(chat_id) = &self.chat_id else { return false };
+        let result = match get_pool().await {
+            Ok(pool) => insert_draft(&pool, chat_id).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(id) => {
+                draft.id = Some(id);
+                true
+            }
+            Err(e) => {
+                eprintln!("Failed to create draft for chat {}: {}", chat_id, e);
+                false
+            }
+        }
+    }
+
+    /// Save the partial reply if enough chunks or time have passed since the last checkpoint
+    async fn checkpoint_draft(&self, draft: &mut Draft, content: &str) {
+        if draft.id.is_none() {
+            // First content: save it right away
+            if !self.create_draft_row(draft).await {
+                return;
+            }
+            draft.chunks_since_save = DRAFT_CHECKPOINT_CHUNKS;
+        }
+        let Some(id) = &draft.id else { return };
+        draft.chunks_since_save += 1;
+        if draft.chunks_since_save < DRAFT_CHECKPOINT_CHUNKS && draft.last_save.elapsed() < DRAFT_CHECKPOINT_INTERVAL {
+            return;
+        }
+        draft.chunks_since_save = 0;
+        draft.last_save = Instant::now();
+        if let Ok(pool) = get_pool().await {
+            if let Err(e) = update_draft(&pool, id, content, None, "streaming").await {
+                eprintln!("Failed to checkpoint draft {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Write the final content of a draft with its terminal status (complete, cancelled or failed).
+    /// A draft that never got content only gets a row if the message carries tool calls.
+    async fn finish_draft(&self, mut draft: Draft, message: &ChatMessage, status: &str) -> Option<String> {
+        if draft.id.is_none() {
+            let has_output = !message.content.is_empty() || message.tool_calls.as_ref().is_some_and(|c| !c.is_empty());
+            if !has_output || !self.create_draft_row(&mut draft).await {
+                return None;
+            }
+        }
+        let id = draft.id?;
+        let meta_json = MessageMeta::from_message(message).to_json();
+        let result = match get_pool().await {
+            Ok(pool) => update_draft(&pool, &id, &message.content, meta_json, status).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to finalize draft {}: {}", id, e);
+        }
+        Some(id)
     }
 
     pub async fn run_conversation(
@@ -26,216 +353,618 @@ impl ChatOrchestrator {
         initial_messages: Vec<ChatMessage>,
         options: Option<ChatOptions>,
         stream_id: &str,
-        should_cancel: Arc<AtomicBool>,
-    ) -> anyhow::Result<()> {
+        run: Arc<ChatRun>,
+    ) -> anyhow::Result<Option<String>> {
         let mut messages = initial_messages;
         
-        // 1. Gather tools from active MCP clients
-        let (tools, tool_mapping) = self.gather_tools().await;
+        // 1. Gather built-in tools and tools from active MCP clients
+        let params = self.load_params().await;
+        let max_result_tokens = params.max_tool_result_tokens.unwrap_or(tool_output::DEFAULT_MAX_RESULT_TOKENS).max(1);
+        if let Some(chat_id) = &self.chat_id {
+            match resources::context(chat_id, max_result_tokens).await {
+                Ok(Some(context)) => add_to_system_prompt(&mut messages, &context),
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to load attached resources for {}: {}", chat_id, e),
+            }
+        }
+        let (tools, tool_mapping) = self.gather_tools(&params, stream_id).await;
+        // Image outputs from tools are only forwarded to models that can view them
+        let vision = self.provider.supports_images(config, model).await;
+        let mut tool_ctx = ToolContext {
+            app: self.app.clone(),
+            chat_id: self.chat_id.clone(),
+            message_id: None,
+            max_result_tokens,
+            config: config.clone(),
+            run: run.clone(),
+            stream_id: stream_id.to_string(),
+            tool_names: tool_mapping.keys().cloned().collect(),
+        };
         
-        let mut loop_count = 0;
-        const MAX_LOOPS: i32 = 10;
+        let limits = self.limits.clone().or_else(|| params.limits.clone()).unwrap_or_default();
+        let mut usage = LoopUsage::default();
+        let started = Instant::now();
+        // Generation and tool calls are cut off here; the summary turn after a limit is not
+        let deadline = limits.max_duration_secs.map(|secs| started + Duration::from_secs(secs));
+        // Set once a limit is hit: the remaining generation runs without tools
+        let mut limit_hit: Option<LimitHit> = None;
+        let mut final_reply = None;
+        let mut resume = self.resume.clone();
         
         // Emit stream start event
         let _ = self.app.emit("chat:stream-start", serde_json::json!({"stream_id": stream_id}));
 
         loop {
-            if loop_count >= MAX_LOOPS {
-                println!("Max loops reached for conversation.");
-                break;
+            if limit_hit.is_none() {
+                if let Some(hit) = limits.exceeded(&usage, started.elapsed()) {
+                    self.emit_limit_reached(stream_id, &hit, limits.summarize_on_limit);
+                    if !limits.summarize_on_limit {
+                        let _ = self.app.emit("chat:chunk", serde_json::json!({
+                            "stream_id": stream_id,
+                            "message": { "role": "assistant", "content": "" },
+                            "done": true
+                        }));
+                        let _ = self.app.emit("chat:complete", serde_json::json!({"stream_id": stream_id, "completed": false, "limit": hit.limit}));
+                        break;
+                    }
+                    // Not persisted: the transcript only keeps the summary itself
+                    messages.push(ChatMessage {
+                        role: "user".to_string(),
+                        content: format!(
+                            "You have reached the {} limit for this task ({} of {}). Do not call any more tools. \
+                             Summarize what you did, what you found, and what is left to do.",
+                            hit.limit.replace('_', " "), hit.used, hit.max
+                        ),
+                        images: None,
+                        tool_calls: None,
+                        tool_call_id: None,
+                    });
+                    limit_hit = Some(hit);
+                }
             }
-            loop_count += 1;
+            usage.iterations += 1;
             
-            if should_cancel.load(Ordering::Relaxed) {
+            if run.is_cancelled() {
                  let _ = self.app.emit("chat:cancelled", serde_json::json!({"stream_id": stream_id}));
-                 return Ok(());
+                 return Ok(None);
             }
 
-            // Start stream from provider
-            let mut stream = self.provider.stream_chat(config, model, &messages, tools.clone(), options.clone()).await?;
-            
+            let turn_tools = if limit_hit.is_some() { None } else { tools.clone() };
             let mut full_content = String::new();
             let mut tool_calls = Vec::new();
-            
-            while let Some(event) = stream.next().await {
-                 if should_cancel.load(Ordering::Relaxed) {
-                     break; 
-                 }
-                 
-                 match event {
-                     ProviderEvent::Content(s) => {
-                         full_content.push_str(&s);
-                         // Emit chunk to frontend
-                         let _ = self.app.emit("chat:chunk", serde_json::json!({
-                             "stream_id": stream_id,
-                             "message": { "role": "assistant", "content": s },
-                             "done": false
-                         }));
-                     },
-                     ProviderEvent::ToolCall(tc) => {
-                         tool_calls.push(tc);
-                     },
-                     ProviderEvent::Error(e) => {
-                          let _ = self.app.emit("chat:error", serde_json::json!({"stream_id": stream_id, "error": e}));
-                          return Err(anyhow::anyhow!(e));
-                     },
-                     ProviderEvent::Usage(_) => {
-                         // Usage stats can be handled here if needed
+            let mut finish_reason: Option<FinishReason>;
+            let mut continuations = 0;
+            let mut timed_out = false;
+            let mut draft = self.start_draft();
+            if let Some((draft_id, partial)) = resume.take() {
+                // Reattaching UIs get the saved part from the run's snapshot
+                run.push_content(&partial);
+                draft.id = Some(draft_id);
+                draft.chunks_since_save = DRAFT_CHECKPOINT_CHUNKS;
+                full_content = partial;
+            }
+
+            // One generation, plus continuations when auto-continue picks up a reply cut off by the token limit
+            loop {
+                // Wait for a generation slot on this provider
+                let Some(permit) = scheduler::acquire(&self.app, config, stream_id, run.priority, run.cancelled()).await else {
+                    self.finish_draft(draft, &assistant_message(full_content, None), "cancelled").await;
+                    let _ = self.app.emit("chat:cancelled", serde_json::json!({"stream_id": stream_id}));
+                    return Ok(None);
+                };
+
+                // A continuation (or resumed draft) replays the partial reply and asks the model
+                // to carry on from there
+                let continuation_messages;
+                let request: &[ChatMessage] = if full_content.is_empty() {
+                    &messages
+                } else {
+                    continuation_messages = continuation_request(&messages, &full_content);
+                    &continuation_messages
+                };
+
+                // Start stream from provider
+                let mut stream = match self.provider.stream_chat(config, model, request, turn_tools.clone(), options.clone()).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        self.finish_draft(draft, &assistant_message(full_content, None), "failed").await;
+                        return Err(e);
+                    }
+                };
+                
+                let segment_start = full_content.len();
+                let mut reported_tokens = None;
+                finish_reason = None;
+                let deadline = if limit_hit.is_none() { deadline } else { None };
+                
+                while let Some(event) = next_event(&mut stream, deadline, &mut timed_out).await {
+                     if run.is_cancelled() {
+                         break; 
                      }
-                 }
+                     
+                     match event {
+                         ProviderEvent::Content(s) => {
+                             full_content.push_str(&s);
+                             self.checkpoint_draft(&mut draft, &full_content).await;
+                             let seq = run.push_content(&s);
+                             // Emit chunk to frontend
+                             let _ = self.app.emit("chat:chunk", serde_json::json!({
+                                 "stream_id": stream_id,
+                                 "seq": seq,
+                                 "message": { "role": "assistant", "content": s },
+                                 "done": false
+                             }));
+                         },
+                         ProviderEvent::ToolCall(tc) => {
+                             tool_calls.push(tc);
+                         },
+                         ProviderEvent::Finish(reason) => {
+                             finish_reason = Some(reason);
+                         },
+                         ProviderEvent::Error(e) => {
+                              self.finish_draft(draft, &assistant_message(full_content, None), "failed").await;
+                              return Err(anyhow::anyhow!(e));
+                         },
+                         ProviderEvent::Usage(u) => {
+                             if let Some(total) = u.total_tokens.or(u.completion_tokens) {
+                                 reported_tokens = Some(total.max(0) as u64);
+                             }
+                         }
+                     }
+                }
+                // Generation is over; free the slot before running tools
+                drop(stream);
+                drop(permit);
+                usage.tokens += reported_tokens.unwrap_or_else(|| tool_output::estimate_tokens(&full_content[segment_start..]) as u64);
+
+                let truncated = finish_reason == Some(FinishReason::Length) && tool_calls.is_empty();
+                if truncated && params.auto_continue.unwrap_or(false) && continuations < MAX_CONTINUATIONS && !run.is_cancelled() {
+                    continuations += 1;
+                    println!("Reply hit the token limit; continuing ({}/{})", continuations, MAX_CONTINUATIONS);
+                    let _ = self.app.emit("chat:continuing", serde_json::json!({
+                        "stream_id": stream_id,
+                        "continuation": continuations
+                    }));
+                    continue;
+                }
+                break;
             }
             
-            if should_cancel.load(Ordering::Relaxed) {
+            if run.is_cancelled() {
+                 self.finish_draft(draft, &assistant_message(full_content, None), "cancelled").await;
                  let _ = self.app.emit("chat:cancelled", serde_json::json!({"stream_id": stream_id}));
-                 return Ok(());
+                 return Ok(None);
+            }
+
+            // Out of time mid-generation: the partial reply is the answer, without a summary turn
+            if timed_out {
+                let elapsed = started.elapsed().as_secs();
+                let hit = LimitHit { limit: "max_duration_secs", max: limits.max_duration_secs.unwrap_or(elapsed), used: elapsed };
+                self.emit_limit_reached(stream_id, &hit, false);
+                tool_calls.clear();
+                limit_hit = Some(hit);
             }
 
-            // If no tool calls, we are done
-            if tool_calls.is_empty() {
+            // If no tool calls, we are done; after a limit, stray tool calls are ignored
+            if tool_calls.is_empty() || limit_hit.is_some() {
+                final_reply = Some(full_content.clone());
+                let message_id = self.finish_draft(draft, &assistant_message(full_content, None), "complete").await;
+
                 // Emit final chunk with done=true
                 let _ = self.app.emit("chat:chunk", serde_json::json!({
                      "stream_id": stream_id,
                      "message": { "role": "assistant", "content": "" },
                      "done": true
                  }));
-                 let _ = self.app.emit("chat:complete", serde_json::json!({"stream_id": stream_id, "completed": true}));
+                 let _ = self.app.emit("chat:complete", serde_json::json!({
+                     "stream_id": stream_id,
+                     "completed": limit_hit.is_none(),
+                     "limit": limit_hit.as_ref().map(|h| h.limit),
+                     "finish_reason": finish_reason,
+                     "continuations": continuations,
+                     "message_id": message_id
+                 }));
                 break;
             }
             
             // Handle tool calls - This is the "Loop" part
             
             // 1. Append assistant message with content and tool_calls
-            messages.push(ChatMessage {
-                role: "assistant".to_string(),
-                content: full_content,
-                images: None,
-                tool_calls: Some(tool_calls.clone()),
-                tool_call_id: None, 
-            });
+            let tool_call_message = assistant_message(full_content, Some(tool_calls.clone()));
+            tool_ctx.message_id = self.finish_draft(draft, &tool_call_message, "complete").await;
+            messages.push(tool_call_message);
             
             // 2. Execute tools
             for call in tool_calls {
                  if let Some(function) = call.get("function") {
                      let name = function.get("name").and_then(|n| n.as_str()).unwrap_or_default();
-                     let args_str = function.get("arguments").and_then(|v| v.as_str()).unwrap_or("{}");
                      let call_id = call.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
-                     
-                     let args = serde_json::from_str(args_str).unwrap_or(serde_json::json!({}));
+                     let entry = tool_mapping.get(name);
+
+                     // Calls past the limit are answered without running them; the loop then
+                     // stops at the limit check above
+                     if let Some(max) = limits.max_tool_calls.filter(|max| usage.tool_calls >= *max) {
+                         let tool_message = ChatMessage {
+                             role: "tool".to_string(),
+                             content: format!("Not executed: the limit of {} tool calls for this task was reached.", max),
+                             images: None,
+                             tool_calls: None,
+                             tool_call_id: Some(call_id),
+                         };
+                         self.persist(&tool_message).await;
+                         messages.push(tool_message);
+                         continue;
+                     }
+                     monitoring::record_tool_call();
+                     usage.tool_calls += 1;
+
+                     // Check the arguments before dispatch; on failure the model gets the
+                     // problems as the tool result so it can correct the call
+                     let args = match validation::parse_arguments(function.get("arguments")) {
+                         Ok(args) => {
+                             let problems = entry.map(|e| validation::validate(&e.schema, &args)).unwrap_or_default();
+                             if problems.is_empty() { Ok(args) } else { Err(problems.join("\n- ")) }
+                         }
+                         Err(e) => Err(e),
+                     };
 
                      // Notify frontend of tool execution
                      let _ = self.app.emit("chat:tool-start", serde_json::json!({
                          "stream_id": stream_id,
                          "tool": name,
-                         "args": args
+                         "display_name": entry.map(|e| e.display_name.as_str()).unwrap_or(name),
+                         "args": args.as_ref().ok()
                      }));
                      
-                     if let Some(client_name) = tool_mapping.get(name) {
-                         if let Some(mcp_client) = McpClient::get_client(client_name) {
-                             println!("Executing tool {} on client {}", name, client_name);
-                             
-                             let result_content = match mcp_client.call_tool(name, args).await {
-                                 Ok(res) => {
-                                     let mut text = String::new();
-                                     for item in res.content {
-                                         match item {
-                                             crate::mcp::protocol::Content::Text { text: t } => {
-                                                 text.push_str(&t);
-                                                 text.push('\n');
-                                             },
-                                             crate::mcp::protocol::Content::Resource { text: Some(t), .. } => {
-                                                 text.push_str(&t);
+                     let result = match args {
+                         Ok(args) => match deadline {
+                             Some(deadline) => {
+                                 let remaining = deadline.saturating_duration_since(Instant::now());
+                                 tokio::time::timeout(remaining, self.call_tool(name, args, &tool_mapping, &tool_ctx)).await
+                                     .unwrap_or_else(|_| Err(anyhow::anyhow!("stopped because the time limit for this task was reached")))
+                             }
+                             None => self.call_tool(name, args, &tool_mapping, &tool_ctx).await,
+                         },
+                         Err(problems) => {
+                             monitoring::record_tool_validation_failure(name);
+                             Err(anyhow::anyhow!(
+                                 "invalid arguments for tool {}:\n- {}\nFix the arguments to match the tool's schema and call it again.",
+                                 name, problems
+                             ))
+                         }
+                     };
+
+                     let mut result_images = Vec::new();
+                     let result_content = match result {
+                         Ok(res) => {
+                             let mut text = String::new();
+                             for item in res.content {
+                                 match item {
+                                     Content::Text { text: t } => {
+                                         text.push_str(&t);
+                                         text.push('\n');
+                                     },
+                                     Content::Image { data, mime_type } => {
+                                         match image_placeholder(vision, &mime_type, &data) {
+                                             None => result_images.push(data),
+                                             Some(placeholder) => {
+                                                 text.push_str(&placeholder);
                                                  text.push('\n');
-                                             },
-                                             _ => {}
+                                             }
+                                         }
+                                     },
+                                     Content::Resource { resource } => {
+                                         if let Some(t) = resource.text {
+                                             text.push_str(&t);
+                                         } else if let Some(blob) = resource.blob {
+                                             let mime = resource.mime_type.as_deref().unwrap_or("application/octet-stream");
+                                             if mime.starts_with("image/") && image_placeholder(vision, mime, &blob).is_none() {
+                                                 result_images.push(blob);
+                                                 continue;
+                                             }
+                                             text.push_str(&format!("[Resource: {} ({}, ~{} KB)]", resource.uri, mime, blob.len() * 3 / 4 / 1024));
+                                         } else {
+                                             text.push_str(&format!("[Resource: {}]", resource.uri));
                                          }
-                                     }
-                                     
-                                     // Truncate large results to prevent context overflow
-                                     const MAX_RESULT_CHARS: usize = 8000;
-                                     if text.len() > MAX_RESULT_CHARS {
-                                         let truncated = &text[..MAX_RESULT_CHARS];
-                                         // Find last newline for cleaner cut
-                                         let cut_point = truncated.rfind('\n').unwrap_or(MAX_RESULT_CHARS);
-                                         format!(
-                                             "{}\n\n[... Output truncated. Showing {}/{} characters. Consider using more specific queries or filters to reduce output size.]",
-                                             &text[..cut_point],
-                                             cut_point,
-                                             text.len()
-                                         )
-                                     } else {
-                                         text
-                                     }
-                                 },
-                                 Err(e) => format!("Error executing tool: {}", e),
-                             };
+                                         text.push('\n');
+                                     },
+                                     Content::Unsupported => {}
+                                 }
+                             }
                              
-                             // Append tool result
-                             messages.push(ChatMessage {
-                                 role: "tool".to_string(),
-                                 content: result_content,
-                                 images: None,
-                                 tool_calls: None,
-                                 tool_call_id: Some(call_id),
-                             });
-                         } else {
-                             eprintln!("McpClient {} not found for tool {}", client_name, name);
-                             messages.push(ChatMessage {
-                                 role: "tool".to_string(),
-                                 content: format!("Error: Client {} not found", client_name),
-                                 images: None,
-                                 tool_calls: None,
-                                 tool_call_id: Some(call_id),
-                             });
+                             // Pages of read_tool_output are already sized to the budget
+                             if name == "read_tool_output" {
+                                 text
+                             } else {
+                                 self.truncate_result(name, text, &tool_ctx, tool_mapping.contains_key("read_tool_output")).await
+                             }
+                         },
+                         Err(e) => {
+                             eprintln!("Tool {} failed: {}", name, e);
+                             format!("Error executing tool: {}", e)
                          }
-                     } else {
-                         eprintln!("No client mapping found for tool {}", name);
-                         messages.push(ChatMessage {
-                             role: "tool".to_string(),
-                             content: format!("Error: No client found for tool {}", name),
-                             images: None,
-                             tool_calls: None,
-                             tool_call_id: Some(call_id),
-                         });
-                     }
+                     };
+
+                     // Append tool result
+                     let tool_message = ChatMessage {
+                         role: "tool".to_string(),
+                         content: result_content,
+                         images: if result_images.is_empty() { None } else { Some(result_images) },
+                         tool_calls: None,
+                         tool_call_id: Some(call_id),
+                     };
+                     self.persist(&tool_message).await;
+                     messages.push(tool_message);
                  }
             }
             
             // Loop continues to feed tool results back to provider
         }
         
-        Ok(())
+        Ok(final_reply)
+    }
+    
+    fn emit_limit_reached(&self, stream_id: &str, hit: &LimitHit, summarizing: bool) {
+        println!("Conversation stopped by {} ({} of {})", hit.limit, hit.used, hit.max);
+        let _ = self.app.emit("chat:limit-reached", serde_json::json!({
+            "stream_id": stream_id,
+            "limit": hit.limit,
+            "max": hit.max,
+            "used": hit.used,
+            "summarizing": summarizing
+        }));
+    }
+
+    /// Dispatch a tool call to the built-in tool or MCP server that provides it
+    async fn call_tool(&self, name: &str, args: Value, tool_mapping: &HashMap<String, ToolEntry>, ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        match tool_mapping.get(name).map(|e| &e.source) {
+            Some(ToolSource::Native) => {
+                let tool = tools::get(name).ok_or_else(|| anyhow::anyhow!("Built-in tool {} not found", name))?;
+                tool.call(args, ctx).await
+            }
+            Some(ToolSource::Mcp { server, tool, .. }) => {
+                // A server being restarted gets a moment to come back
+                let mcp_client = supervisor::wait_ready(server, MCP_READY_TIMEOUT).await
+                    .ok_or_else(|| anyhow::anyhow!("Client {} not found", server))?;
+                println!("Executing tool {} on client {}", tool, server);
+                mcp_client.call_tool(tool, args).await
+            }
+            None => Err(anyhow::anyhow!("No client found for tool {}", name)),
+        }
+    }
+
+    /// Cut a tool result down to the chat's token budget. The full text is always kept in the
+    /// artifact store; when `can_page`, the model can page through it with `read_tool_output`.
+    async fn truncate_result(&self, name: &str, text: String, ctx: &ToolContext, can_page: bool) -> String {
+        if tool_output::estimate_tokens(&text) <= ctx.max_result_tokens {
+            return text;
+        }
+        let cut = tool_output::truncation_point(&text, ctx.max_result_tokens);
+        let shown = text[..cut].chars().count();
+        let total = text.chars().count();
+
+        let stored = tool_output::save(self.chat_id.as_deref(), name, &text).await
+            .map_err(|e| eprintln!("{}", e))
+            .ok();
+        let note = truncation_note(shown, total, stored.as_deref(), can_page);
+        format!("{}\n\n{}", &text[..cut], note)
+    }
+
+    /// Per-chat settings, or the defaults when the conversation is not persisted
+    async fn load_params(&self) -> ChatParams {
+        match &self.chat_id {
+            Some(chat_id) => get_chat_params(chat_id).await.unwrap_or_else(|e| {
+                eprintln!("Failed to load params for chat {}: {}", chat_id, e);
+                ChatParams::default()
+            }),
+            None => ChatParams::default(),
+        }
     }
     
-    async fn gather_tools(&self) -> (Option<Vec<Value>>, HashMap<String, String>) {
+    async fn gather_tools(&self, params: &ChatParams, stream_id: &str) -> (Option<Vec<Value>>, HashMap<String, ToolEntry>) {
+        let dialect = self.provider.schema_dialect();
         let mut available_tools = Vec::new();
         let mut tool_mapping = HashMap::new();
+
+        // A delegated conversation has no chat settings of its own; it gets what it was allowed
+        let native_tools = if self.delegated { Some(self.allowed_tools.as_slice()) } else { params.native_tools.as_deref() };
+        for tool in tools::enabled(native_tools) {
+            add_native_tool(tool.as_ref(), dialect, &mut available_tools, &mut tool_mapping);
+        }
         
-        let active_clients = McpClient::list_active_clients();
-        for client_name in &active_clients {
-            if let Some(mcp_client) = McpClient::get_client(client_name) {
-                if let Ok(tools) = mcp_client.list_tools().await {
-                    for tool in tools {
-                        let mut schema = tool.input_schema.clone();
-                        if let serde_json::Value::Object(ref mut map) = schema {
-                            map.remove("$schema");
-                        }
-                        
-                        available_tools.push(serde_json::json!({
-                            "type": "function",
-                            "function": {
-                                "name": tool.name,
-                                "description": tool.description,
-                                "parameters": schema
-                            }
-                        }));
-                        
-                        tool_mapping.insert(tool.name.clone(), client_name.clone());
-                    }
-                }
+        let reserved: Vec<&str> = tool_mapping.keys().map(|k: &String| k.as_str()).collect();
+        for named in naming::list_named_tools(&reserved).await {
+            // Rewrite the schema for this provider and surface anything that got lost
+            let normalized = schema::normalize(&named.tool.input_schema, dialect);
+            if !normalized.warnings.is_empty() {
+                eprintln!("Tool schema of {} adjusted for provider: {}", named.display_name, normalized.warnings.join("; "));
+                let _ = self.app.emit("chat:tool-schema-warning", serde_json::json!({
+                    "stream_id": stream_id,
+                    "tool": named.qualified_name,
+                    "display_name": named.display_name,
+                    "warnings": normalized.warnings,
+                }));
             }
+
+            available_tools.push(serde_json::json!({
+                "type": "function",
+                "function": {
+                    "name": named.qualified_name,
+                    "description": named.tool.description,
+                    "parameters": normalized.schema
+                }
+            }));
+
+            tool_mapping.insert(named.qualified_name, ToolEntry {
+                source: ToolSource::Mcp { server: named.server, tool: named.tool.name },
+                display_name: named.display_name,
+                schema: named.tool.input_schema,
+            });
+        }
+        
+        if self.delegated {
+            let allowed = |name: &str| name != "delegate" && self.allowed_tools.iter().any(|n| n == name);
+            available_tools.retain(|t| allowed(t["function"]["name"].as_str().unwrap_or_default()));
+            tool_mapping.retain(|name, _| allowed(name));
         }
+        add_paging_tool(dialect, &mut available_tools, &mut tool_mapping);
         
         let tools = if available_tools.is_empty() { None } else { Some(available_tools) };
         (tools, tool_mapping)
     }
 }
+
+/// The next event of a generation, or `None` once it ends or `deadline` passes (which sets `timed_out`)
+async fn next_event(stream: &mut BoxStream<'static, ProviderEvent>, deadline: Option<Instant>, timed_out: &mut bool) -> Option<ProviderEvent> {
+    let Some(deadline) = deadline else { return stream.next().await };
+    match tokio::time::timeout(deadline.saturating_duration_since(Instant::now()), stream.next()).await {
+        Ok(event) => event,
+        Err(_) => {
+            *timed_out = true;
+            None
+        }
+    }
+}
+
+/// The conversation so far plus the cut-off reply and a request to continue it
+fn continuation_request(messages: &[ChatMessage], partial: &str) -> Vec<ChatMessage> {
+    let mut request = messages.to_vec();
+    request.push(assistant_message(partial.to_string(), None));
+    request.push(ChatMessage {
+        role: "user".to_string(),
+        content: CONTINUE_PROMPT.to_string(),
+        images: None,
+        tool_calls: None,
+        tool_call_id: None,
+    });
+    request
+}
+
+/// Append to the leading system message, or start one. Some providers keep only one system
+/// message, so context is never added as a second one.
+fn add_to_system_prompt(messages: &mut Vec<ChatMessage>, text: &str) {
+    match messages.first_mut() {
+        Some(first) if first.role == "system" => {
+            first.content.push_str("\n\n");
+            first.content.push_str(text);
+        }
+        _ => messages.insert(0, ChatMessage {
+            role: "system".to_string(),
+            content: text.to_string(),
+            images: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }),
+    }
+}
+
+fn assistant_message(content: String, tool_calls: Option<Vec<Value>>) -> ChatMessage {
+    ChatMessage {
+        role: "assistant".to_string(),
+        content,
+        images: None,
+        tool_calls,
+        tool_call_id: None,
+    }
+}
+
+/// Offer a built-in tool to the model
+fn add_native_tool(tool: &dyn tools::NativeTool, dialect: schema::SchemaDialect, available_tools: &mut Vec<Value>, tool_mapping: &mut HashMap<String, ToolEntry>) {
+    available_tools.push(serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": tool.name(),
+            "description": tool.description(),
+            "parameters": schema::normalize(&tool.input_schema(), dialect).schema
+        }
+    }));
+    tool_mapping.insert(tool.name().to_string(), ToolEntry {
+        source: ToolSource::Native,
+        display_name: tool.name().to_string(),
+        schema: tool.input_schema(),
+    });
+}
+
+/// Any tool result can be cut to the chat's budget, so a turn with tools always gets
+/// `read_tool_output` to page through the rest, whatever built-in tools the chat enabled
+fn add_paging_tool(dialect: schema::SchemaDialect, available_tools: &mut Vec<Value>, tool_mapping: &mut HashMap<String, ToolEntry>) {
+    if tool_mapping.is_empty() || tool_mapping.contains_key("read_tool_output") {
+        return;
+    }
+    if let Some(tool) = tools::get("read_tool_output") {
+        add_native_tool(tool.as_ref(), dialect, available_tools, tool_mapping);
+    }
+}
+
+/// What the model is told after a result was cut at `shown` of `total` characters
+fn truncation_note(shown: usize, total: usize, stored: Option<&str>, can_page: bool) -> String {
+    match stored {
+        Some(id) if can_page => format!(
+            "[... Output truncated. Showing characters 0-{} of {}. The full output is stored as id \"{}\"; call read_tool_output with that id and offset={} to read more.]",
+            shown, total, id, shown
+        ),
+        _ => format!(
+            "[... Output truncated. Showing {}/{} characters. Consider using more specific queries or filters to reduce output size.]",
+            shown, total
+        ),
+    }
+}
+
+/// Text standing in for a tool's image, or `None` if the image can be sent to the model as
+/// is: it must be able to view images, and the image must be in a format every vision
+/// provider accepts (images travel as bare base64, their type guessed from the data).
+fn image_placeholder(vision: bool, mime_type: &str, data: &str) -> Option<String> {
+    let reason = if !vision {
+        "the current model cannot view images"
+    } else if !FORWARDED_IMAGE_TYPES.contains(&mime_type.to_ascii_lowercase().as_str()) {
+        "models only accept PNG, JPEG, GIF and WebP images"
+    } else {
+        return None;
+    };
+    Some(format!("[Image: {}, ~{} KB — not shown because {}]", mime_type, data.len() * 3 / 4 / 1024, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_results_can_be_paged_with_default_params() {
+        // A chat that never opted into built-in tools, with one MCP tool connected
+        let params = ChatParams::default();
+        let mut available_tools = Vec::new();
+        let mut tool_mapping = HashMap::new();
+        for tool in tools::enabled(params.native_tools.as_deref()) {
+            add_native_tool(tool.as_ref(), schema::SchemaDialect::JsonSchema, &mut available_tools, &mut tool_mapping);
+        }
+        tool_mapping.insert("fs__read".to_string(), ToolEntry {
+            source: ToolSource::Mcp { server: "fs".to_string(), tool: "read".to_string() },
+            display_name: "read".to_string(),
+            schema: serde_json::json!({ "type": "object" }),
+        });
+        add_paging_tool(schema::SchemaDialect::JsonSchema, &mut available_tools, &mut tool_mapping);
+        assert!(tool_mapping.contains_key("read_tool_output"));
+        assert!(available_tools.iter().any(|t| t["function"]["name"] == "read_tool_output"));
+
+        let output: String = (0..1200).map(|i| format!("line {}\n", i)).collect();
+        let max_tokens = params.max_tool_result_tokens.unwrap_or(tool_output::DEFAULT_MAX_RESULT_TOKENS);
+        let cut = tool_output::truncation_point(&output, max_tokens);
+        assert!(cut < output.len());
+        let shown = output[..cut].chars().count();
+        let note = truncation_note(shown, output.chars().count(), Some("out-1"), tool_mapping.contains_key("read_tool_output"));
+        assert!(note.contains("call read_tool_output with that id and offset="));
+
+        // Following the note pages through to the end of the output
+        let rest = tool_output::page(&output, shown, None, max_tokens).unwrap();
+        assert!(rest.starts_with(&output[cut..]));
+        assert!(rest.ends_with("End of output.]"));
+    }
+
+    #[test]
+    fn no_paging_tool_without_tools() {
+        let mut available_tools = Vec::new();
+        let mut tool_mapping = HashMap::new();
+        add_paging_tool(schema::SchemaDialect::JsonSchema, &mut available_tools, &mut tool_mapping);
+        assert!(available_tools.is_empty() && tool_mapping.is_empty());
+    }
+}
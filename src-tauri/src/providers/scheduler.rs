@@ -0,0 +1,296 @@
+//! Request scheduler in front of the provider layer.
+//!
+//! Each provider admits generations up to its concurrency limit (see
+//! `ProviderConfig::concurrency_limit`). Everything else waits in a priority queue,
+//! FIFO within the same priority, and is told its position through `chat:queued` events.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+use crate::providers::{ProviderConfig, ProviderType};
+
+struct Waiter {
+    stream_id: String,
+    priority: i32,
+    seq: u64,
+    wake: oneshot::Sender<()>,
+}
+
+struct ProviderQueue {
+    provider_type: ProviderType,
+    limit: usize,
+    active: usize,
+    waiting: Vec<Waiter>,
+    /// Receives the queue position events; `None` in tests
+    app: Option<AppHandle>,
+}
+
+impl ProviderQueue {
+    /// Waiters in admission order: highest priority first, then oldest first
+    fn sort_waiting(&mut self) {
+        self.waiting.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.seq.cmp(&b.seq)));
+    }
+
+    /// Hand free slots to waiting requests. Returns where the rest stand, to be emitted once
+    /// the `QUEUES` lock is released.
+    fn admit(&mut self) -> Positions {
+        self.sort_waiting();
+        while self.active < self.limit && !self.waiting.is_empty() {
+            let waiter = self.waiting.remove(0);
+            // A closed receiver means the request was cancelled while queued
+            if waiter.wake.send(()).is_ok() {
+                self.active += 1;
+            }
+        }
+        let waiting = match &self.app {
+            Some(_) => self.waiting.iter().map(|w| w.stream_id.clone()).collect(),
+            None => Vec::new(),
+        };
+        Positions { app: self.app.clone(), waiting }
+    }
+}
+
+/// Queue positions of waiting requests, in order
+#[must_use]
+struct Positions {
+    app: Option<AppHandle>,
+    waiting: Vec<String>,
+}
+
+impl Positions {
+    /// Send the `chat:queued` events; never call this while holding `QUEUES`
+    fn emit(self) {
+        let Some(app) = self.app else { return };
+        for (i, stream_id) in self.waiting.iter().enumerate() {
+            let _ = app.emit("chat:queued", serde_json::json!({
+                "stream_id": stream_id,
+                "position": i + 1,
+            }));
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref QUEUES: Mutex<HashMap<String, ProviderQueue>> = Mutex::new(HashMap::new());
+    static ref NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+}
+
+/// A generation slot on a provider. The slot is released when the permit is dropped.
+pub struct Permit {
+    provider_id: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let positions = {
+            let mut queues = QUEUES.lock().unwrap_or_else(|e| e.into_inner());
+            queues.get_mut(&self.provider_id).map(|queue| {
+                queue.active = queue.active.saturating_sub(1);
+                queue.admit()
+            })
+        };
+        if let Some(positions) = positions {
+            positions.emit();
+        }
+    }
+}
+
+/// A request in the queue. Dropped while still waiting (the request was cancelled, or the
+/// future waiting for it was dropped) it leaves the queue; dropped after being admitted but
+/// before a `Permit` was handed out, it frees the slot again.
+struct Queued {
+    provider_id: String,
+    seq: u64,
+    wake: oneshot::Receiver<()>,
+    /// Set once the slot has been turned into a `Permit`
+    admitted: bool,
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        if self.admitted {
+            return;
+        }
+        let positions = {
+            let mut queues = QUEUES.lock().unwrap_or_else(|e| e.into_inner());
+            queues.get_mut(&self.provider_id).map(|queue| {
+                let before = queue.waiting.len();
+                queue.waiting.retain(|w| w.seq != self.seq);
+                if queue.waiting.len() == before && self.wake.try_recv().is_ok() {
+                    queue.active = queue.active.saturating_sub(1);
+                }
+                queue.admit()
+            })
+        };
+        if let Some(positions) = positions {
+            positions.emit();
+        }
+    }
+}
+
+/// Wait for a generation slot on `config`'s provider.
+/// Returns `None` if `cancelled` resolves while the request is still queued.
+pub async fn acquire(
+    app: &AppHandle,
+    config: &ProviderConfig,
+    stream_id: &str,
+    priority: i32,
+    cancelled: impl Future<Output = ()>,
+) -> Option<Permit> {
+    let provider = (config.id.as_str(), config.provider_type.clone(), config.concurrency_limit());
+    wait_for_slot(Some(app), provider, stream_id, priority, cancelled).await
+}
+
+async fn wait_for_slot(
+    app: Option<&AppHandle>,
+    (provider_id, provider_type, limit): (&str, ProviderType, usize),
+    stream_id: &str,
+    priority: i32,
+    cancelled: impl Future<Output = ()>,
+) -> Option<Permit> {
+    let (mut queued, positions) = {
+        let mut queues = QUEUES.lock().unwrap_or_else(|e| e.into_inner());
+        let queue = queues.entry(provider_id.to_string()).or_insert_with(|| ProviderQueue {
+            provider_type: provider_type.clone(),
+            limit: 1,
+            active: 0,
+            waiting: Vec::new(),
+            app: app.cloned(),
+        });
+        queue.limit = limit;
+        queue.provider_type = provider_type;
+
+        if queue.active < queue.limit && queue.waiting.is_empty() {
+            queue.active += 1;
+            return Some(Permit { provider_id: provider_id.to_string() });
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+        queue.waiting.push(Waiter {
+            stream_id: stream_id.to_string(),
+            priority,
+            seq,
+            wake: tx,
+        });
+        let positions = queue.admit();
+        (Queued { provider_id: provider_id.to_string(), seq, wake: rx, admitted: false }, positions)
+    };
+    positions.emit();
+
+    let queued_at = Instant::now();
+    tokio::select! {
+        woken = &mut queued.wake => {
+            // An error means the queue dropped us without admitting us; should not happen
+            woken.ok()?;
+        }
+        // Dropping `queued` takes the request out of the queue
+        _ = cancelled => return None,
+    }
+    queued.admitted = true;
+
+    if let Some(app) = app {
+        let _ = app.emit("chat:dequeued", serde_json::json!({
+            "stream_id": stream_id,
+            "waited_ms": queued_at.elapsed().as_millis() as u64,
+        }));
+    }
+    Some(Permit { provider_id: provider_id.to_string() })
+}
+
+/// Running and queued generations across all providers of the given type
+pub fn stats(provider_type: &ProviderType) -> (u32, u32) {
+    let queues = QUEUES.lock().unwrap_or_else(|e| e.into_inner());
+    queues.values()
+        .filter(|q| &q.provider_type == provider_type)
+        .fold((0, 0), |(active, waiting), q| (active + q.active as u32, waiting + q.waiting.len() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn provider(id: &str) -> (&str, ProviderType, usize) {
+        (id, ProviderType::Ollama, 1)
+    }
+
+    fn counts(provider_id: &str) -> (usize, usize) {
+        let queues = QUEUES.lock().unwrap();
+        queues.get(provider_id).map(|q| (q.active, q.waiting.len())).unwrap_or_default()
+    }
+
+    async fn until_waiting(provider_id: &str, n: usize) {
+        while counts(provider_id).1 != n {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn admits_by_priority_then_age() {
+        let id = "test-priority";
+        let running = wait_for_slot(None, provider(id), "running", 0, std::future::pending()).await.unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (i, (name, priority)) in [("low-1", 0), ("high", 5), ("low-2", 0), ("mid", 2)].into_iter().enumerate() {
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let permit = wait_for_slot(None, provider(id), name, priority, std::future::pending()).await.unwrap();
+                order.lock().unwrap().push(name);
+                drop(permit);
+            }));
+            until_waiting(id, i + 1).await;
+        }
+
+        drop(running);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["high", "mid", "low-1", "low-2"]);
+        assert_eq!(counts(id), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn dropping_a_permit_hands_its_slot_on() {
+        let id = "test-release";
+        let first = wait_for_slot(None, provider(id), "first", 0, std::future::pending()).await.unwrap();
+        let second = tokio::spawn(wait_for_slot(None, provider(id), "second", 0, std::future::pending()));
+        until_waiting(id, 1).await;
+        assert_eq!(counts(id), (1, 1));
+
+        drop(first);
+        let second = second.await.unwrap().unwrap();
+        assert_eq!(counts(id), (1, 0));
+        drop(second);
+        assert_eq!(counts(id), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn cancelled_or_dropped_waiters_leave_the_queue() {
+        let id = "test-cancel";
+        let running = wait_for_slot(None, provider(id), "running", 0, std::future::pending()).await.unwrap();
+
+        let (cancel, cancelled) = oneshot::channel::<()>();
+        let waiter = tokio::spawn(wait_for_slot(None, provider(id), "cancelled", 0, async move {
+            let _ = cancelled.await;
+        }));
+        until_waiting(id, 1).await;
+        cancel.send(()).unwrap();
+        assert!(waiter.await.unwrap().is_none());
+        assert_eq!(counts(id), (1, 0));
+
+        let dropped = wait_for_slot(None, provider(id), "dropped", 0, std::future::pending());
+        assert!(tokio::time::timeout(Duration::from_millis(10), dropped).await.is_err());
+        assert_eq!(counts(id), (1, 0));
+
+        drop(running);
+        assert_eq!(counts(id), (0, 0));
+    }
+}
@@ -0,0 +1,268 @@
+//! Normalization of MCP tool input schemas for each provider.
+//!
+//! MCP servers publish full JSON Schema, but providers accept different subsets:
+//! Gemini takes a restricted OpenAPI 3.0 schema (no `$ref`, `additionalProperties`,
+//! `oneOf`, type arrays...), and some OpenAI-compatible hosts reject meta keywords.
+//! Every schema has its local `$ref`s inlined, then is rewritten for the provider's
+//! dialect. Anything that cannot be expressed is dropped and reported as a warning
+//! rather than failing the whole request.
+
+use serde_json::{json, Map, Value};
+
+/// The schema flavour a provider accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaDialect {
+    /// Full JSON Schema (Anthropic, Ollama)
+    JsonSchema,
+    /// JSON Schema without meta keywords, for OpenAI and compatible hosts
+    OpenAI,
+    /// Gemini's OpenAPI 3.0 subset
+    Gemini,
+}
+
+/// Keywords that only describe the schema document itself
+const META_KEYWORDS: &[&str] = &["$schema", "$id", "$comment", "$defs", "definitions"];
+
+/// Keywords Gemini accepts on a schema node
+const GEMINI_KEYWORDS: &[&str] = &[
+    "type", "format", "title", "description", "nullable", "enum", "properties", "required",
+    "items", "minItems", "maxItems", "minProperties", "maxProperties", "minLength", "maxLength",
+    "pattern", "minimum", "maximum", "anyOf", "default", "example", "propertyOrdering",
+];
+
+/// Nesting limit for inlining recursive `$ref`s
+const MAX_REF_DEPTH: usize = 3;
+
+/// Result of normalizing one schema
+pub struct NormalizedSchema {
+    pub schema: Value,
+    /// Lossy conversions, one per line, prefixed with the JSON path they apply to
+    pub warnings: Vec<String>,
+}
+
+pub fn normalize(schema: &Value, dialect: SchemaDialect) -> NormalizedSchema {
+    let mut warnings = Vec::new();
+    let inlined = inline_refs(schema, schema, "$", 0, &mut warnings);
+    let schema = match dialect {
+        SchemaDialect::JsonSchema | SchemaDialect::OpenAI => strip_meta(inlined),
+        SchemaDialect::Gemini => to_gemini(strip_meta(inlined), "$", &mut warnings),
+    };
+    NormalizedSchema { schema, warnings }
+}
+
+/// Replace local `$ref`s with the schema they point to. Recursive references are cut
+/// off after `MAX_REF_DEPTH` levels and replaced by a generic object.
+fn inline_refs(root: &Value, node: &Value, path: &str, depth: usize, warnings: &mut Vec<String>) -> Value {
+    match node {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(|r| r.as_str()) {
+                let target = reference.strip_prefix('#')
+                    .and_then(|p| if p.is_empty() { Some(root) } else { root.pointer(p) });
+                let mut resolved = match target {
+                    Some(_) if depth >= MAX_REF_DEPTH => {
+                        warnings.push(format!("{}: recursive reference {} cut off", path, reference));
+                        json!({ "type": "object" })
+                    }
+                    Some(target) => inline_refs(root, target, path, depth + 1, warnings),
+                    None => {
+                        warnings.push(format!("{}: unresolvable reference {} replaced by an unconstrained value", path, reference));
+                        json!({})
+                    }
+                };
+                // Sibling keywords (usually a description) override the referenced schema
+                if let Value::Object(resolved_map) = &mut resolved {
+                    for (k, v) in map.iter().filter(|(k, _)| k.as_str() != "$ref") {
+                        resolved_map.insert(k.clone(), inline_refs(root, v, path, depth, warnings));
+                    }
+                }
+                return resolved;
+            }
+            let mut out = Map::new();
+            for (k, v) in map {
+                if k == "$defs" || k == "definitions" {
+                    continue;
+                }
+                // Property names, not the `properties` keyword, make up the path
+                let child = if k == "properties" { path.to_string() } else { child_path(path, k) };
+                out.insert(k.clone(), inline_refs(root, v, &child, depth, warnings));
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| inline_refs(root, v, path, depth, warnings)).collect()),
+        other => other.clone(),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}.{}", path, key)
+}
+
+fn strip_meta(mut schema: Value) -> Value {
+    if let Value::Object(map) = &mut schema {
+        for keyword in META_KEYWORDS {
+            map.remove(*keyword);
+        }
+    }
+    schema
+}
+
+/// Merge the branches of an `allOf` into one schema (properties and required are unioned)
+fn merge_all_of(mut map: Map<String, Value>, branches: Vec<Value>) -> Map<String, Value> {
+    for branch in branches {
+        let Value::Object(branch) = branch else { continue };
+        for (k, v) in branch {
+            match (k.as_str(), map.get_mut(&k), v) {
+                ("properties", Some(Value::Object(existing)), Value::Object(props)) => existing.extend(props),
+                ("required", Some(Value::Array(existing)), Value::Array(req)) => {
+                    for r in req {
+                        if !existing.contains(&r) {
+                            existing.push(r);
+                        }
+                    }
+                }
+                (_, None, v) => {
+                    map.insert(k, v);
+                }
+                _ => {}
+            }
+        }
+    }
+    map
+}
+
+fn append_description(map: &mut Map<String, Value>, note: &str) {
+    let description = match map.get("description").and_then(|d| d.as_str()) {
+        Some(d) if !d.is_empty() => format!("{} ({})", d, note),
+        _ => note.to_string(),
+    };
+    map.insert("description".to_string(), Value::String(description));
+}
+
+fn to_gemini(schema: Value, path: &str, warnings: &mut Vec<String>) -> Value {
+    let Value::Object(mut map) = schema else {
+        // `true` / `{}`-like boolean schemas have no Gemini equivalent
+        return json!({});
+    };
+
+    if let Some(Value::Array(branches)) = map.remove("allOf") {
+        map = merge_all_of(map, branches);
+    }
+    if let Some(one_of) = map.remove("oneOf") {
+        warnings.push(format!("{}: oneOf relaxed to anyOf", path));
+        map.entry("anyOf".to_string()).or_insert(one_of);
+    }
+
+    // Type arrays: ["string", "null"] becomes a nullable string, several real types an anyOf
+    if let Some(Value::Array(types)) = map.get("type").cloned() {
+        let real: Vec<Value> = types.iter().filter(|t| t.as_str() != Some("null")).cloned().collect();
+        if real.len() < types.len() {
+            map.insert("nullable".to_string(), Value::Bool(true));
+        }
+        match real.len() {
+            0 => { map.remove("type"); }
+            1 => { map.insert("type".to_string(), real[0].clone()); }
+            _ => {
+                map.remove("type");
+                map.insert("anyOf".to_string(), Value::Array(real.into_iter().map(|t| json!({ "type": t })).collect()));
+            }
+        }
+    }
+
+    if let Some(constant) = map.remove("const") {
+        match constant {
+            Value::String(_) => { map.insert("enum".to_string(), json!([constant])); }
+            other => {
+                warnings.push(format!("{}: const {} moved to the description", path, other));
+                append_description(&mut map, &format!("must be {}", other));
+            }
+        }
+    }
+
+    let type_name = map.get("type").and_then(|t| t.as_str()).unwrap_or("").to_string();
+
+    // Gemini enums must be strings on a string-typed schema
+    if let Some(Value::Array(values)) = map.get("enum").cloned() {
+        if type_name == "string" || values.iter().all(|v| v.is_string()) {
+            map.insert("type".to_string(), json!("string"));
+        } else {
+            map.remove("enum");
+            let listed: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            warnings.push(format!("{}: non-string enum moved to the description", path));
+            append_description(&mut map, &format!("one of {}", listed.join(", ")));
+        }
+    }
+
+    if let Some(format) = map.get("format").and_then(|f| f.as_str()).map(|f| f.to_string()) {
+        let supported = match type_name.as_str() {
+            "string" => format == "enum" || format == "date-time",
+            "integer" => format == "int32" || format == "int64",
+            "number" => format == "float" || format == "double",
+            _ => false,
+        };
+        if !supported {
+            map.remove("format");
+            append_description(&mut map, &format!("format: {}", format));
+        }
+    }
+
+    match map.get("additionalProperties") {
+        None | Some(Value::Bool(true)) => {}
+        Some(_) => warnings.push(format!("{}: additionalProperties dropped", path)),
+    }
+
+    // Recurse into sub-schemas
+    if let Some(Value::Object(props)) = map.remove("properties") {
+        let converted: Map<String, Value> = props.into_iter()
+            .map(|(k, v)| { let p = child_path(path, &k); (k, to_gemini(v, &p, warnings)) })
+            .collect();
+        // Gemini rejects an empty properties object
+        if !converted.is_empty() {
+            map.insert("properties".to_string(), Value::Object(converted));
+        }
+    }
+    if let Some(items) = map.remove("items") {
+        let items = match items {
+            // Tuple form: Gemini only knows a single item schema
+            Value::Array(mut tuple) if !tuple.is_empty() => {
+                warnings.push(format!("{}: tuple items reduced to the first item schema", path));
+                tuple.swap_remove(0)
+            }
+            other => other,
+        };
+        map.insert("items".to_string(), to_gemini(items, &child_path(path, "items"), warnings));
+    }
+    if let Some(Value::Array(options)) = map.remove("anyOf") {
+        let converted: Vec<Value> = options.into_iter()
+            .enumerate()
+            .map(|(i, o)| to_gemini(o, &format!("{}.anyOf[{}]", path, i), warnings))
+            .collect();
+        map.insert("anyOf".to_string(), Value::Array(converted));
+    }
+
+    // Keep only what Gemini understands, reporting keywords that carried constraints
+    let dropped: Vec<String> = map.keys()
+        .filter(|k| !GEMINI_KEYWORDS.contains(&k.as_str()))
+        .cloned()
+        .collect();
+    for key in dropped {
+        map.remove(&key);
+        if !matches!(key.as_str(), "additionalProperties" | "examples" | "readOnly" | "writeOnly" | "deprecated") {
+            warnings.push(format!("{}: unsupported keyword {} dropped", path, key));
+        }
+    }
+
+    // `required` may only name properties that still exist
+    if let Some(Value::Array(required)) = map.get("required").cloned() {
+        let props = map.get("properties").and_then(|p| p.as_object());
+        let kept: Vec<Value> = required.into_iter()
+            .filter(|r| r.as_str().map(|r| props.map(|p| p.contains_key(r)).unwrap_or(false)).unwrap_or(false))
+            .collect();
+        if kept.is_empty() {
+            map.remove("required");
+        } else {
+            map.insert("required".to_string(), Value::Array(kept));
+        }
+    }
+
+    Value::Object(map)
+}
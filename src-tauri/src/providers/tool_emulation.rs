@@ -0,0 +1,123 @@
+//! Prompt-based tool calling for models without native tool support.
+//!
+//! The tools are described in the system prompt together with a fixed text protocol
+//! (Hermes-style `<tool_call>` tags). Replies are turned back into tool calls by
+//! `tool_recovery`, and earlier tool calls and results in the history are rewritten as text
+//! in the same protocol, since the model's chat template has no place for them.
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use crate::providers::ChatMessage;
+
+lazy_static::lazy_static! {
+    /// Models (keyed by server URL and name) that rejected native tools
+    static ref NO_NATIVE_TOOLS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn model_key(base_url: &str, model: &str) -> String {
+    format!("{}|{}", base_url, model)
+}
+
+/// Whether this model is known to reject native tools
+pub fn is_required(base_url: &str, model: &str) -> bool {
+    NO_NATIVE_TOOLS.lock().unwrap_or_else(|e| e.into_inner()).contains(&model_key(base_url, model))
+}
+
+/// Remember that this model rejected native tools so later requests go straight to emulation
+pub fn mark_required(base_url: &str, model: &str) {
+    NO_NATIVE_TOOLS.lock().unwrap_or_else(|e| e.into_inner()).insert(model_key(base_url, model));
+}
+
+/// System prompt describing the tools and the protocol for calling them
+pub fn system_prompt(tools: &[Value]) -> String {
+    let mut prompt = String::from(
+        "You can call tools to help answer the user. To call a tool, reply with one or more blocks in exactly this format \
+         and nothing after them:\n\
+         <tool_call>\n{\"name\": \"<tool name>\", \"arguments\": {<arguments as JSON>}}\n</tool_call>\n\
+         The results come back in <tool_response> blocks. Only call tools listed below, with arguments that match their \
+         parameters. When you do not need a tool, answer normally without any <tool_call> block.\n\n\
+         Available tools:\n",
+    );
+    for tool in tools {
+        let function = tool.get("function").unwrap_or(tool);
+        let name = function.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        let description = function.get("description").and_then(|d| d.as_str()).unwrap_or_default();
+        let parameters = function.get("parameters").cloned().unwrap_or_else(|| json!({}));
+        prompt.push_str(&format!("\n- {}: {}\n  Parameters (JSON Schema): {}\n", name, description, parameters));
+    }
+    prompt
+}
+
+/// History for an emulated request: the tool prompt joins the system message, and tool calls
+/// and tool results become plain assistant and user text in the protocol's format.
+pub fn rewrite_messages(messages: &[ChatMessage], tools: &[Value]) -> Vec<ChatMessage> {
+    let prompt = system_prompt(tools);
+    let mut call_names: HashMap<String, String> = HashMap::new();
+    let mut rewritten: Vec<ChatMessage> = Vec::with_capacity(messages.len() + 1);
+
+    for msg in messages {
+        match msg.role.as_str() {
+            "assistant" if msg.tool_calls.is_some() => {
+                let mut content = msg.content.clone();
+                for call in msg.tool_calls.iter().flatten() {
+                    let function = call.get("function").unwrap_or(call);
+                    let name = function.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+                    let arguments = match function.get("arguments") {
+                        Some(Value::String(s)) => serde_json::from_str(s).unwrap_or_else(|_| json!({})),
+                        Some(v) => v.clone(),
+                        None => json!({}),
+                    };
+                    if let Some(id) = call.get("id").and_then(|i| i.as_str()) {
+                        call_names.insert(id.to_string(), name.to_string());
+                    }
+                    if !content.is_empty() && !content.ends_with('\n') {
+                        content.push('\n');
+                    }
+                    content.push_str(&format!(
+                        "<tool_call>\n{}\n</tool_call>\n",
+                        json!({ "name": name, "arguments": arguments })
+                    ));
+                }
+                rewritten.push(ChatMessage { tool_calls: None, content, ..msg.clone() });
+            }
+            "tool" => {
+                let name = msg.tool_call_id.as_ref().and_then(|id| call_names.get(id)).map(String::as_str).unwrap_or("tool");
+                let block = format!("<tool_response name=\"{}\">\n{}\n</tool_response>", name, msg.content.trim_end());
+                // Results of one round of calls go back as a single user turn
+                match rewritten.last_mut() {
+                    Some(prev) if prev.role == "user" && prev.content.ends_with("</tool_response>") => {
+                        prev.content.push('\n');
+                        prev.content.push_str(&block);
+                        if let Some(images) = &msg.images {
+                            prev.images.get_or_insert_with(Vec::new).extend(images.iter().cloned());
+                        }
+                    }
+                    _ => rewritten.push(ChatMessage {
+                        role: "user".to_string(),
+                        content: block,
+                        images: msg.images.clone(),
+                        tool_calls: None,
+                        tool_call_id: None,
+                    }),
+                }
+            }
+            _ => rewritten.push(msg.clone()),
+        }
+    }
+
+    match rewritten.first_mut() {
+        Some(first) if first.role == "system" => {
+            first.content.push_str("\n\n");
+            first.content.push_str(&prompt);
+        }
+        _ => rewritten.insert(0, ChatMessage {
+            role: "system".to_string(),
+            content: prompt,
+            images: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }),
+    }
+    rewritten
+}
@@ -0,0 +1,548 @@
+//! Recovery of tool calls that models print as text instead of using the API's tool calling.
+//!
+//! Local models in particular often answer with their chat template's raw tool-call syntax:
+//! Hermes `<tool_call>{...}</tool_call>`, Llama `<|python_tag|>{...}`, Mistral `[TOOL_CALLS]`,
+//! Groq's `<function=name{...}></function>`, or a fenced JSON block. Adapters wrap their event
+//! stream with [`recover_tool_calls`] to turn those back into `ProviderEvent::ToolCall`s.
+//!
+//! Only the Ollama and OpenAI-compatible adapters do: those serve models with arbitrary chat
+//! templates. Anthropic and Gemini return tool calls as structured content blocks produced by
+//! the service itself, so their text never carries template syntax, and holding back their
+//! replies for scanning would only delay streaming.
+
+use futures::stream::{BoxStream, Stream, StreamExt};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use crate::providers::traits::ProviderEvent;
+
+const HERMES_OPEN: &str = "<tool_call>";
+const HERMES_CLOSE: &str = "</tool_call>";
+const FUNCTION_OPEN: &str = "<function=";
+const FUNCTION_CLOSE: &str = "</function>";
+const PYTHON_TAG: &str = "<|python_tag|>";
+const MISTRAL_TAG: &str = "[TOOL_CALLS]";
+const FENCE: &str = "```";
+
+/// Openers of text that may hold a tool call. Tag formats run to the end of the reply.
+const MARKERS: [&str; 5] = [HERMES_OPEN, FUNCTION_OPEN, PYTHON_TAG, MISTRAL_TAG, FENCE];
+/// How much of a reply that starts like JSON is held back while waiting to see whether it
+/// is an untagged tool call; past this it is released as text
+const MAX_JSON_REPLY: usize = 64 * 1024;
+/// Llama end-of-message tokens that sometimes leak into the text after a python_tag call
+const LLAMA_END_TOKENS: [&str; 2] = ["<|eom_id|>", "<|eot_id|>"];
+
+/// A tool call parsed out of assistant text
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+impl RecoveredCall {
+    /// The call in the OpenAI shape the orchestrator consumes. `string_arguments` encodes the
+    /// arguments as a JSON string (OpenAI-style APIs) instead of an object (Ollama).
+    pub fn to_tool_call(&self, string_arguments: bool) -> Value {
+        let arguments = if string_arguments {
+            Value::String(self.arguments.to_string())
+        } else {
+            self.arguments.clone()
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        json!({
+            "id": format!("call_{}", id.split('-').next().unwrap_or("0")),
+            "type": "function",
+            "function": { "name": self.name, "arguments": arguments }
+        })
+    }
+}
+
+/// A piece of assistant text after recovery
+#[derive(Debug, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Calls(Vec<RecoveredCall>),
+}
+
+/// Incremental scanner over streamed assistant text. Plain text is released as soon as it
+/// cannot be the start of a tool call; candidate segments are held until they are complete.
+pub struct Scanner {
+    /// Names of the tools offered to the model; untagged JSON only counts as a call to one of these
+    known_tools: Vec<String>,
+    pending: String,
+    /// Whether any text has been released yet (untagged JSON replies are only recognised at the start)
+    released: bool,
+    json_checked: bool,
+}
+
+impl Scanner {
+    pub fn new(known_tools: Vec<String>) -> Self {
+        Self { known_tools, pending: String::new(), released: false, json_checked: false }
+    }
+
+    /// Add streamed text and return what can be released so far
+    pub fn push(&mut self, text: &str) -> Vec<Piece> {
+        self.pending.push_str(text);
+        self.scan(false)
+    }
+
+    /// End of the reply: resolve everything still held
+    pub fn finish(&mut self) -> Vec<Piece> {
+        self.scan(true)
+    }
+
+    fn scan(&mut self, at_end: bool) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        loop {
+            // A reply that is nothing but JSON may be an untagged call; wait for all of it,
+            // unless it already shows itself to be something else
+            if !self.released && !self.json_checked && self.pending.trim_start().starts_with(['{', '[']) {
+                if !at_end && self.pending.len() < MAX_JSON_REPLY && may_be_json_call(&self.pending, &self.known_tools) {
+                    break;
+                }
+                self.json_checked = true;
+                if let Some(calls) = parse_json_calls(&self.pending, Some(&self.known_tools)) {
+                    self.pending.clear();
+                    pieces.push(Piece::Calls(calls));
+                    break;
+                }
+                // Not a call; it may still hold a tagged one (Mistral's "[TOOL_CALLS]")
+                continue;
+            }
+
+            let Some((start, marker)) = find_marker(&self.pending) else {
+                // Hold back a trailing partial marker such as "<tool_"
+                let keep = if at_end { 0 } else { partial_marker_len(&self.pending) };
+                let text: String = self.pending.drain(..self.pending.len() - keep).collect();
+                self.release(&mut pieces, text);
+                break;
+            };
+
+            if start > 0 {
+                let text: String = self.pending.drain(..start).collect();
+                self.release(&mut pieces, text);
+            }
+
+            let Some(end) = segment_end(&self.pending, marker, at_end) else {
+                break;
+            };
+            let segment: String = self.pending.drain(..end).collect();
+            match parse_segment(&segment, marker, &self.known_tools) {
+                Some(calls) => pieces.push(Piece::Calls(calls)),
+                None => self.release(&mut pieces, segment),
+            }
+        }
+        pieces
+    }
+
+    fn release(&mut self, pieces: &mut Vec<Piece>, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        // Leading whitespace does not rule out an untagged JSON reply yet
+        self.released |= !text.trim().is_empty();
+        match pieces.last_mut() {
+            Some(Piece::Text(prev)) => prev.push_str(&text),
+            _ => pieces.push(Piece::Text(text)),
+        }
+    }
+}
+
+/// Parse every tool call in a complete text. Returns the calls and the text around them.
+pub fn parse(text: &str, known_tools: &[String]) -> (Vec<RecoveredCall>, String) {
+    let mut scanner = Scanner::new(known_tools.to_vec());
+    let mut pieces = scanner.push(text);
+    pieces.extend(scanner.finish());
+
+    let mut calls = Vec::new();
+    let mut rest = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(t) => rest.push_str(&t),
+            Piece::Calls(c) => calls.extend(c),
+        }
+    }
+    (calls, rest)
+}
+
+/// Wrap an adapter's event stream so tool calls printed as text become `ToolCall` events.
+/// A no-op when no tools were offered. See [`RecoveredCall::to_tool_call`] for `string_arguments`.
+pub fn recover_tool_calls(
+    inner: BoxStream<'static, ProviderEvent>,
+    tools: Option<&[Value]>,
+    string_arguments: bool,
+) -> BoxStream<'static, ProviderEvent> {
+    let known_tools: Vec<String> = tools.unwrap_or_default().iter()
+        .filter_map(tool_name)
+        .collect();
+    if known_tools.is_empty() {
+        return inner;
+    }
+    Box::pin(RecoveringStream {
+        inner,
+        scanner: Scanner::new(known_tools),
+        queue: VecDeque::new(),
+        string_arguments,
+        done: false,
+    })
+}
+
+/// Name of a tool definition in any of the adapters' formats
+fn tool_name(tool: &Value) -> Option<String> {
+    tool.pointer("/function/name")
+        .or_else(|| tool.get("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string)
+}
+
+struct RecoveringStream {
+    inner: BoxStream<'static, ProviderEvent>,
+    scanner: Scanner,
+    queue: VecDeque<ProviderEvent>,
+    string_arguments: bool,
+    done: bool,
+}
+
+impl RecoveringStream {
+    fn enqueue(&mut self, pieces: Vec<Piece>) {
+        for piece in pieces {
+            match piece {
+                Piece::Text(t) => self.queue.push_back(ProviderEvent::Content(t)),
+                Piece::Calls(calls) => {
+                    for call in calls {
+                        self.queue.push_back(ProviderEvent::ToolCall(call.to_tool_call(self.string_arguments)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Stream for RecoveringStream {
+    type Item = ProviderEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(ProviderEvent::Content(text))) => {
+                    let pieces = self.scanner.push(&text);
+                    self.enqueue(pieces);
+                }
+                Poll::Ready(Some(event)) => {
+                    // Usage and errors close a reply: release held text first
+                    let pieces = self.scanner.finish();
+                    self.enqueue(pieces);
+                    self.queue.push_back(event);
+                }
+                Poll::Ready(None) => {
+                    let pieces = self.scanner.finish();
+                    self.enqueue(pieces);
+                    self.done = true;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+fn find_marker(text: &str) -> Option<(usize, &'static str)> {
+    MARKERS.iter()
+        .filter_map(|m| text.find(m).map(|i| (i, *m)))
+        .min_by_key(|(i, _)| *i)
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of a marker
+fn partial_marker_len(text: &str) -> usize {
+    MARKERS.iter()
+        .flat_map(|m| (1..m.len()).rev().filter(move |&n| text.ends_with(&m[..n])))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Byte length of the segment that starts with `marker` at the beginning of `text`,
+/// or `None` if it is not complete yet
+fn segment_end(text: &str, marker: &str, at_end: bool) -> Option<usize> {
+    let close = match marker {
+        HERMES_OPEN => Some(HERMES_CLOSE),
+        FUNCTION_OPEN => Some(FUNCTION_CLOSE),
+        FENCE => Some(FENCE),
+        // Llama and Mistral tool calls take up the rest of the reply
+        _ => None,
+    };
+    let found = close.and_then(|c| text[marker.len()..].find(c).map(|i| marker.len() + i + c.len()));
+    match found {
+        Some(end) => Some(end),
+        None if at_end => Some(text.len()),
+        None => None,
+    }
+}
+
+fn parse_segment(segment: &str, marker: &str, known_tools: &[String]) -> Option<Vec<RecoveredCall>> {
+    let body = &segment[marker.len()..];
+    match marker {
+        HERMES_OPEN => parse_json_calls(body.strip_suffix(HERMES_CLOSE).unwrap_or(body), None),
+        FUNCTION_OPEN => parse_function_tag(body.strip_suffix(FUNCTION_CLOSE).unwrap_or(body)),
+        PYTHON_TAG => {
+            let end = LLAMA_END_TOKENS.iter().filter_map(|t| body.find(t)).min().unwrap_or(body.len());
+            parse_json_calls(&body[..end], None)
+        }
+        MISTRAL_TAG => parse_mistral(body),
+        FENCE => {
+            // Skip the info string ("json", "tool_call", ...) of the opening fence
+            let body = body.strip_suffix(FENCE).unwrap_or(body);
+            let content = body.split_once('\n').map(|(_, rest)| rest).unwrap_or(body);
+            parse_json_calls(content, Some(known_tools))
+        }
+        _ => None,
+    }
+}
+
+/// Groq/Llama `<function=name{"arg": 1}></function>` (also `<function=name>{...}`)
+fn parse_function_tag(body: &str) -> Option<Vec<RecoveredCall>> {
+    let json_start = body.find('{');
+    let name_end = json_start.unwrap_or(body.len());
+    let name = body[..name_end].trim().trim_end_matches('>').trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let arguments = match json_start {
+        Some(i) => serde_json::from_str(body[i..].trim().trim_end_matches('>').trim()).ok()?,
+        None => json!({}),
+    };
+    Some(vec![RecoveredCall { name: name.to_string(), arguments }])
+}
+
+/// Mistral `[TOOL_CALLS] [{"name": ..., "arguments": ...}]` or the newer `[TOOL_CALLS]name[ARGS]{...}`
+fn parse_mistral(body: &str) -> Option<Vec<RecoveredCall>> {
+    if body.contains("[ARGS]") {
+        let mut calls = Vec::new();
+        for part in body.split(MISTRAL_TAG) {
+            let (name, args) = part.split_once("[ARGS]")?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let arguments = json_values(args)?.into_iter().next()?;
+            calls.push(RecoveredCall { name: name.to_string(), arguments });
+        }
+        return Some(calls);
+    }
+    parse_json_calls(body, None)
+}
+
+/// One or more JSON call objects (or arrays of them), separated by whitespace, `;` or `,`.
+/// With `known_tools`, every call must name one of them and carry explicit arguments; that
+/// keeps ordinary JSON in a reply from being taken for a call.
+fn parse_json_calls(text: &str, known_tools: Option<&[String]>) -> Option<Vec<RecoveredCall>> {
+    let mut calls = Vec::new();
+    for value in json_values(text)? {
+        let items = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+        for item in items {
+            let call = call_from_value(&item, known_tools.is_some())?;
+            if let Some(known) = known_tools {
+                if !known.contains(&call.name) {
+                    return None;
+                }
+            }
+            calls.push(call);
+        }
+    }
+    if calls.is_empty() { None } else { Some(calls) }
+}
+
+/// Whether a reply that starts with JSON can still turn out to be an untagged call: its
+/// first value is incomplete, or complete and a call
+fn may_be_json_call(text: &str, known_tools: &[String]) -> bool {
+    let mut stream = serde_json::Deserializer::from_str(text.trim_start()).into_iter::<Value>();
+    match stream.next() {
+        Some(Ok(value)) => parse_json_calls(&value.to_string(), Some(known_tools)).is_some(),
+        Some(Err(e)) => e.is_eof(),
+        None => true,
+    }
+}
+
+/// Read a call from `{"name", "arguments"|"parameters"}` or `{"function": {"name", "arguments"}}`
+fn call_from_value(value: &Value, require_arguments: bool) -> Option<RecoveredCall> {
+    let obj = value.get("function").filter(|f| f.is_object()).unwrap_or(value);
+    let name = obj.get("name")?.as_str()?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let arguments = match obj.get("arguments").or_else(|| obj.get("parameters")) {
+        Some(Value::String(s)) => serde_json::from_str(s).ok()?,
+        Some(v @ Value::Object(_)) => v.clone(),
+        Some(Value::Null) | None if !require_arguments => json!({}),
+        _ => return None,
+    };
+    Some(RecoveredCall { name: name.to_string(), arguments })
+}
+
+/// All JSON values in `text`; `None` if anything else is in between
+fn json_values(text: &str) -> Option<Vec<Value>> {
+    let mut values = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ';' || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        let value = stream.next()?.ok()?;
+        if !value.is_object() && !value.is_array() {
+            return None;
+        }
+        values.push(value);
+        rest = &rest[stream.byte_offset()..];
+    }
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools() -> Vec<String> {
+        vec!["get_weather".to_string(), "search".to_string()]
+    }
+
+    fn weather(city: &str) -> RecoveredCall {
+        RecoveredCall { name: "get_weather".to_string(), arguments: json!({ "city": city }) }
+    }
+
+    /// Feed `chunks` to a scanner one by one and join the resulting pieces
+    fn scan(chunks: &[&str]) -> (Vec<RecoveredCall>, String) {
+        let mut scanner = Scanner::new(tools());
+        let mut pieces = Vec::new();
+        for chunk in chunks {
+            pieces.extend(scanner.push(chunk));
+        }
+        pieces.extend(scanner.finish());
+        let mut calls = Vec::new();
+        let mut text = String::new();
+        for piece in pieces {
+            match piece {
+                Piece::Text(t) => text.push_str(&t),
+                Piece::Calls(c) => calls.extend(c),
+            }
+        }
+        (calls, text)
+    }
+
+    #[test]
+    fn fenced_json() {
+        let (calls, text) = parse("Checking.\n```json\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Oslo\"}}\n```", &tools());
+        assert_eq!(calls, [weather("Oslo")]);
+        assert_eq!(text, "Checking.\n");
+    }
+
+    #[test]
+    fn fenced_code_that_is_not_a_call_stays_text() {
+        let reply = "Example:\n```json\n{\"name\": \"Ada\", \"age\": 36}\n```";
+        assert_eq!(parse(reply, &tools()), (vec![], reply.to_string()));
+    }
+
+    #[test]
+    fn bare_json() {
+        let (calls, text) = parse("  [{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Oslo\"}}, {\"name\": \"search\", \"parameters\": {\"q\": \"x\"}}]", &tools());
+        assert_eq!(calls, [weather("Oslo"), RecoveredCall { name: "search".to_string(), arguments: json!({ "q": "x" }) }]);
+        assert_eq!(text.trim(), "");
+    }
+
+    #[test]
+    fn bare_json_of_unknown_tool_stays_text() {
+        let reply = "{\"name\": \"delete_everything\", \"arguments\": {}}";
+        assert_eq!(parse(reply, &tools()), (vec![], reply.to_string()));
+    }
+
+    #[test]
+    fn tagged_calls() {
+        let (calls, text) = parse("<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Oslo\"}}\n</tool_call>", &tools());
+        assert_eq!((calls, text), (vec![weather("Oslo")], String::new()));
+
+        let (calls, _) = parse("<function=get_weather{\"city\": \"Rome\"}></function>", &tools());
+        assert_eq!(calls, [weather("Rome")]);
+
+        let (calls, _) = parse("<|python_tag|>{\"name\": \"get_weather\", \"parameters\": {\"city\": \"Lima\"}}<|eom_id|>", &tools());
+        assert_eq!(calls, [weather("Lima")]);
+
+        let (calls, _) = parse("[TOOL_CALLS]get_weather[ARGS]{\"city\": \"Kyiv\"}", &tools());
+        assert_eq!(calls, [weather("Kyiv")]);
+    }
+
+    #[test]
+    fn calls_split_across_chunks() {
+        let (calls, text) = scan(&["Let me check. <to", "ol_call>{\"name\": \"get_", "weather\", \"arguments\": {\"city\": \"Oslo\"}}</tool", "_call>"]);
+        assert_eq!(calls, [weather("Oslo")]);
+        assert_eq!(text, "Let me check. ");
+
+        let (calls, _) = scan(&["{\"name\": \"get_weather\",", " \"arguments\": {\"city\": \"Oslo\"}}"]);
+        assert_eq!(calls, [weather("Oslo")]);
+    }
+
+    #[test]
+    fn truncated_json_is_released_as_text() {
+        let reply = "<tool_call>{\"name\": \"get_weather\", \"arguments\": {\"ci";
+        assert_eq!(scan(&[reply]), (vec![], reply.to_string()));
+
+        let reply = "{\"name\": \"get_weather\", \"arguments\": {\"city\": ";
+        assert_eq!(scan(&[reply]), (vec![], reply.to_string()));
+    }
+
+    #[test]
+    fn prose_that_looks_like_json_streams_without_waiting() {
+        let mut scanner = Scanner::new(tools());
+        // Not JSON after all: released as soon as that is clear
+        let pieces = scanner.push("[1] First point, and {braces} in prose");
+        assert_eq!(pieces, [Piece::Text("[1] First point, and {braces} in prose".to_string())]);
+
+        // Complete JSON that is not a call does not hold back the rest of the reply
+        let mut scanner = Scanner::new(tools());
+        let pieces = scanner.push("{\"status\": \"ok\"} is the response");
+        assert_eq!(pieces, [Piece::Text("{\"status\": \"ok\"} is the response".to_string())]);
+    }
+
+    #[test]
+    fn json_reply_hold_back_is_bounded() {
+        let mut scanner = Scanner::new(tools());
+        let start = "{\"name\": \"get_weather\", \"arguments\": {\"city\": \"";
+        assert!(scanner.push(start).is_empty());
+        let pieces = scanner.push(&"x".repeat(MAX_JSON_REPLY));
+        assert!(matches!(pieces.as_slice(), [Piece::Text(t)] if t.len() == start.len() + MAX_JSON_REPLY));
+    }
+
+    #[tokio::test]
+    async fn recovers_calls_from_a_stream() {
+        let tool_defs = vec![json!({ "type": "function", "function": { "name": "get_weather" } })];
+        let events = vec![
+            ProviderEvent::Content("Sure. <tool_call>{\"name\": \"get_weather\", ".to_string()),
+            ProviderEvent::Content("\"arguments\": {\"city\": \"Oslo\"}}</tool_call>".to_string()),
+            ProviderEvent::Finish(crate::providers::traits::FinishReason::Stop),
+        ];
+        let stream = recover_tool_calls(futures::stream::iter(events).boxed(), Some(&tool_defs), true);
+        let out: Vec<ProviderEvent> = stream.collect().await;
+
+        assert!(matches!(&out[0], ProviderEvent::Content(t) if t == "Sure. "));
+        let ProviderEvent::ToolCall(call) = &out[1] else { panic!("expected a tool call, got {:?}", out[1]) };
+        assert_eq!(call["function"]["name"], "get_weather");
+        assert_eq!(call["function"]["arguments"], "{\"city\":\"Oslo\"}");
+        assert!(matches!(out[2], ProviderEvent::Finish(_)));
+        assert_eq!(out.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn streams_pass_through_without_tools() {
+        let events = vec![ProviderEvent::Content("<tool_call>{}</tool_call>".to_string())];
+        let out: Vec<ProviderEvent> = recover_tool_calls(futures::stream::iter(events).boxed(), None, false).collect().await;
+        assert!(matches!(out.as_slice(), [ProviderEvent::Content(t)] if t == "<tool_call>{}</tool_call>"));
+    }
+}
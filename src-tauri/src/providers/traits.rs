@@ -1,15 +1,15 @@
 use async_trait::async_trait;
 use futures::stream::BoxStream;
+use serde::Serialize;
 use serde_json::Value;
 use crate::providers::{ChatMessage, ProviderConfig, ChatOptions};
+use crate::providers::schema::SchemaDialect;
 
 #[derive(Debug, Clone)]
 pub struct Usage {
     #[allow(dead_code)]
     pub prompt_tokens: Option<i32>,
-    #[allow(dead_code)]
     pub completion_tokens: Option<i32>,
-    #[allow(dead_code)]
     pub total_tokens: Option<i32>,
 }
 
@@ -21,14 +21,55 @@ pub enum ProviderEvent {
     /// The provider adapter is responsible for assembling deltas.
     ToolCall(Value),
     /// Usage statistics
-    #[allow(dead_code)]
     Usage(Usage),
+    /// Why the generation stopped
+    Finish(FinishReason),
     /// An error occurred
     Error(String),
 }
 
+/// Normalized stop reason of a generation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    /// Natural end of the reply or a stop sequence
+    Stop,
+    /// Cut off by the output token limit
+    Length,
+    ToolUse,
+    ContentFilter,
+    /// Any reason not listed above, as the provider named it
+    Other(String),
+}
+
+impl FinishReason {
+    /// Map a provider's own stop reason (OpenAI, Anthropic, Gemini and Ollama spellings)
+    pub fn from_provider(reason: &str) -> Self {
+        match reason.to_ascii_lowercase().as_str() {
+            "stop" | "end_turn" | "stop_sequence" | "eos" => FinishReason::Stop,
+            "length" | "max_tokens" | "model_length" => FinishReason::Length,
+            "tool_calls" | "tool_use" | "function_call" => FinishReason::ToolUse,
+            "content_filter" | "safety" | "recitation" | "blocklist" | "prohibited_content" | "spii" | "refusal" => {
+                FinishReason::ContentFilter
+            }
+            _ => FinishReason::Other(reason.to_string()),
+        }
+    }
+}
+
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
+    /// Tool schema flavour this provider accepts; tool schemas are normalized to it
+    fn schema_dialect(&self) -> SchemaDialect {
+        SchemaDialect::JsonSchema
+    }
+
+    /// Whether `model` accepts images, e.g. screenshots returned by tools.
+    /// Models that don't get a text placeholder instead.
+    async fn supports_images(&self, _config: &ProviderConfig, _model: &str) -> bool {
+        false
+    }
+
     /// Stream chat completion events
     async fn stream_chat(
         &self,
@@ -0,0 +1,372 @@
+//! Arithmetic evaluation and unit conversion, so models don't have to do math in their head.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use crate::mcp::protocol::CallToolResult;
+use crate::tools::{str_arg, text_result, NativeTool, ToolContext};
+
+pub struct Calculator;
+
+#[async_trait]
+impl NativeTool for Calculator {
+    fn name(&self) -> &'static str {
+        "calculator"
+    }
+
+    fn description(&self) -> &'static str {
+        "Evaluate an arithmetic expression. Supports + - * / % ^, parentheses, the constants pi and e, \
+         and the functions sqrt, abs, ln, log (base 10), exp, sin, cos, tan, floor, ceil and round."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string", "description": "Expression to evaluate, e.g. (3 + 4) * 2 ^ 3" }
+            },
+            "required": ["expression"]
+        })
+    }
+
+    async fn call(&self, args: Value, _ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let expression = str_arg(&args, "expression")?;
+        let value = evaluate(expression)?;
+        Ok(text_result(format!("{} = {}", expression.trim(), format_number(value))))
+    }
+}
+
+pub struct ConvertUnits;
+
+#[async_trait]
+impl NativeTool for ConvertUnits {
+    fn name(&self) -> &'static str {
+        "convert_units"
+    }
+
+    fn description(&self) -> &'static str {
+        "Convert a value between units of length, mass, volume, time, digital storage or temperature \
+         (e.g. km to mi, lb to kg, l to gal, h to s, GiB to MB, C to F). Digital storage symbols are \
+         case-sensitive: Mb is megabits, MB megabytes."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "value": { "type": "number" },
+                "from": { "type": "string", "description": "Source unit symbol, e.g. km" },
+                "to": { "type": "string", "description": "Target unit symbol, e.g. mi" }
+            },
+            "required": ["value", "from", "to"]
+        })
+    }
+
+    async fn call(&self, args: Value, _ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let value = args.get("value")
+            .and_then(|v| v.as_f64())
+            .ok_or_else(|| anyhow::anyhow!("Missing number argument 'value'"))?;
+        let from = str_arg(&args, "from")?;
+        let to = str_arg(&args, "to")?;
+        let converted = convert(value, from, to)?;
+        Ok(text_result(format!("{} {} = {} {}", format_number(value), from, format_number(converted), to)))
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        let s = format!("{:.10}", value);
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+// ============================================================================
+// Expression evaluator (recursive descent)
+// ============================================================================
+
+/// Nesting (parentheses, function calls, signs and exponents) the parser follows before giving up
+const MAX_DEPTH: usize = 64;
+
+pub fn evaluate(expression: &str) -> anyhow::Result<f64> {
+    let mut parser = Parser { chars: expression.chars().filter(|c| !c.is_whitespace()).collect(), pos: 0, depth: 0 };
+    let value = parser.expr()?;
+    if parser.pos < parser.chars.len() {
+        return Err(anyhow::anyhow!("Unexpected '{}' at position {}", parser.chars[parser.pos], parser.pos + 1));
+    }
+    if !value.is_finite() {
+        return Err(anyhow::anyhow!("Result is not a finite number"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Current recursion depth, bounded by `MAX_DEPTH`
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> anyhow::Result<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> anyhow::Result<f64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let rhs = self.unary()?;
+                if rhs == 0.0 {
+                    return Err(anyhow::anyhow!("Division by zero"));
+                }
+                value /= rhs;
+            } else if self.eat('%') {
+                value %= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // Every recursive rule goes through unary, so this is where the depth is bounded
+    fn unary(&mut self) -> anyhow::Result<f64> {
+        if self.depth >= MAX_DEPTH {
+            return Err(anyhow::anyhow!("Expression is nested too deeply"));
+        }
+        self.depth += 1;
+        let value = self.signed();
+        self.depth -= 1;
+        value
+    }
+
+    // unary := '-' unary | power
+    fn signed(&mut self) -> anyhow::Result<f64> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    // power := atom ('^' unary)?   (right associative)
+    fn power(&mut self) -> anyhow::Result<f64> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    // atom := number | ident | ident '(' expr ')' | '(' expr ')'
+    fn atom(&mut self) -> anyhow::Result<f64> {
+        if self.eat('(') {
+            let value = self.expr()?;
+            if !self.eat(')') {
+                return Err(anyhow::anyhow!("Missing closing parenthesis"));
+            }
+            return Ok(value);
+        }
+
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '.') {
+                    self.pos += 1;
+                }
+                // Scientific notation, e.g. 1.5e-3
+                if matches!(self.peek(), Some('e') | Some('E'))
+                    && matches!(self.chars.get(self.pos + 1), Some(c) if c.is_ascii_digit() || *c == '-' || *c == '+')
+                {
+                    self.pos += 2;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                        self.pos += 1;
+                    }
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                text.parse::<f64>().map_err(|_| anyhow::anyhow!("Invalid number '{}'", text))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric()) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect::<String>().to_lowercase();
+                if self.eat('(') {
+                    let arg = self.expr()?;
+                    if !self.eat(')') {
+                        return Err(anyhow::anyhow!("Missing closing parenthesis after {}(", name));
+                    }
+                    apply_function(&name, arg)
+                } else {
+                    match name.as_str() {
+                        "pi" => Ok(std::f64::consts::PI),
+                        "e" => Ok(std::f64::consts::E),
+                        _ => Err(anyhow::anyhow!("Unknown constant '{}'", name)),
+                    }
+                }
+            }
+            Some(c) => Err(anyhow::anyhow!("Unexpected '{}' at position {}", c, self.pos + 1)),
+            None => Err(anyhow::anyhow!("Unexpected end of expression")),
+        }
+    }
+}
+
+fn apply_function(name: &str, arg: f64) -> anyhow::Result<f64> {
+    Ok(match name {
+        "sqrt" => arg.sqrt(),
+        "abs" => arg.abs(),
+        "ln" => arg.ln(),
+        "log" => arg.log10(),
+        "exp" => arg.exp(),
+        "sin" => arg.sin(),
+        "cos" => arg.cos(),
+        "tan" => arg.tan(),
+        "floor" => arg.floor(),
+        "ceil" => arg.ceil(),
+        "round" => arg.round(),
+        _ => return Err(anyhow::anyhow!("Unknown function '{}'", name)),
+    })
+}
+
+// ============================================================================
+// Unit conversion
+// ============================================================================
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Time,
+    Data,
+}
+
+/// (symbols, dimension, factor to the dimension's base unit)
+const UNITS: &[(&[&str], Dimension, f64)] = &[
+    (&["mm", "millimeter", "millimeters"], Dimension::Length, 0.001),
+    (&["cm", "centimeter", "centimeters"], Dimension::Length, 0.01),
+    (&["m", "meter", "meters", "metre", "metres"], Dimension::Length, 1.0),
+    (&["km", "kilometer", "kilometers"], Dimension::Length, 1000.0),
+    (&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    (&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    (&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    (&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    (&["nmi", "nautical_mile"], Dimension::Length, 1852.0),
+    (&["mg", "milligram", "milligrams"], Dimension::Mass, 0.001),
+    (&["g", "gram", "grams"], Dimension::Mass, 1.0),
+    (&["kg", "kilogram", "kilograms"], Dimension::Mass, 1000.0),
+    (&["t", "tonne", "tonnes"], Dimension::Mass, 1_000_000.0),
+    (&["oz", "ounce", "ounces"], Dimension::Mass, 28.349523125),
+    (&["lb", "lbs", "pound", "pounds"], Dimension::Mass, 453.59237),
+    (&["st", "stone"], Dimension::Mass, 6350.29318),
+    (&["ml", "milliliter", "milliliters"], Dimension::Volume, 0.001),
+    (&["l", "liter", "liters", "litre", "litres"], Dimension::Volume, 1.0),
+    (&["m3", "cubic_meter"], Dimension::Volume, 1000.0),
+    (&["tsp", "teaspoon"], Dimension::Volume, 0.00492892159375),
+    (&["tbsp", "tablespoon"], Dimension::Volume, 0.01478676478125),
+    (&["floz", "fl_oz", "fluid_ounce"], Dimension::Volume, 0.0295735295625),
+    (&["cup", "cups"], Dimension::Volume, 0.2365882365),
+    (&["pt", "pint", "pints"], Dimension::Volume, 0.473176473),
+    (&["qt", "quart", "quarts"], Dimension::Volume, 0.946352946),
+    (&["gal", "gallon", "gallons"], Dimension::Volume, 3.785411784),
+    (&["ms", "millisecond", "milliseconds"], Dimension::Time, 0.001),
+    (&["s", "sec", "second", "seconds"], Dimension::Time, 1.0),
+    (&["min", "minute", "minutes"], Dimension::Time, 60.0),
+    (&["h", "hr", "hour", "hours"], Dimension::Time, 3600.0),
+    (&["d", "day", "days"], Dimension::Time, 86400.0),
+    (&["wk", "week", "weeks"], Dimension::Time, 604800.0),
+    (&["yr", "year", "years"], Dimension::Time, 31_557_600.0),
+    // Data symbols are case-sensitive: b is a bit, B a byte
+    (&["b", "bit", "bits"], Dimension::Data, 0.125),
+    (&["Kb", "kbit"], Dimension::Data, 125.0),
+    (&["Mb", "Mbit"], Dimension::Data, 125e3),
+    (&["Gb", "Gbit"], Dimension::Data, 125e6),
+    (&["Tb", "Tbit"], Dimension::Data, 125e9),
+    (&["B", "byte", "bytes"], Dimension::Data, 1.0),
+    (&["kB", "KB"], Dimension::Data, 1e3),
+    (&["MB"], Dimension::Data, 1e6),
+    (&["GB"], Dimension::Data, 1e9),
+    (&["TB"], Dimension::Data, 1e12),
+    (&["KiB"], Dimension::Data, 1024.0),
+    (&["MiB"], Dimension::Data, 1048576.0),
+    (&["GiB"], Dimension::Data, 1073741824.0),
+    (&["TiB"], Dimension::Data, 1099511627776.0),
+];
+
+/// Find a unit by its exact symbol, or else ignoring case as long as that leaves a single
+/// unit (so "KM" is km, but "mb" could be Mb or MB and is rejected)
+fn lookup_unit(unit: &str) -> anyhow::Result<(Dimension, f64)> {
+    let unit = unit.trim();
+    if let Some((_, dim, factor)) = UNITS.iter().find(|(symbols, _, _)| symbols.contains(&unit)) {
+        return Ok((*dim, *factor));
+    }
+    let matches: Vec<&str> = UNITS.iter()
+        .filter_map(|(symbols, _, _)| symbols.iter().find(|s| s.eq_ignore_ascii_case(unit)).copied())
+        .collect();
+    match matches.as_slice() {
+        [] => Err(anyhow::anyhow!("Unknown unit '{}'", unit)),
+        [symbol] => lookup_unit(symbol),
+        _ => Err(anyhow::anyhow!("Ambiguous unit '{}'; use one of {}", unit, matches.join(", "))),
+    }
+}
+
+fn temperature_to_kelvin(value: f64, unit: &str) -> Option<f64> {
+    match unit {
+        "c" | "celsius" => Some(value + 273.15),
+        "f" | "fahrenheit" => Some((value - 32.0) * 5.0 / 9.0 + 273.15),
+        "k" | "kelvin" => Some(value),
+        _ => None,
+    }
+}
+
+fn kelvin_to_temperature(kelvin: f64, unit: &str) -> Option<f64> {
+    match unit {
+        "c" | "celsius" => Some(kelvin - 273.15),
+        "f" | "fahrenheit" => Some((kelvin - 273.15) * 9.0 / 5.0 + 32.0),
+        "k" | "kelvin" => Some(kelvin),
+        _ => None,
+    }
+}
+
+pub fn convert(value: f64, from: &str, to: &str) -> anyhow::Result<f64> {
+    let (from_l, to_l) = (from.trim().to_lowercase(), to.trim().to_lowercase());
+    if let Some(kelvin) = temperature_to_kelvin(value, &from_l) {
+        return kelvin_to_temperature(kelvin, &to_l)
+            .ok_or_else(|| anyhow::anyhow!("Cannot convert temperature unit '{}' to '{}'", from, to));
+    }
+
+    let (from_dim, from_factor) = lookup_unit(from)?;
+    let (to_dim, to_factor) = lookup_unit(to)?;
+    if from_dim != to_dim {
+        return Err(anyhow::anyhow!("Cannot convert {:?} unit '{}' to {:?} unit '{}'", from_dim, from, to_dim, to));
+    }
+    Ok(value * from_factor / to_factor)
+}
@@ -0,0 +1,170 @@
+//! Search and read over the user's own stored chats
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use crate::db::get_pool;
+use crate::mcp::protocol::CallToolResult;
+use crate::tools::{str_arg, text_result, NativeTool, ToolContext};
+
+/// Characters of context shown around each search hit
+const SNIPPET_CHARS: usize = 160;
+
+#[derive(FromRow)]
+struct SearchHit {
+    chat_id: String,
+    title: Option<String>,
+    role: String,
+    content: String,
+    created_at: i64,
+}
+
+pub struct SearchChatHistory;
+
+#[async_trait]
+impl NativeTool for SearchChatHistory {
+    fn name(&self) -> &'static str {
+        "search_chat_history"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search the user's previous chats for a word or phrase. Returns matching snippets with the chat id; \
+         use read_chat to open a chat."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Text to look for (case-insensitive)" },
+                "limit": { "type": "integer", "description": "Maximum number of hits (default 10)" }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn call(&self, args: Value, _ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let query = str_arg(&args, "query")?;
+        let limit = args.get("limit").and_then(|v| v.as_i64()).unwrap_or(10).clamp(1, 50);
+        let pool = get_pool().await.map_err(|e| anyhow::anyhow!(e))?;
+
+        let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let hits = sqlx::query_as::<_, SearchHit>(
+            r#"SELECT m.chat_id, c.title, m.role, m.content, m.created_at
+               FROM messages m JOIN chats c ON c.id = m.chat_id
+               WHERE m.role IN ('user', 'assistant') AND m.content LIKE ? ESCAPE '\'
+               ORDER BY m.created_at DESC LIMIT ?"#
+        )
+        .bind(&pattern)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        if hits.is_empty() {
+            return Ok(text_result(format!("No messages found containing \"{}\".", query)));
+        }
+
+        let mut out = String::new();
+        for hit in hits {
+            let date = chrono::DateTime::from_timestamp_millis(hit.created_at)
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "- chat_id: {} | \"{}\" | {} | {}: {}\n",
+                hit.chat_id,
+                hit.title.unwrap_or_else(|| "Untitled".to_string()),
+                date,
+                hit.role,
+                snippet(&hit.content, query)
+            ));
+        }
+        Ok(text_result(out))
+    }
+}
+
+/// Cut `SNIPPET_CHARS` characters of `content` around the first match of `query`
+fn snippet(content: &str, query: &str) -> String {
+    let lower = fold_case(content);
+    let chars: Vec<char> = content.chars().collect();
+    // Work in char positions so multi-byte text is never split
+    let hit = lower.find(&fold_case(query))
+        .map(|byte| lower[..byte].chars().count())
+        .unwrap_or(0);
+    let start = hit.saturating_sub(SNIPPET_CHARS / 2);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+    let mut s: String = chars[start..end].iter().collect::<String>().replace('\n', " ");
+    if start > 0 {
+        s.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        s.push_str("...");
+    }
+    s
+}
+
+/// Lowercase one char at a time, keeping a single char for each, so that char positions
+/// in the result match those in `s` (`str::to_lowercase` turns e.g. 'İ' into two chars)
+fn fold_case(s: &str) -> String {
+    s.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect()
+}
+
+#[derive(FromRow)]
+struct ChatLine {
+    role: String,
+    content: String,
+}
+
+pub struct ReadChat;
+
+#[async_trait]
+impl NativeTool for ReadChat {
+    fn name(&self) -> &'static str {
+        "read_chat"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read the user and assistant messages of one of the user's previous chats, oldest first."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "chat_id": { "type": "string" },
+                "offset": { "type": "integer", "description": "Number of messages to skip (default 0)" },
+                "limit": { "type": "integer", "description": "Maximum number of messages (default 20)" }
+            },
+            "required": ["chat_id"]
+        })
+    }
+
+    async fn call(&self, args: Value, _ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let chat_id = str_arg(&args, "chat_id")?;
+        let offset = args.get("offset").and_then(|v| v.as_i64()).unwrap_or(0).max(0);
+        let limit = args.get("limit").and_then(|v| v.as_i64()).unwrap_or(20).clamp(1, 100);
+        let pool = get_pool().await.map_err(|e| anyhow::anyhow!(e))?;
+
+        let title: Option<Option<String>> = sqlx::query_scalar("SELECT title FROM chats WHERE id = ?")
+            .bind(chat_id)
+            .fetch_optional(&pool)
+            .await?;
+        let Some(title) = title else {
+            return Err(anyhow::anyhow!("Chat '{}' not found", chat_id));
+        };
+
+        let lines = sqlx::query_as::<_, ChatLine>(
+            "SELECT role, content FROM messages WHERE chat_id = ? AND role IN ('user', 'assistant') AND content != '' ORDER BY created_at ASC LIMIT ? OFFSET ?"
+        )
+        .bind(chat_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&pool)
+        .await?;
+
+        let mut out = format!("Chat \"{}\" (messages {}-{}):\n\n", title.unwrap_or_else(|| "Untitled".to_string()), offset + 1, offset + lines.len() as i64);
+        for line in lines {
+            out.push_str(&format!("{}: {}\n\n", line.role, line.content));
+        }
+        Ok(text_result(out))
+    }
+}
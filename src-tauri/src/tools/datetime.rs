@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use chrono::{Local, Utc};
+use serde_json::{json, Value};
+use crate::mcp::protocol::CallToolResult;
+use crate::tools::{text_result, NativeTool, ToolContext};
+
+pub struct CurrentDateTime;
+
+#[async_trait]
+impl NativeTool for CurrentDateTime {
+    fn name(&self) -> &'static str {
+        "current_datetime"
+    }
+
+    fn description(&self) -> &'static str {
+        "Get the current date, time, weekday and time zone offset of the user's machine, plus the current UTC time."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn call(&self, _args: Value, _ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let local = Local::now();
+        let utc = Utc::now();
+        Ok(text_result(format!(
+            "Local: {} ({})\nUTC offset: {}\nUTC: {}\nUnix timestamp: {}",
+            local.format("%Y-%m-%d %H:%M:%S"),
+            local.format("%A"),
+            local.format("%:z"),
+            utc.format("%Y-%m-%dT%H:%M:%SZ"),
+            utc.timestamp()
+        )))
+    }
+}
@@ -0,0 +1,136 @@
+//! Hand a self-contained task to a sub-agent: a child conversation on another model or
+//! provider, run to completion by its own `ChatOrchestrator`.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tauri::Emitter;
+use crate::commands::chat::register_run;
+use crate::commands::db::{create_child_chat, insert_message};
+use crate::commands::settings::provider_get;
+use crate::db::get_pool;
+use crate::mcp::protocol::CallToolResult;
+use crate::providers::orchestrator::{ChatOrchestrator, ChatRun};
+use crate::providers::{create_provider, ChatMessage};
+use crate::tools::{str_arg, text_result, NativeTool, ToolContext};
+
+/// Characters of the task used as the title of the child chat
+const TITLE_CHARS: usize = 60;
+
+pub struct Delegate;
+
+#[async_trait]
+impl NativeTool for Delegate {
+    fn name(&self) -> &'static str {
+        "delegate"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hand a self-contained task to a sub-agent running on another model, for example bulk work on a cheaper \
+         local model. The sub-agent sees only the task and its own system prompt, may use the listed tools, and \
+         returns its final answer as the result of this call."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "task": { "type": "string", "description": "Complete instructions for the sub-agent, including all context it needs" },
+                "model": { "type": "string", "description": "Model to run the sub-agent on" },
+                "provider": { "type": "string", "description": "Id or name of a configured provider (default: the current one)" },
+                "system_prompt": { "type": "string", "description": "System prompt for the sub-agent" },
+                "tools": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Names of the tools the sub-agent may use (default: all of yours except delegate)"
+                }
+            },
+            "required": ["task", "model"]
+        })
+    }
+
+    async fn call(&self, args: Value, ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let task = str_arg(&args, "task")?;
+        let model = str_arg(&args, "model")?;
+        let system_prompt = args.get("system_prompt").and_then(|v| v.as_str());
+        let config = match args.get("provider").and_then(|v| v.as_str()) {
+            Some(p) => provider_get(p).await.map_err(|e| anyhow::anyhow!(e))?,
+            None => ctx.config.clone(),
+        };
+
+        let allowed_tools = match args.get("tools").and_then(|v| v.as_array()) {
+            Some(names) => {
+                let names: Vec<String> = names.iter().filter_map(|n| n.as_str().map(str::to_string)).collect();
+                if let Some(unknown) = names.iter().find(|n| !ctx.tool_names.contains(n)) {
+                    anyhow::bail!("Unknown tool '{}'. Available tools: {}", unknown, ctx.tool_names.join(", "));
+                }
+                names
+            }
+            // By default the sub-agent gets the tools of this conversation
+            None => ctx.tool_names.clone(),
+        };
+
+        // Keep the sub-agent's transcript as a child of this chat
+        let mut child_chat_id = None;
+        if let Some(parent_id) = &ctx.chat_id {
+            let title: String = task.chars().take(TITLE_CHARS).collect();
+            let stored = async {
+                let pool = get_pool().await?;
+                let id = create_child_chat(&pool, parent_id, ctx.message_id.as_deref(), model, system_prompt, &title).await?;
+                insert_message(&pool, &id, "user", task, None).await?;
+                Ok::<_, String>(id)
+            }.await;
+            match stored {
+                Ok(id) => child_chat_id = Some(id),
+                Err(e) => eprintln!("Failed to store delegated chat for {}: {}", parent_id, e),
+            }
+        }
+
+        let mut messages = Vec::new();
+        if let Some(prompt) = system_prompt {
+            messages.push(ChatMessage {
+                role: "system".to_string(),
+                content: prompt.to_string(),
+                images: None,
+                tool_calls: None,
+                tool_call_id: None,
+            });
+        }
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: task.to_string(),
+            images: None,
+            tool_calls: None,
+            tool_call_id: None,
+        });
+
+        let child_stream_id = uuid::Uuid::new_v4().to_string();
+        let child_run = Arc::new(ChatRun::child(ctx.run.clone(), child_chat_id.clone(), model.to_string()));
+        println!("Delegating to {} on {} (stream {})", model, config.name, child_stream_id);
+        // Listed with the other runs, so it can be watched and cancelled on its own
+        let _registration = register_run(&child_stream_id, child_run.clone()).await;
+        let _ = ctx.app.emit("chat:delegate-start", json!({
+            "stream_id": ctx.stream_id,
+            "child_stream_id": child_stream_id,
+            "child_chat_id": child_chat_id,
+            "provider": config.name,
+            "model": model,
+        }));
+
+        let orchestrator = ChatOrchestrator::new(ctx.app.clone(), create_provider(&config.provider_type), child_chat_id.clone())
+            .delegated(allowed_tools);
+        let result = orchestrator.run_conversation(&config, model, messages, None, &child_stream_id, child_run).await;
+
+        let _ = ctx.app.emit("chat:delegate-end", json!({
+            "stream_id": ctx.stream_id,
+            "child_stream_id": child_stream_id,
+            "child_chat_id": child_chat_id,
+            "completed": matches!(result, Ok(Some(_))),
+        }));
+
+        match result? {
+            Some(reply) => Ok(text_result(reply)),
+            None => anyhow::bail!("The sub-agent stopped without a final answer"),
+        }
+    }
+}
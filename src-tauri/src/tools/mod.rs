@@ -0,0 +1,89 @@
+//! Built-in tools that run in-process, next to the tools of external MCP servers.
+//!
+//! Native tools return the same `CallToolResult` as MCP tools so the orchestrator can
+//! treat both alike, minus the IPC round trip.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+use tauri::AppHandle;
+use crate::mcp::protocol::{CallToolResult, Content};
+use crate::providers::orchestrator::ChatRun;
+use crate::providers::ProviderConfig;
+
+pub mod calculator;
+pub mod chat_history;
+pub mod datetime;
+pub mod delegate;
+pub mod tool_output;
+pub mod validation;
+
+/// Per-call context handed to native tools
+pub struct ToolContext {
+    pub app: AppHandle,
+    /// Chat the conversation belongs to, if it is persisted
+    pub chat_id: Option<String>,
+    /// Stored assistant message that made the current tool calls
+    pub message_id: Option<String>,
+    /// Token budget for a single tool result in this chat
+    pub max_result_tokens: usize,
+    /// Provider the conversation runs on
+    pub config: ProviderConfig,
+    pub run: Arc<ChatRun>,
+    pub stream_id: String,
+    /// Names of all tools offered in this conversation
+    pub tool_names: Vec<String>,
+}
+
+#[async_trait]
+pub trait NativeTool: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// JSON Schema of the arguments object
+    fn input_schema(&self) -> Value;
+    async fn call(&self, args: Value, ctx: &ToolContext) -> anyhow::Result<CallToolResult>;
+}
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: Vec<Arc<dyn NativeTool>> = vec![
+        Arc::new(datetime::CurrentDateTime),
+        Arc::new(calculator::Calculator),
+        Arc::new(calculator::ConvertUnits),
+        Arc::new(chat_history::SearchChatHistory),
+        Arc::new(chat_history::ReadChat),
+        Arc::new(tool_output::ReadToolOutput),
+        Arc::new(delegate::Delegate),
+    ];
+}
+
+/// All built-in tools
+pub fn all() -> &'static [Arc<dyn NativeTool>] {
+    &REGISTRY
+}
+
+pub fn get(name: &str) -> Option<Arc<dyn NativeTool>> {
+    REGISTRY.iter().find(|t| t.name() == name).cloned()
+}
+
+/// Built-in tools enabled for a chat. `enabled` is the chat's allow-list; `None` enables none.
+pub fn enabled(enabled: Option<&[String]>) -> Vec<Arc<dyn NativeTool>> {
+    REGISTRY.iter()
+        .filter(|t| enabled.is_some_and(|e| e.iter().any(|n| n == t.name())))
+        .cloned()
+        .collect()
+}
+
+/// Wrap plain text output as a successful tool result
+pub fn text_result(text: impl Into<String>) -> CallToolResult {
+    CallToolResult {
+        content: vec![Content::Text { text: text.into() }],
+        is_error: false,
+    }
+}
+
+/// Read a required string argument
+pub fn str_arg<'a>(args: &'a Value, key: &str) -> anyhow::Result<&'a str> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing string argument '{}'", key))
+}
@@ -0,0 +1,117 @@
+//! Artifact store for tool outputs too large to hand to the model in one piece.
+//!
+//! The orchestrator keeps the full text of an oversized result here and sends the model a
+//! truncated view; `read_tool_output` pages through the rest.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use crate::db::get_pool;
+use crate::mcp::protocol::CallToolResult;
+use crate::tools::{str_arg, text_result, NativeTool, ToolContext};
+
+/// Default per-result budget when the chat does not set `max_tool_result_tokens`
+pub const DEFAULT_MAX_RESULT_TOKENS: usize = 2000;
+/// Rough characters-per-token ratio used for budgeting (no tokenizer for local models)
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Approximate token count of a text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Byte index at which `text` must be cut to keep at most `max_tokens` tokens.
+/// Always a char boundary; prefers the last line break in the second half of the kept part.
+pub fn truncation_point(text: &str, max_tokens: usize) -> usize {
+    let max_chars = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+    let end = text.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(text.len());
+    match text[..end].rfind('\n') {
+        Some(nl) if end < text.len() && nl >= end / 2 => nl,
+        _ => end,
+    }
+}
+
+/// Keep the full output of a tool call; returns the artifact id
+pub async fn save(chat_id: Option<&str>, tool_name: &str, content: &str) -> Result<String, String> {
+    let pool = get_pool().await?;
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO tool_outputs (id, chat_id, tool_name, content, created_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(&id)
+        .bind(chat_id)
+        .bind(tool_name)
+        .bind(content)
+        .bind(chrono::Utc::now().timestamp_millis())
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to store tool output: {}", e))?;
+    Ok(id)
+}
+
+async fn load(id: &str) -> Result<Option<(Option<String>, String)>, String> {
+    let pool = get_pool().await?;
+    sqlx::query_as::<_, (Option<String>, String)>("SELECT chat_id, content FROM tool_outputs WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| format!("Failed to load tool output: {}", e))
+}
+
+pub struct ReadToolOutput;
+
+#[async_trait]
+impl NativeTool for ReadToolOutput {
+    fn name(&self) -> &'static str {
+        "read_tool_output"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read part of a tool output that was truncated. Use the id from the truncation note; \
+         offset and length are in characters."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Id of the stored tool output" },
+                "offset": { "type": "integer", "minimum": 0, "description": "Character offset to start reading at (default 0)" },
+                "length": { "type": "integer", "minimum": 1, "description": "Number of characters to read (capped by the chat's tool result limit)" }
+            },
+            "required": ["id"]
+        })
+    }
+
+    async fn call(&self, args: Value, ctx: &ToolContext) -> anyhow::Result<CallToolResult> {
+        let id = str_arg(&args, "id")?;
+        let (owner, content) = load(id).await
+            .map_err(|e| anyhow::anyhow!(e))?
+            .ok_or_else(|| anyhow::anyhow!("No stored tool output with id {}", id))?;
+        // Outputs stored for one chat are not readable from another
+        if owner.is_some() && owner != ctx.chat_id {
+            anyhow::bail!("No stored tool output with id {}", id);
+        }
+
+        let offset = args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        let length = args.get("length").and_then(|v| v.as_u64()).map(|l| l as usize);
+        Ok(text_result(page(&content, offset, length, ctx.max_result_tokens)?))
+    }
+}
+
+/// The characters of `content` from `offset`, at most `length` and never more than the budget,
+/// with a footer telling where to continue
+pub fn page(content: &str, offset: usize, length: Option<usize>, max_tokens: usize) -> anyhow::Result<String> {
+    let max_chars = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+    let total = content.chars().count();
+    let length = length.unwrap_or(max_chars).clamp(1, max_chars);
+    if offset >= total {
+        anyhow::bail!("Offset {} is past the end of the output ({} characters)", offset, total);
+    }
+
+    let chunk: String = content.chars().skip(offset).take(length).collect();
+    let end = offset + chunk.chars().count();
+    let footer = if end < total {
+        format!("\n\n[Characters {}-{} of {}. Call read_tool_output with offset={} to continue.]", offset, end, total, end)
+    } else {
+        format!("\n\n[Characters {}-{} of {}. End of output.]", offset, end, total)
+    };
+    Ok(format!("{}{}", chunk, footer))
+}
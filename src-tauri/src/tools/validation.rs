@@ -0,0 +1,208 @@
+//! Validation of tool-call arguments against the tool's JSON Schema.
+//!
+//! Covers the keywords tool schemas use in practice: `type`, `properties`, `required`,
+//! `additionalProperties`, `items`, `enum`, `const`, numeric and length bounds,
+//! `anyOf`/`oneOf`/`allOf` and local `$ref`s. Unknown keywords are ignored.
+
+use serde_json::Value;
+
+/// Guards against self-referencing `$ref` cycles
+const MAX_DEPTH: usize = 32;
+
+/// Validate `args` against `schema`. Returns one message per problem, each starting
+/// with the JSON path of the offending value (`$` is the arguments object).
+pub fn validate(schema: &Value, args: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, schema, args, "$", 0, &mut errors);
+    errors
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => value.as_f64().map(|n| n.fract() == 0.0).unwrap_or(false),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+/// Resolve a local reference such as `#/$defs/Point` against the root schema
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    root.pointer(pointer)
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, path: &str, depth: usize, errors: &mut Vec<String>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let Some(schema) = schema.as_object() else {
+        // `false` rejects everything, `true` or a non-object accepts anything
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: no value is allowed here", path));
+        }
+        return;
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+        match resolve_ref(root, reference) {
+            Some(target) => check(root, target, value, path, depth + 1, errors),
+            None => eprintln!("Cannot resolve schema reference {}", reference),
+        }
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            errors.push(format!("{}: expected {}, got {}", path, allowed.join(" or "), type_name(value)));
+            // Further keywords would only repeat the same mistake
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            let listed: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+            errors.push(format!("{}: must be one of {}, got {}", path, listed.join(", "), value));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            errors.push(format!("{}: must be {}, got {}", path, constant, value));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if !map.contains_key(key) {
+                        errors.push(format!("{}: missing required property '{}'", path, key));
+                    }
+                }
+            }
+            for (key, item) in map {
+                let item_path = format!("{}.{}", path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(item_schema) => check(root, item_schema, item, &item_path, depth + 1, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            let known: Vec<&str> = properties.map(|p| p.keys().map(|k| k.as_str()).collect()).unwrap_or_default();
+                            errors.push(format!("{}: unknown property '{}' (allowed: {})", path, key, known.join(", ")));
+                        }
+                        Some(extra @ Value::Object(_)) => check(root, extra, item, &item_path, depth + 1, errors),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(root, item_schema, item, &format!("{}[{}]", path, i), depth + 1, errors);
+                }
+            }
+            if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{}: expected at least {} items, got {}", path, min, items.len()));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()) {
+                if items.len() as u64 > max {
+                    errors.push(format!("{}: expected at most {} items, got {}", path, max, items.len()));
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+                if len < min {
+                    errors.push(format!("{}: expected at least {} characters, got {}", path, min, len));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()) {
+                if len > max {
+                    errors.push(format!("{}: expected at most {} characters, got {}", path, max, len));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+                if n < min {
+                    errors.push(format!("{}: must be >= {}, got {}", path, min, n));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+                if n > max {
+                    errors.push(format!("{}: must be <= {}, got {}", path, max, n));
+                }
+            }
+            if let Some(min) = schema.get("exclusiveMinimum").and_then(|m| m.as_f64()) {
+                if n <= min {
+                    errors.push(format!("{}: must be > {}, got {}", path, min, n));
+                }
+            }
+            if let Some(max) = schema.get("exclusiveMaximum").and_then(|m| m.as_f64()) {
+                if n >= max {
+                    errors.push(format!("{}: must be < {}, got {}", path, max, n));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+        for sub in all {
+            check(root, sub, value, path, depth + 1, errors);
+        }
+    }
+    for (keyword, exactly_one) in [("anyOf", false), ("oneOf", true)] {
+        if let Some(options) = schema.get(keyword).and_then(|a| a.as_array()) {
+            let mut option_errors = Vec::new();
+            let matching = options.iter().filter(|sub| {
+                let mut errs = Vec::new();
+                check(root, sub, value, path, depth + 1, &mut errs);
+                let ok = errs.is_empty();
+                option_errors.extend(errs);
+                ok
+            }).count();
+            if matching == 0 {
+                errors.push(format!("{}: does not match any allowed form ({})", path, option_errors.join("; ")));
+            } else if exactly_one && matching > 1 {
+                errors.push(format!("{}: matches {} of the oneOf forms, expected exactly one", path, matching));
+            }
+        }
+    }
+}
+
+/// Parse the `arguments` of a tool call. Most providers send a JSON string, Ollama
+/// sends an object; an empty or missing value means no arguments.
+pub fn parse_arguments(arguments: Option<&Value>) -> Result<Value, String> {
+    match arguments {
+        None | Some(Value::Null) => Ok(Value::Object(Default::default())),
+        Some(Value::String(s)) if s.trim().is_empty() => Ok(Value::Object(Default::default())),
+        Some(Value::String(s)) => serde_json::from_str(s)
+            .map_err(|e| format!("arguments are not valid JSON ({}): {}", e, s)),
+        Some(other) => Ok(other.clone()),
+    }
+}
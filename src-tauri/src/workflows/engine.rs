@@ -0,0 +1,284 @@
+//! Runs workflows in the background, step by step, on top of `ChatOrchestrator`
+//! (and so on `LLMProvider` and the MCP/native tools).
+//!
+//! Events: `workflow:run-start`, `workflow:step-start` (with the `stream_id` whose `chat:*`
+//! events carry the step's streamed reply), `workflow:step-complete`, `workflow:step-skipped`,
+//! `workflow:step-failed` and `workflow:run-complete`.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use crate::commands::settings::{provider_get, provider_get_active};
+use crate::commands::workflows::{finish_run, insert_run, insert_step, StepRecord};
+use crate::db::get_pool;
+use crate::providers::orchestrator::{ChatOrchestrator, ChatRun};
+use crate::providers::{create_provider, ChatMessage};
+use crate::workflows::{condition_holds, lookup, render, OutputFormat, Workflow, WorkflowStep};
+
+lazy_static::lazy_static! {
+    /// Workflow runs in progress, by run id; cancelling the `ChatRun` cancels every step under it
+    static ref WORKFLOW_RUNS: Mutex<HashMap<String, Arc<ChatRun>>> = Mutex::new(HashMap::new());
+}
+
+/// Check the inputs, record the run and execute it in the background. Returns the run id.
+pub async fn start(app: AppHandle, workflow_id: Option<String>, workflow: Workflow, inputs: Map<String, Value>) -> Result<String, String> {
+    let mut vars: HashMap<String, Value> = HashMap::new();
+    for input in &workflow.inputs {
+        match inputs.get(&input.name).cloned().or_else(|| input.default.clone()) {
+            Some(value) => { vars.insert(input.name.clone(), value); }
+            None => return Err(format!("Missing workflow input '{}'", input.name)),
+        }
+    }
+    // Extra inputs are available to templates as well
+    for (name, value) in &inputs {
+        vars.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+
+    let pool = get_pool().await?;
+    let run_id = insert_run(&pool, workflow_id.as_deref(), &workflow.name, &Value::Object(inputs)).await?;
+    let run = Arc::new(ChatRun::new(None, workflow.name.clone(), 0));
+    WORKFLOW_RUNS.lock().await.insert(run_id.clone(), run.clone());
+
+    let task_run_id = run_id.clone();
+    tokio::spawn(async move {
+        let _ = app.emit("workflow:run-start", json!({ "run_id": task_run_id, "workflow": workflow.name }));
+        let result = execute(&app, &task_run_id, &workflow, &mut vars, &run).await;
+        WORKFLOW_RUNS.lock().await.remove(&task_run_id);
+
+        let outputs: Map<String, Value> = workflow.steps.iter()
+            .filter_map(|s| vars.get(s.output_var()).map(|v| (s.output_var().to_string(), v.clone())))
+            .collect();
+        let outputs = Value::Object(outputs);
+        let (status, error) = match &result {
+            Ok(()) => ("completed", None),
+            Err(_) if run.is_cancelled() => ("cancelled", None),
+            Err(e) => ("failed", Some(e.as_str())),
+        };
+        if let Err(e) = finish_run(&pool, &task_run_id, status, &outputs, error).await {
+            eprintln!("{}", e);
+        }
+        let _ = app.emit("workflow:run-complete", json!({
+            "run_id": task_run_id,
+            "status": status,
+            "error": error,
+            "outputs": outputs,
+        }));
+    });
+
+    Ok(run_id)
+}
+
+pub async fn cancel(run_id: &str) -> bool {
+    match WORKFLOW_RUNS.lock().await.get(run_id) {
+        Some(run) => {
+            run.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+async fn execute(app: &AppHandle, run_id: &str, workflow: &Workflow, vars: &mut HashMap<String, Value>, run: &Arc<ChatRun>) -> Result<(), String> {
+    let pool = get_pool().await?;
+
+    for step in &workflow.steps {
+        if run.is_cancelled() {
+            return Err("Workflow cancelled".to_string());
+        }
+        let started_at = chrono::Utc::now().timestamp_millis();
+
+        if let Some(condition) = &step.when {
+            if !condition_holds(condition, vars) {
+                let _ = app.emit("workflow:step-skipped", json!({ "run_id": run_id, "step": step.id }));
+                let record = StepRecord { step_id: &step.id, iteration: None, status: "skipped", prompt: None, output: None, error: None, started_at };
+                if let Err(e) = insert_step(&pool, run_id, &record).await {
+                    eprintln!("{}", e);
+                }
+                continue;
+            }
+        }
+
+        let result = match &step.for_each {
+            Some(list_var) => {
+                let items = list_items(vars, list_var)
+                    .ok_or_else(|| format!("Step '{}': '{}' is not a list", step.id, list_var))?;
+                let mut results = Vec::with_capacity(items.len());
+                for (index, item) in items.into_iter().enumerate() {
+                    let mut scope = vars.clone();
+                    scope.insert(step.item_var.clone(), item);
+                    scope.insert("index".to_string(), json!(index));
+                    results.push(run_step(app, run_id, workflow, step, &scope, Some(index), run).await?);
+                }
+                Value::Array(results)
+            }
+            None => run_step(app, run_id, workflow, step, vars, None, run).await?,
+        };
+        vars.insert(step.output_var().to_string(), result);
+    }
+    Ok(())
+}
+
+/// Run one execution of a step and record it. Returns the parsed output.
+async fn run_step(
+    app: &AppHandle,
+    run_id: &str,
+    workflow: &Workflow,
+    step: &WorkflowStep,
+    vars: &HashMap<String, Value>,
+    iteration: Option<usize>,
+    run: &Arc<ChatRun>,
+) -> Result<Value, String> {
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let prompt = render(&step.prompt, vars);
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let _ = app.emit("workflow:step-start", json!({
+        "run_id": run_id,
+        "step": step.id,
+        "iteration": iteration,
+        "stream_id": stream_id,
+    }));
+
+    let reply = generate(app, workflow, step, vars, &prompt, &stream_id, run).await;
+    let output = reply.and_then(|text| parse_output(&text, &step.format).map(|value| (text, value)));
+
+    let (status, error) = match &output {
+        Ok(_) => ("completed", None),
+        Err(_) if run.is_cancelled() => ("cancelled", None),
+        Err(e) => ("failed", Some(e.as_str())),
+    };
+    let text = output.as_ref().ok().map(|(text, _)| text.as_str());
+    let record = StepRecord { step_id: &step.id, iteration, status, prompt: Some(&prompt), output: text, error, started_at };
+    if let Err(e) = insert_step(&get_pool().await?, run_id, &record).await {
+        eprintln!("{}", e);
+    }
+
+    match output {
+        Ok((_, value)) => {
+            let _ = app.emit("workflow:step-complete", json!({
+                "run_id": run_id,
+                "step": step.id,
+                "iteration": iteration,
+                "output": value,
+            }));
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = app.emit("workflow:step-failed", json!({
+                "run_id": run_id,
+                "step": step.id,
+                "iteration": iteration,
+                "error": e,
+            }));
+            Err(format!("Step '{}' failed: {}", step.id, e))
+        }
+    }
+}
+
+/// Run the step's prompt through the orchestrator, with only the step's tools available
+async fn generate(
+    app: &AppHandle,
+    workflow: &Workflow,
+    step: &WorkflowStep,
+    vars: &HashMap<String, Value>,
+    prompt: &str,
+    stream_id: &str,
+    run: &Arc<ChatRun>,
+) -> Result<String, String> {
+    let config = match step.provider.as_ref().or(workflow.provider.as_ref()) {
+        Some(p) => provider_get(p).await?,
+        None => provider_get_active().await?,
+    };
+    // Validation guarantees one of the two
+    let model = step.model.as_ref().or(workflow.model.as_ref()).cloned().unwrap_or_default();
+
+    let mut messages = Vec::new();
+    if let Some(system) = &step.system {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: render(system, vars),
+            images: None,
+            tool_calls: None,
+            tool_call_id: None,
+        });
+    }
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: prompt.to_string(),
+        images: None,
+        tool_calls: None,
+        tool_call_id: None,
+    });
+
+    let step_run = Arc::new(ChatRun::child(run.clone(), None, model.clone()));
+    let orchestrator = ChatOrchestrator::new(app.clone(), create_provider(&config.provider_type), None)
+        .delegated(step.tools.clone().unwrap_or_default());
+    match orchestrator.run_conversation(&config, &model, messages, None, stream_id, step_run).await {
+        Ok(Some(reply)) => Ok(reply),
+        Ok(None) => Err("Generation stopped without a reply".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// The elements of a list variable. Text is read as a JSON array, or else as one item per line.
+fn list_items(vars: &HashMap<String, Value>, path: &str) -> Option<Vec<Value>> {
+    let path = path.trim().trim_start_matches("{{").trim_end_matches("}}").trim();
+    match lookup(vars, path)? {
+        Value::Array(items) => Some(items.clone()),
+        Value::String(text) => match serde_json::from_str::<Value>(text.trim()) {
+            Ok(Value::Array(items)) => Some(items),
+            _ => Some(text.lines().map(str::trim).filter(|l| !l.is_empty()).map(|l| json!(l)).collect()),
+        },
+        _ => None,
+    }
+}
+
+fn parse_output(text: &str, format: &OutputFormat) -> Result<Value, String> {
+    match format {
+        OutputFormat::Text => Ok(Value::String(text.trim().to_string())),
+        OutputFormat::Lines => Ok(Value::Array(
+            text.lines().map(str::trim).filter(|l| !l.is_empty()).map(|l| json!(l)).collect(),
+        )),
+        OutputFormat::Json => {
+            let trimmed = text.trim();
+            // Models like to wrap JSON in a ```json fence
+            let body = trimmed.strip_prefix("```")
+                .and_then(|rest| rest.split_once('\n'))
+                .map(|(_, rest)| rest.trim_end().trim_end_matches("```"))
+                .unwrap_or(trimmed);
+            serde_json::from_str(body.trim()).map_err(|e| format!("Reply is not valid JSON: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_items_from_arrays_json_and_lines() {
+        let vars: HashMap<String, Value> = [
+            ("urls".to_string(), json!(["a", "b"])),
+            ("json".to_string(), json!("[1, 2]")),
+            ("text".to_string(), json!("first\n\n  second \n")),
+            ("page".to_string(), json!({ "links": ["x"] })),
+            ("count".to_string(), json!(3)),
+        ].into_iter().collect();
+        assert_eq!(list_items(&vars, "urls"), Some(vec![json!("a"), json!("b")]));
+        assert_eq!(list_items(&vars, "{{ json }}"), Some(vec![json!(1), json!(2)]));
+        assert_eq!(list_items(&vars, "text"), Some(vec![json!("first"), json!("second")]));
+        assert_eq!(list_items(&vars, "page.links"), Some(vec![json!("x")]));
+        assert_eq!(list_items(&vars, "count"), None);
+        assert_eq!(list_items(&vars, "missing"), None);
+    }
+
+    #[test]
+    fn parses_step_output() {
+        assert_eq!(parse_output("  done \n", &OutputFormat::Text).unwrap(), json!("done"));
+        assert_eq!(parse_output("a\n\n b \n", &OutputFormat::Lines).unwrap(), json!(["a", "b"]));
+        assert_eq!(parse_output("```json\n{\"ok\": true}\n```", &OutputFormat::Json).unwrap(), json!({ "ok": true }));
+        assert_eq!(parse_output("[1, 2]", &OutputFormat::Json).unwrap(), json!([1, 2]));
+        assert!(parse_output("not json", &OutputFormat::Json).unwrap_err().contains("not valid JSON"));
+    }
+}
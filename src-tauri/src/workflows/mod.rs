@@ -0,0 +1,280 @@
+//! Declarative multi-step workflows (prompt chains) defined in YAML or JSON.
+//!
+//! A workflow is a list of steps. Each step renders a prompt template against the variables
+//! collected so far, runs it on a model (optionally with tools) and stores the reply in an
+//! output variable. Steps can be skipped with a `when` condition or mapped over a list with
+//! `for_each`. `engine` runs them; runs are recorded in SQLite.
+//!
+//! ```yaml
+//! name: Summarize pages
+//! model: llama3.2
+//! inputs:
+//!   - name: urls
+//! steps:
+//!   - id: fetch
+//!     for_each: urls
+//!     prompt: "Fetch {{item}} and summarize it in three sentences."
+//!     tools: [fetch__fetch]
+//!     output: summaries
+//!   - id: digest
+//!     when: "{{summaries}}"
+//!     prompt: "Combine these summaries into one digest:\n{{summaries}}"
+//!     output: digest
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+pub mod engine;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Default provider (id or name) and model for steps that do not set their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<WorkflowInput>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowInput {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Used when the input is not given; inputs without a default are required
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// Parse the reply as JSON (a surrounding code fence is ignored)
+    Json,
+    /// Split the reply into a list of non-empty lines
+    Lines,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    pub id: String,
+    /// Prompt template; `{{name}}` and `{{name.field}}` are replaced with variables
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tools the model may call in this step (qualified names, as the chat shows them)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    /// Condition; the step is skipped unless it holds. See [`condition_holds`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Run the step once per element of this list variable; the element is bound to `as`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub for_each: Option<String>,
+    #[serde(default = "default_item_var", rename = "as")]
+    pub item_var: String,
+    /// Variable the result is stored in (defaults to the step id). Mapped steps store a list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+fn default_item_var() -> String {
+    "item".to_string()
+}
+
+impl WorkflowStep {
+    pub fn output_var(&self) -> &str {
+        self.output.as_deref().unwrap_or(&self.id)
+    }
+}
+
+impl Workflow {
+    /// Parse a definition. `format` is "yaml" or "json"; without it JSON is detected by a leading `{`.
+    pub fn parse(text: &str, format: Option<&str>) -> Result<Self, String> {
+        let is_json = match format {
+            Some(f) => f.eq_ignore_ascii_case("json"),
+            None => text.trim_start().starts_with('{'),
+        };
+        let workflow: Workflow = if is_json {
+            serde_json::from_str(text).map_err(|e| format!("Invalid workflow JSON: {}", e))?
+        } else {
+            serde_norway::from_str(text).map_err(|e| format!("Invalid workflow YAML: {}", e))?
+        };
+        workflow.validate()?;
+        Ok(workflow)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Workflow needs a name".to_string());
+        }
+        if self.steps.is_empty() {
+            return Err("Workflow has no steps".to_string());
+        }
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if !ids.insert(step.id.as_str()) {
+                return Err(format!("Duplicate step id '{}'", step.id));
+            }
+            if step.model.is_none() && self.model.is_none() {
+                return Err(format!("Step '{}' has no model and the workflow sets no default", step.id));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replace `{{name}}` / `{{name.field.0}}` placeholders. Strings are inserted as-is, other
+/// values as JSON; unknown variables render as an empty string.
+pub fn render(template: &str, vars: &HashMap<String, Value>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        out.push_str(&rest[..start]);
+        let path = rest[start + 2..start + 2 + len].trim();
+        if let Some(value) = lookup(vars, path) {
+            match value {
+                Value::String(s) => out.push_str(s),
+                Value::Null => {}
+                other => out.push_str(&other.to_string()),
+            }
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Resolve a dotted variable path such as `page.title` or `results.0`
+pub fn lookup<'a>(vars: &'a HashMap<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = vars.get(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+            other => other.get(part)?,
+        };
+    }
+    Some(value)
+}
+
+/// Evaluate a `when` condition. Supported forms are `a == b`, `a != b`, `a contains b`
+/// (quotes around operands are optional) and a bare value, which holds unless it is empty,
+/// `false`, `no`, `0`, `null` or `[]`. The operator is taken from the template before the
+/// operands are rendered, so variables holding e.g. `==` cannot change the comparison.
+pub fn condition_holds(condition: &str, vars: &HashMap<String, Value>) -> bool {
+    let operand = |s: &str| render(s, vars).trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+    if let Some((a, b)) = split_operator(condition, "!=") {
+        return operand(a) != operand(b);
+    }
+    if let Some((a, b)) = split_operator(condition, "==") {
+        return operand(a) == operand(b);
+    }
+    if let Some((a, b)) = split_operator(condition, " contains ") {
+        return operand(a).contains(&operand(b));
+    }
+    let value = operand(condition).to_lowercase();
+    !matches!(value.as_str(), "" | "false" | "no" | "0" | "null" | "[]")
+}
+
+/// Split `template` around the first `op` outside of `{{...}}` placeholders
+fn split_operator<'a>(template: &'a str, op: &str) -> Option<(&'a str, &'a str)> {
+    let mut i = 0;
+    while i < template.len() {
+        let rest = &template[i..];
+        if rest.starts_with("{{") {
+            i += rest.find("}}").map_or(rest.len(), |end| end + 2);
+        } else if let Some(after) = rest.strip_prefix(op) {
+            return Some((&template[..i], after));
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, Value)]) -> HashMap<String, Value> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn parses_yaml_and_json() {
+        let yaml = "name: Digest\nmodel: llama3.2\nsteps:\n  - id: a\n    prompt: Hi\n    for_each: urls\n    format: lines\n";
+        let workflow = Workflow::parse(yaml, None).unwrap();
+        assert_eq!(workflow.steps[0].item_var, "item");
+        assert_eq!(workflow.steps[0].output_var(), "a");
+        assert!(matches!(workflow.steps[0].format, OutputFormat::Lines));
+
+        let json = r#"{"name": "Digest", "steps": [{"id": "a", "prompt": "Hi", "model": "m", "as": "url", "output": "out"}]}"#;
+        let workflow = Workflow::parse(json, None).unwrap();
+        assert_eq!(workflow.steps[0].item_var, "url");
+        assert_eq!(workflow.steps[0].output_var(), "out");
+    }
+
+    #[test]
+    fn rejects_invalid_workflows() {
+        let no_model = "name: W\nsteps:\n  - id: a\n    prompt: Hi\n";
+        assert!(Workflow::parse(no_model, None).unwrap_err().contains("has no model"));
+        let duplicate = "name: W\nmodel: m\nsteps:\n  - id: a\n    prompt: Hi\n  - id: a\n    prompt: Ho\n";
+        assert!(Workflow::parse(duplicate, None).unwrap_err().contains("Duplicate step id"));
+        assert!(Workflow::parse("name: W\nmodel: m\nsteps: []\n", None).unwrap_err().contains("no steps"));
+        assert!(Workflow::parse("{", Some("json")).unwrap_err().contains("Invalid workflow JSON"));
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let vars = vars(&[
+            ("name", json!("Ada")),
+            ("page", json!({ "title": "Home", "tags": ["a", "b"] })),
+            ("count", json!(3)),
+        ]);
+        assert_eq!(render("{{ name }} / {{page.title}} / {{page.tags.1}} / {{count}}", &vars), "Ada / Home / b / 3");
+        assert_eq!(render("{{page.tags}}", &vars), r#"["a","b"]"#);
+        assert_eq!(render("[{{missing}}] {{unclosed", &vars), "[] {{unclosed");
+    }
+
+    #[test]
+    fn evaluates_conditions() {
+        let vars = vars(&[("lang", json!("en")), ("text", json!("hello world")), ("list", json!([])), ("n", json!(0))]);
+        assert!(condition_holds("{{lang}} == en", &vars));
+        assert!(condition_holds("'{{lang}}' != \"de\"", &vars));
+        assert!(condition_holds("{{text}} contains world", &vars));
+        assert!(!condition_holds("{{text}} contains moon", &vars));
+        assert!(condition_holds("{{lang}}", &vars));
+        assert!(!condition_holds("{{list}}", &vars));
+        assert!(!condition_holds("{{n}}", &vars));
+        assert!(!condition_holds("{{missing}}", &vars));
+    }
+
+    #[test]
+    fn operators_in_values_do_not_change_conditions() {
+        let vars = vars(&[("reply", json!("a == a")), ("other", json!("x != y"))]);
+        // Rendered first, this would have read as `a == a == ok`
+        assert!(!condition_holds("{{reply}} == ok", &vars));
+        assert!(condition_holds("{{reply}} == a == a", &vars));
+        // A bare value holding an operator is still just a value
+        assert!(condition_holds("{{other}}", &vars));
+        assert!(condition_holds("{{other}} contains y", &vars));
+    }
+}